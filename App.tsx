@@ -2,7 +2,7 @@ import React, { Suspense, useEffect, useState, useCallback } from "react";
 import Sidebar from "./components/Sidebar";
 import TopBar from "./components/TopBar";
 import MainView from "./components/MainView";
-import { VkNode } from "./types";
+import { ConflictPolicy, VkNode } from "./types";
 import { TranslationProvider } from "./i18n";
 import { DEFAULT_DOWNLOAD_PATH, UI } from "./utils/constants";
 import { idbDel, idbGet, idbSet, migrateLocalStorageJsonToIdb } from "./utils/storage";
@@ -34,6 +34,11 @@ const App: React.FC = () => {
   const [vkGroupId, setVkGroupId] = useState(() => localStorage.getItem("vk_group_id") || "203785966");
   const [vkTopicId, setVkTopicId] = useState(() => localStorage.getItem("vk_topic_id") || "47515406");
   const [downloadPath, setDownloadPath] = useState(() => localStorage.getItem("vk_download_path") || DEFAULT_DOWNLOAD_PATH);
+  const [writeComicInfo, setWriteComicInfo] = useState(() => localStorage.getItem("vk_write_comic_info") === "true");
+  const [namingTemplate, setNamingTemplate] = useState(() => localStorage.getItem("vk_naming_template") || "");
+  const [conflictPolicy, setConflictPolicy] = useState<ConflictPolicy>(
+    () => (localStorage.getItem("vk_conflict_policy") as ConflictPolicy) || "verify",
+  );
   const [hasFullSynced, setHasFullSynced] = useState(() => localStorage.getItem("vk_has_full_synced") === "true");
   const [isSettingsLoaded, setIsSettingsLoaded] = useState(false);
 
@@ -60,6 +65,14 @@ const App: React.FC = () => {
             setDownloadPath(settings.vk_download_path);
             localStorage.setItem("vk_download_path", settings.vk_download_path);
           }
+          setWriteComicInfo(Boolean(settings.write_comic_info));
+          localStorage.setItem("vk_write_comic_info", String(Boolean(settings.write_comic_info)));
+          setNamingTemplate(settings.naming_template || "");
+          localStorage.setItem("vk_naming_template", settings.naming_template || "");
+          if (settings.conflict_policy) {
+            setConflictPolicy(settings.conflict_policy);
+            localStorage.setItem("vk_conflict_policy", settings.conflict_policy);
+          }
         }
       } catch (e) {
         console.error("Failed to load settings from Tauri:", e);
@@ -80,13 +93,16 @@ const App: React.FC = () => {
           vk_group_id: vkGroupId,
           vk_topic_id: vkTopicId,
           vk_download_path: downloadPath,
+          write_comic_info: writeComicInfo,
+          naming_template: namingTemplate,
+          conflict_policy: conflictPolicy,
         });
       } catch (e) {
         console.error("Failed to save settings to Tauri:", e);
       }
     };
     save();
-  }, [vkToken, vkGroupId, vkTopicId, downloadPath, isSettingsLoaded]);
+  }, [vkToken, vkGroupId, vkTopicId, downloadPath, writeComicInfo, namingTemplate, conflictPolicy, isSettingsLoaded]);
 
   // Sync Logic
   const [syncedData, setSyncedData] = useState<VkNode[] | null>(null);
@@ -95,7 +111,7 @@ const App: React.FC = () => {
 
   // --- CUSTOM HOOKS ---
   const update = useAppUpdate();
-  const downloads = useDownloads(downloadPath, vkToken);
+  const downloads = useDownloads(downloadPath, vkToken, writeComicInfo);
   const connection = useVkConnection(vkToken);
 
   // --- HANDLERS ---
@@ -120,6 +136,21 @@ const App: React.FC = () => {
     localStorage.setItem("vk_download_path", path);
   }, []);
 
+  const handleSetWriteComicInfo = useCallback((enabled: boolean) => {
+    setWriteComicInfo(enabled);
+    localStorage.setItem("vk_write_comic_info", String(enabled));
+  }, []);
+
+  const handleSetNamingTemplate = useCallback((template: string) => {
+    setNamingTemplate(template);
+    localStorage.setItem("vk_naming_template", template);
+  }, []);
+
+  const handleSetConflictPolicy = useCallback((policy: ConflictPolicy) => {
+    setConflictPolicy(policy);
+    localStorage.setItem("vk_conflict_policy", policy);
+  }, []);
+
   // Persist hasFullSynced
   useEffect(() => {
     localStorage.setItem("vk_has_full_synced", String(hasFullSynced));
@@ -238,6 +269,12 @@ const App: React.FC = () => {
               setHasFullSynced={setHasFullSynced}
               downloadPath={downloadPath}
               setDownloadPath={handleSetDownloadPath}
+              writeComicInfo={writeComicInfo}
+              setWriteComicInfo={handleSetWriteComicInfo}
+              namingTemplate={namingTemplate}
+              setNamingTemplate={handleSetNamingTemplate}
+              conflictPolicy={conflictPolicy}
+              setConflictPolicy={handleSetConflictPolicy}
               onVkStatusChange={connection.setVkStatus}
               downloads={downloads.downloads}
               addDownload={downloads.addDownload}
@@ -246,6 +283,10 @@ const App: React.FC = () => {
               cancelDownload={downloads.cancelDownload}
               retryDownload={downloads.retryDownload}
               clearDownloads={downloads.clearDownloads}
+              lowDiskSpace={downloads.lowDiskSpace}
+              resumeQueue={downloads.resumeQueue}
+              queueState={downloads.queueState}
+              moveDownloadToTop={downloads.moveDownloadToTop}
             />
           </div>
 
@@ -28,6 +28,7 @@ import {
   fetchFolderTreeUpToDepth,
   performPassiveSync,
   tauriShell,
+  tauriVk,
 } from "../lib/tauri";
 import { normalizeText } from "../utils/text";
 import { LIMITS } from "../utils/constants";
@@ -234,6 +235,14 @@ const BrowserFileItem = React.memo(({
             >
               {displayTitle}
             </h3>
+            {node.duplicateOf && !isCompleted && (
+              <span
+                className="text-[10px] font-bold px-1.5 py-0.5 rounded border border-amber-700/60 text-amber-400 bg-amber-900/20 uppercase flex-shrink-0"
+                title={node.duplicateOf.parentTitle ?? undefined}
+              >
+                {t.library.duplicate}
+              </span>
+            )}
             {!showProgress && !isCompleted && (
               <span className="text-[10px] font-bold px-1.5 py-0.5 rounded border border-slate-700 text-slate-400 bg-slate-800/50 uppercase flex-shrink-0">
                 {node.extension || "FILE"}
@@ -351,7 +360,7 @@ interface BrowserViewProps {
   searchQuery: string;
   setSearchQuery: (q: string) => void;
   onVkStatusChange: (status: VkConnectionStatus) => void;
-  addDownload: (node: VkNode, subFolder?: string) => void;
+  addDownload: (node: VkNode, subFolder?: string, ancestors?: string[]) => void;
   downloads: DownloadItem[];
   pauseDownload: (id: string) => void;
   resumeDownload: (id: string) => void;
@@ -553,9 +562,17 @@ const BrowserView: React.FC<BrowserViewProps> = ({
   // lors des mises à jour fréquentes de `downloads` (barres de progression).
   const navigateTo = React.useCallback(
     async (node: VkNode) => {
+      // Liens externes (hébergeurs, vk.cc non résolus) : on les ouvre dans le navigateur
+      if (node.isExternal && node.url) {
+        tauriShell.openExternal(node.url).catch(() => {});
+        return;
+      }
+
       // Pour les fichiers, on lance le téléchargement SANS toucher à la recherche
       if (node.type === "file" && node.url) {
-        addDownload(node);
+        // Hors recherche, le chemin affiché est bien celui du document (modèle de nommage)
+        const ancestors = isSearching ? undefined : navPath.map((n) => getDisplayTitle(n, language));
+        addDownload(node, undefined, ancestors);
         return;
       }
 
@@ -622,7 +639,15 @@ const BrowserView: React.FC<BrowserViewProps> = ({
             });
 
           if (syncedData) {
-            setSyncedData(updateTree(syncedData));
+            let tree = updateTree(syncedData);
+            try {
+              // Les documents chargés peuvent déjà exister dans un autre topic
+              tree = (await tauriVk.markDuplicates(tree)).tree;
+              finalNode = resolvePath(tree, node.id)?.at(-1) ?? finalNode;
+            } catch (err) {
+              console.error(err);
+            }
+            setSyncedData(tree);
           }
 
           if (initialNewPath) {
@@ -644,7 +669,7 @@ const BrowserView: React.FC<BrowserViewProps> = ({
         }
       }
     },
-    [addDownload, isSearching, setSearchQuery, syncedData, setSyncedData, vkToken],
+    [addDownload, isSearching, language, navPath, setSearchQuery, syncedData, setSyncedData, vkToken],
   );
 
   const navigateUp = React.useCallback((index?: number) => {
@@ -688,10 +713,11 @@ const BrowserView: React.FC<BrowserViewProps> = ({
       const subFolder = currentFolder
         ? getDisplayTitle(currentFolder, language)
         : undefined;
+      const ancestors = navPath.map((n) => getDisplayTitle(n, language));
 
       fileNodes.forEach((node) => {
         const d = downloadsById.get(node.id);
-        if (!d || d.status !== "completed") addDownload(node, subFolder);
+        if (!d || d.status !== "completed") addDownload(node, subFolder, ancestors);
       });
     }
   };
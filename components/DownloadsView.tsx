@@ -9,11 +9,13 @@ import {
   Folder,
   RefreshCw,
   Trash2,
+  AlertTriangle,
+  ChevronUp,
 } from "./Icons";
 import { tauriFs } from "../lib/tauri";
 import { useTranslation, Translations } from "../i18n";
-import { DownloadItem, VkNode } from "../types";
-import { formatDateISO } from "../utils/formatters";
+import { DiskSpace, DownloadItem, LowDiskSpace, QueueState, VkNode } from "../types";
+import { formatBytes, formatDateISO } from "../utils/formatters";
 import { extractVolumeLabel } from "../utils/text";
 
 interface DownloadsViewProps {
@@ -25,6 +27,10 @@ interface DownloadsViewProps {
   syncedData: VkNode[] | null;
   downloadPath?: string;
   clearDownloads: () => void;
+  lowDiskSpace: LowDiskSpace | null;
+  resumeQueue: () => void;
+  queueState: QueueState | null;
+  moveDownloadToTop: (id: string) => void;
 }
 
 // Nombre d'items chargés initialement et à chaque scroll
@@ -38,6 +44,7 @@ interface RowProps {
   cancelDownload: (id: string) => void;
   retryDownload: (id: string) => void;
   openFolder: (path?: string) => void;
+  moveToTop?: (id: string) => void; // Seulement pour les tâches en attente dans la file
 }
 
 const DownloadRow = React.memo(({
@@ -47,7 +54,8 @@ const DownloadRow = React.memo(({
   resumeDownload,
   cancelDownload,
   retryDownload,
-  openFolder
+  openFolder,
+  moveToTop
 }: RowProps) => {
   const isCompleted = d.status === "completed";
   const isDownloading = d.status === "downloading";
@@ -130,6 +138,11 @@ const DownloadRow = React.memo(({
                   <Pause size={16} />
                 </button>
               )}
+              {moveToTop && (
+                <button onClick={() => moveToTop(d.id)} className="p-1.5 hover:bg-slate-700 rounded text-slate-300" title={t.downloads.moveToTop}>
+                  <ChevronUp size={16} />
+                </button>
+              )}
               <button onClick={() => cancelDownload(d.id)} className="p-1.5 hover:bg-rose-900/30 rounded text-rose-400" title={t.tooltips.cancel}>
                 <X size={16} />
               </button>
@@ -152,7 +165,8 @@ const DownloadCard = React.memo(({
   resumeDownload,
   cancelDownload,
   retryDownload,
-  openFolder
+  openFolder,
+  moveToTop
 }: RowProps) => {
   const isCompleted = d.status === "completed";
   const isDownloading = d.status === "downloading";
@@ -221,6 +235,12 @@ const DownloadCard = React.memo(({
               {isPaused ? <Play size={14} /> : <Pause size={14} />}
               <span>{isPaused ? t.tooltips.resume : t.tooltips.pause}</span>
             </button>
+            {moveToTop && (
+              <button onClick={() => moveToTop(d.id)} className="flex-1 min-w-[120px] px-3 py-2 rounded-lg bg-slate-800 text-slate-200 border border-slate-700/50 text-sm font-semibold flex items-center justify-center gap-2">
+                <ChevronUp size={14} />
+                <span>{t.downloads.moveToTop}</span>
+              </button>
+            )}
             <button onClick={() => cancelDownload(d.id)} className="flex-1 min-w-[100px] px-3 py-2 rounded-lg bg-rose-900/40 text-rose-300 border border-rose-900/60 text-sm font-semibold flex items-center justify-center gap-2">
               <X size={14} />
               <span>{t.tooltips.cancel}</span>
@@ -246,9 +266,14 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
   syncedData,
   downloadPath,
   clearDownloads,
+  lowDiskSpace,
+  resumeQueue,
+  queueState,
+  moveDownloadToTop,
 }) => {
   const { t } = useTranslation();
   const [visibleCount, setVisibleCount] = useState(ITEMS_PER_BATCH);
+  const [diskSpace, setDiskSpace] = useState<DiskSpace | null>(null);
   const scrollContainerRef = useRef<HTMLDivElement>(null);
 
   const indexedCount = useMemo(() => {
@@ -275,6 +300,17 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
     return { downloaded, inProgress };
   }, [downloads]);
 
+  // Espace libre du volume, relu à chaque téléchargement terminé
+  useEffect(() => {
+    if (!downloadPath) return;
+    tauriFs.getDiskSpace().then(setDiskSpace).catch(() => setDiskSpace(null));
+  }, [downloadPath, downloadCounts.downloaded, lowDiskSpace]);
+
+  const queueOrder = useMemo(
+    () => new Map((queueState?.queued ?? []).map((item, index) => [item.id, index])),
+    [queueState],
+  );
+
   // Pré-calcul des données triées avec valeurs formatées (évite les recalculs dans le render)
   const sortedDownloads = useMemo((): DownloadItem[] => {
     const statusPriority: Record<DownloadItem["status"], number> = {
@@ -295,6 +331,11 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
           return priorityA - priorityB;
         }
 
+        // En attente : dans l'ordre de démarrage de la file du backend
+        if (a.status === "pending" && queueOrder.has(a.id) && queueOrder.has(b.id)) {
+          return queueOrder.get(a.id)! - queueOrder.get(b.id)!;
+        }
+
         // Tri par date UNIQUEMENT pour 'completed' et 'canceled'
         if (["completed", "canceled"].includes(a.status)) {
           const dateA = a.createdAt ? new Date(a.createdAt).getTime() : 0;
@@ -304,7 +345,7 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
 
         return 0; // Garde l'ordre original pour les autres statuts
       });
-  }, [downloads]);
+  }, [downloads, queueOrder]);
 
   // Items visibles (chargement progressif)
   const visibleDownloads = useMemo(() => {
@@ -389,11 +430,36 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
             </div>
           </div>
 
+          {lowDiskSpace && (
+            <div className="mb-6 flex flex-wrap items-center gap-3 bg-amber-500/10 border border-amber-500/30 rounded-xl p-4 text-amber-300 text-sm">
+              <AlertTriangle size={18} />
+              <span className="flex-1 min-w-[200px]">
+                {t.downloads.lowSpace
+                  .replace("{available}", formatBytes(lowDiskSpace.available) ?? "0")
+                  .replace("{needed}", formatBytes(lowDiskSpace.needed) ?? "0")}
+              </span>
+              <button
+                onClick={resumeQueue}
+                className="flex items-center gap-2 px-4 py-2 bg-amber-500/20 hover:bg-amber-500/30 rounded-lg transition-colors font-medium"
+              >
+                <Play size={14} />
+                <span>{t.downloads.resumeQueue}</span>
+              </button>
+            </div>
+          )}
+
           {/* Header */}
           <div className="flex items-center justify-between mb-6">
             <div className="flex items-center gap-3">
               <Download className="text-slate-200" size={24} />
               <h2 className="text-2xl font-bold text-white tracking-tight">{t.downloads.title}</h2>
+              {diskSpace && (
+                <span className="text-xs text-slate-500 font-mono">
+                  {t.downloads.diskFree
+                    .replace("{free}", formatBytes(diskSpace.available) ?? "0")
+                    .replace("{total}", formatBytes(diskSpace.total) ?? "0")}
+                </span>
+              )}
             </div>
             {downloads.length > 0 && (
               <button
@@ -452,6 +518,7 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
                           cancelDownload={cancelDownload}
                           retryDownload={retryDownload}
                           openFolder={openFolder}
+                          moveToTop={queueOrder.has(d.id) ? moveDownloadToTop : undefined}
                         />
                       ))}
                     </tbody>
@@ -470,6 +537,7 @@ const DownloadsView: React.FC<DownloadsViewProps> = ({
                       cancelDownload={cancelDownload}
                       retryDownload={retryDownload}
                       openFolder={openFolder}
+                      moveToTop={queueOrder.has(d.id) ? moveDownloadToTop : undefined}
                     />
                   ))}
                 </div>
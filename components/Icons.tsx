@@ -20,6 +20,7 @@ import {
   DownloadCloud,
   ChevronLeft,
   ChevronDown,
+  ChevronUp,
   FileText,
   BarChart,
   Check,
@@ -52,6 +53,7 @@ export {
   DownloadCloud,
   ChevronLeft,
   ChevronDown,
+  ChevronUp,
   FileText,
   BarChart,
   Check,
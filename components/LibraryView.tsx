@@ -23,6 +23,12 @@ interface LibraryViewProps {
   onNavigateToSettings?: () => void;
 }
 
+const THUMBNAIL_EXTENSIONS = ["cbz", "zip", "cbr", "rar", "cb7", "7z", "pdf"];
+
+const hasThumbnail = (entry: FsEntry) =>
+  !entry.isDirectory &&
+  THUMBNAIL_EXTENSIONS.includes(entry.name.split(".").pop()?.toLowerCase() ?? "");
+
 const normalizePath = (value: string) => value.replace(/\\/g, "/");
 
 const joinPaths = (base: string, segment: string) => {
@@ -108,6 +114,8 @@ const LibraryItem = React.memo<LibraryItemProps>(({
   openFileLabel
 }) => {
   const isFolder = entry.isDirectory;
+  const [thumbnailFailed, setThumbnailFailed] = useState(false);
+  const showThumbnail = hasThumbnail(entry) && !thumbnailFailed;
   const handleDoubleClick = useCallback(() => onNavigate(entry), [onNavigate, entry]);
   const handleIconClick = useCallback(() => onNavigate(entry), [onNavigate, entry]);
   const handleActionClick = useCallback(() => isFolder ? onNavigate(entry) : onOpen(entry), [isFolder, onNavigate, onOpen, entry]);
@@ -117,6 +125,15 @@ const LibraryItem = React.memo<LibraryItemProps>(({
       onDoubleClick={handleDoubleClick}
       className="bg-[#0f1523] rounded-xl border border-slate-800/60 p-5 shadow-sm flex flex-col justify-between hover:border-blue-500/40 transition-all duration-200 cursor-pointer"
     >
+      {showThumbnail && (
+        <img
+          src={tauriLibrary.thumbnailUrl(entry.path)}
+          alt={entry.name}
+          loading="lazy"
+          onError={() => setThumbnailFailed(true)}
+          className="w-full aspect-[2/3] object-cover rounded-lg mb-4 bg-slate-800"
+        />
+      )}
       <div className="flex items-center gap-3 mb-4">
         <button
           type="button"
@@ -160,7 +177,7 @@ const LibraryItem = React.memo<LibraryItemProps>(({
   );
 });
 
-import { tauriFs } from "../lib/tauri";
+import { tauriEvents, tauriFs, tauriLibrary } from "../lib/tauri";
 
 // Parcourt le dossier de téléchargement local via Rust
 const LibraryView: React.FC<LibraryViewProps> = ({
@@ -200,6 +217,17 @@ const LibraryView: React.FC<LibraryViewProps> = ({
     }
   }, [effectivePath, loadPath]);
 
+  // Fichiers ajoutés / supprimés hors de l'app : on recharge le dossier affiché
+  useEffect(() => {
+    if (!currentPath) return;
+    const unlisten = tauriEvents.onLibraryChanged(() => {
+      loadPath(currentPath);
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [currentPath, loadPath]);
+
   const breadcrumbs = useMemo(() => {
     if (!effectivePath || !currentPath) return [];
     return buildBreadcrumbs(effectivePath, currentPath, t.library.rootLabel);
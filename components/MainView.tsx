@@ -4,7 +4,7 @@ import BrowserView from "./BrowserView";
 const SettingsView = React.lazy(() => import("./SettingsView"));
 const DownloadsView = React.lazy(() => import("./DownloadsView"));
 const LibraryView = React.lazy(() => import("./LibraryView"));
-import { VkNode, VkConnectionStatus, DownloadItem } from "../types";
+import { ConflictPolicy, VkNode, VkConnectionStatus, DownloadItem, LowDiskSpace, QueueState } from "../types";
 
 interface MainViewProps {
   searchQuery: string;
@@ -23,14 +23,24 @@ interface MainViewProps {
   setHasFullSynced: (hasSynced: boolean) => void;
   downloadPath: string;
   setDownloadPath: (path: string) => void;
+  writeComicInfo: boolean;
+  setWriteComicInfo: (enabled: boolean) => void;
+  namingTemplate: string;
+  setNamingTemplate: (template: string) => void;
+  conflictPolicy: ConflictPolicy;
+  setConflictPolicy: (policy: ConflictPolicy) => void;
   onVkStatusChange: (status: VkConnectionStatus) => void;
   downloads: DownloadItem[];
-  addDownload: (node: VkNode, subFolder?: string) => void;
+  addDownload: (node: VkNode, subFolder?: string, ancestors?: string[]) => void;
   pauseDownload: (id: string) => void;
   resumeDownload: (id: string) => void;
   cancelDownload: (id: string) => void;
   retryDownload: (id: string) => void;
   clearDownloads: () => void;
+  lowDiskSpace: LowDiskSpace | null;
+  resumeQueue: () => void;
+  queueState: QueueState | null;
+  moveDownloadToTop: (id: string) => void;
 }
 
 // Ce composant agit comme un "Routeur".
@@ -52,6 +62,12 @@ const MainView: React.FC<MainViewProps> = ({
   setHasFullSynced,
   downloadPath,
   setDownloadPath,
+  writeComicInfo,
+  setWriteComicInfo,
+  namingTemplate,
+  setNamingTemplate,
+  conflictPolicy,
+  setConflictPolicy,
   onVkStatusChange,
   downloads,
   addDownload,
@@ -60,6 +76,10 @@ const MainView: React.FC<MainViewProps> = ({
   cancelDownload,
   retryDownload,
   clearDownloads,
+  lowDiskSpace,
+  resumeQueue,
+  queueState,
+  moveDownloadToTop,
 }) => {
   const [navPath, setNavPath] = useState<VkNode[]>([]);
 
@@ -89,6 +109,12 @@ const MainView: React.FC<MainViewProps> = ({
                   setVkTopicId={setVkTopicId}
                   downloadPath={downloadPath}
                   setDownloadPath={setDownloadPath}
+                  writeComicInfo={writeComicInfo}
+                  setWriteComicInfo={setWriteComicInfo}
+                  namingTemplate={namingTemplate}
+                  setNamingTemplate={setNamingTemplate}
+                  conflictPolicy={conflictPolicy}
+                  setConflictPolicy={setConflictPolicy}
                   onResetDatabase={() => {
                     setSyncedData(null);
                     setHasFullSynced(false);
@@ -106,6 +132,10 @@ const MainView: React.FC<MainViewProps> = ({
                   retryDownload={retryDownload}
                   downloadPath={downloadPath}
                   clearDownloads={clearDownloads}
+                  lowDiskSpace={lowDiskSpace}
+                  resumeQueue={resumeQueue}
+                  queueState={queueState}
+                  moveDownloadToTop={moveDownloadToTop}
                   syncedData={syncedData}
                 />
               );
@@ -1,13 +1,20 @@
 import React, { useEffect, useState } from "react";
 import { Save, Folder, ChevronDown, Trash2, AlertCircle } from "./Icons";
 import { useTranslation, Language } from "../i18n";
-import { tauriDialog, tauriShell } from "../lib/tauri";
+import { tauriDialog, tauriFs, tauriShell } from "../lib/tauri";
+import { ConflictPolicy } from "../types";
 
 interface SettingsViewProps {
   vkToken: string;
   setVkToken: (token: string) => void;
   downloadPath: string;
   setDownloadPath: (path: string) => void;
+  writeComicInfo: boolean;
+  setWriteComicInfo: (enabled: boolean) => void;
+  namingTemplate: string;
+  setNamingTemplate: (template: string) => void;
+  conflictPolicy: ConflictPolicy;
+  setConflictPolicy: (policy: ConflictPolicy) => void;
 }
 
 const SettingsView: React.FC<
@@ -23,6 +30,12 @@ const SettingsView: React.FC<
   setVkToken,
   downloadPath,
   setDownloadPath,
+  writeComicInfo,
+  setWriteComicInfo,
+  namingTemplate,
+  setNamingTemplate,
+  conflictPolicy,
+  setConflictPolicy,
   vkGroupId,
   setVkGroupId,
   vkTopicId,
@@ -35,6 +48,8 @@ const SettingsView: React.FC<
     const [localGroupId, setLocalGroupId] = useState(vkGroupId);
     const [localTopicId, setLocalTopicId] = useState(vkTopicId);
     const [localDownloadPath, setLocalDownloadPath] = useState(downloadPath);
+    const [localNamingTemplate, setLocalNamingTemplate] = useState(namingTemplate);
+    const [namingPreview, setNamingPreview] = useState<{ path?: string; error?: string }>({});
     const [isSaved, setIsSaved] = useState(false);
     const [showResetConfirm, setShowResetConfirm] = useState(false);
 
@@ -50,6 +65,28 @@ const SettingsView: React.FC<
       setLocalGroupId(vkGroupId);
     }, [vkGroupId]);
 
+    useEffect(() => {
+      setLocalNamingTemplate(namingTemplate);
+    }, [namingTemplate]);
+
+    // Aperçu du modèle sur un exemple, calculé par le backend (mêmes règles que les téléchargements)
+    useEffect(() => {
+      if (!localNamingTemplate.trim()) {
+        setNamingPreview({});
+        return;
+      }
+      let cancelled = false;
+      tauriFs
+        .resolveDownloadPath(
+          localDownloadPath, "Blacksad T03 - Âme rouge (2005) [FR]", "cbz", ["BD", "Blacksad"], localNamingTemplate,
+        )
+        .then((resolved) => !cancelled && setNamingPreview({ path: resolved?.relativePath }))
+        .catch((e) => !cancelled && setNamingPreview({ error: String(e) }));
+      return () => {
+        cancelled = true;
+      };
+    }, [localNamingTemplate, localDownloadPath]);
+
     useEffect(() => {
       setLocalTopicId(vkTopicId);
     }, [vkTopicId]);
@@ -59,6 +96,7 @@ const SettingsView: React.FC<
       setVkGroupId(localGroupId.trim());
       setVkTopicId(localTopicId.trim());
       setDownloadPath(localDownloadPath);
+      setNamingTemplate(localNamingTemplate.trim());
       setIsSaved(true);
       setTimeout(() => setIsSaved(false), 2000);
     };
@@ -258,6 +296,80 @@ const SettingsView: React.FC<
                     </button>
                   </div>
                 </div>
+
+                {/* Modèle de nommage (vide : dossier VK / titre du document) */}
+                <div>
+                  <label className="block text-sm font-medium text-slate-400 mb-2.5">
+                    {t.settings.namingTemplate}
+                  </label>
+                  <input
+                    type="text"
+                    value={localNamingTemplate}
+                    onChange={(e) => {
+                      setLocalNamingTemplate(e.target.value);
+                      setIsSaved(false);
+                    }}
+                    placeholder="{category}/{series}/{series}< - T{volume:02}>< ({year})>.{ext}"
+                    className="w-full bg-[#161f32] text-slate-200 text-sm rounded-lg px-4 py-3 focus:outline-none focus:ring-1 focus:ring-blue-500 border border-slate-700/50 font-mono"
+                  />
+                  <p className="text-slate-500 text-xs leading-relaxed mt-2">
+                    {t.settings.namingTemplateDescription}
+                  </p>
+                  {namingPreview.path && (
+                    <p className="text-slate-300 text-xs font-mono mt-1 truncate">
+                      {t.settings.namingTemplatePreview} {namingPreview.path}
+                    </p>
+                  )}
+                  {namingPreview.error && (
+                    <p className="text-rose-400 text-xs mt-1">{namingPreview.error}</p>
+                  )}
+                </div>
+
+                {/* Fichier déjà présent (sauvegarde auto) */}
+                <div>
+                  <label className="block text-sm font-medium text-slate-400 mb-2.5">
+                    {t.settings.conflictPolicy}
+                  </label>
+                  <div className="relative">
+                    <select
+                      value={conflictPolicy}
+                      onChange={(e) => setConflictPolicy(e.target.value as ConflictPolicy)}
+                      className="w-full bg-[#161f32] text-slate-200 text-sm rounded-lg px-4 py-3 focus:outline-none focus:ring-1 focus:ring-blue-500 border border-slate-700/50 appearance-none cursor-pointer"
+                    >
+                      <option value="verify">{t.settings.conflictPolicies.verify}</option>
+                      <option value="skip">{t.settings.conflictPolicies.skip}</option>
+                      <option value="resume">{t.settings.conflictPolicies.resume}</option>
+                      <option value="overwrite">{t.settings.conflictPolicies.overwrite}</option>
+                      <option value="rename">{t.settings.conflictPolicies.rename}</option>
+                    </select>
+                    <div className="absolute right-4 top-1/2 -translate-y-1/2 pointer-events-none text-slate-500">
+                      <ChevronDown size={18} />
+                    </div>
+                  </div>
+                </div>
+
+                {/* ComicInfo.xml (sauvegarde auto, comme le dossier) */}
+                <div className="flex items-center justify-between gap-6">
+                  <div className="flex-1">
+                    <h3 className="text-sm font-medium text-slate-200 mb-1">
+                      {t.settings.writeComicInfo}
+                    </h3>
+                    <p className="text-slate-400 text-xs leading-relaxed">
+                      {t.settings.writeComicInfoDescription}
+                    </p>
+                  </div>
+                  <button
+                    type="button"
+                    role="switch"
+                    aria-checked={writeComicInfo}
+                    onClick={() => setWriteComicInfo(!writeComicInfo)}
+                    className={`relative w-11 h-6 rounded-full transition-colors ${writeComicInfo ? "bg-blue-600" : "bg-slate-700"}`}
+                  >
+                    <span
+                      className={`absolute top-0.5 left-0.5 w-5 h-5 rounded-full bg-white transition-transform ${writeComicInfo ? "translate-x-5" : ""}`}
+                    />
+                  </button>
+                </div>
               </div>
             </div>
 
@@ -1,18 +1,20 @@
 
 import { useState, useEffect, useRef, useCallback } from "react";
-import { DownloadItem, VkNode } from "../types";
+import { ComicInfoSource, DownloadItem, DownloadRequest, LowDiskSpace, QueueFilter, QueueState, VkNode } from "../types";
 import { DEFAULT_DOWNLOAD_PATH, UI } from "../utils/constants";
 import { formatBytes, formatSpeed } from "../utils/formatters";
 import { idbDel, idbGet, idbGetByPrefix, idbSet, migrateLocalStorageJsonToIdb } from "../utils/storage";
 import { tauriFs, tauriEvents } from "../lib/tauri";
 
-export const useDownloads = (downloadPath: string, vkToken?: string) => {
+export const useDownloads = (downloadPath: string, vkToken?: string, writeComicInfo = false) => {
     const [downloads, setDownloads] = useState<DownloadItem[]>([]);
     const [downloadsHydrated, setDownloadsHydrated] = useState(false);
     const downloadsRef = useRef(downloads);
     const missingDownloadPathAlertedRef = useRef(false);
     const lastUpdateRef = useRef<number>(0);
     const enqueuedPendingDownloadsRef = useRef<Set<string>>(new Set());
+    const [lowDiskSpace, setLowDiskSpace] = useState<LowDiskSpace | null>(null);
+    const [queueState, setQueueState] = useState<QueueState | null>(null);
 
     // Helper to keep ref in sync
     useEffect(() => {
@@ -69,7 +71,7 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
     }, []);
 
     // 2. Add Download
-    const addDownload = useCallback((node: VkNode, subFolder?: string) => {
+    const addDownload = useCallback((node: VkNode, subFolder?: string, ancestors?: string[]) => {
         if (!node.url && (!node.vkOwnerId || !node.id)) return;
 
         if (!downloadPath || downloadPath === DEFAULT_DOWNLOAD_PATH) {
@@ -114,10 +116,19 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
                 status: "pending", extension: node.extension, speed: "0 MB/s",
                 createdAt: new Date().toISOString(),
                 size: existing && existing.size ? existing.size : formattedSize,
+                sizeBytes: node.sizeBytes,
                 path: existing && (existing as any).path ? (existing as any).path : undefined,
                 vkOwnerId: node.vkOwnerId,
                 vkDocId: node.id.replace("doc_", ""),
                 vkAccessKey: node.vkAccessKey,
+                vkAlbumId: node.vkAlbumId,
+                parts: node.parts?.filter((p) => !!p.url).map((p) => ({
+                    url: p.url!,
+                    fileName: p.extension && !p.title.toLowerCase().endsWith(`.${p.extension.toLowerCase()}`)
+                        ? `${p.title}.${p.extension.toLowerCase()}`
+                        : p.title,
+                    size: p.sizeBytes,
+                })),
             };
 
             if (existing) {
@@ -126,7 +137,7 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
                 return [newDownload, ...newList];
             }
 
-            const itemWithSubFolder = { ...newDownload, subFolder };
+            const itemWithSubFolder = { ...newDownload, subFolder, ancestors };
             return [itemWithSubFolder, ...prev];
         });
     }, [downloadPath]);
@@ -139,6 +150,19 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
 
         const snapshot = downloadsRef.current;
         const enqueued = enqueuedPendingDownloadsRef.current;
+        // Documents simples : envoyés ensemble une fois leurs chemins résolus
+        const batch: Promise<DownloadRequest | null>[] = [];
+
+        const markFailed = (ids: string[]) => {
+            ids.forEach((id) => enqueued.delete(id));
+            setDownloads((prev) =>
+                prev.map((item) => {
+                    if (!ids.includes(item.id)) return item;
+                    if (item.status !== "pending") return item;
+                    return { ...item, status: "canceled", speed: "Error" };
+                })
+            );
+        };
 
         for (let index = snapshot.length - 1; index >= 0; index--) {
             const d = snapshot[index];
@@ -170,23 +194,83 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
                 }
             }
 
+            // ComicInfo.xml : série = dossier parent, groupe = propriétaire négatif du document
+            let comicInfo: ComicInfoSource | undefined;
+            if (writeComicInfo) {
+                const web = d.vkOwnerId && d.vkDocId
+                    ? `https://vk.com/doc${d.vkOwnerId}_${d.vkDocId}`
+                    : d.vkOwnerId && d.vkAlbumId
+                        ? `https://vk.com/album${d.vkOwnerId}_${d.vkAlbumId}`
+                        : d.url;
+                comicInfo = {
+                    series: d.subFolder,
+                    title: fileName,
+                    web,
+                    group: d.vkOwnerId?.startsWith("-") ? d.vkOwnerId.slice(1) : undefined,
+                };
+            }
+
+            // Modèle de nommage configuré : le backend décide du dossier et du nom
+            const resolveTarget = async () => {
+                const resolved = await tauriFs.resolveDownloadPath(
+                    downloadPath, fileName, d.extension, d.ancestors ?? (d.subFolder ? [d.subFolder] : []),
+                );
+                return {
+                    targetDirectory: resolved ? resolved.directory : targetPath,
+                    targetName: resolved ? resolved.fileName : fileName,
+                };
+            };
+
+            if (!(d.parts && d.parts.length > 0) && !(d.vkAlbumId && d.vkOwnerId)) {
+                // Le backend saute le téléchargement si ce document est déjà dans une autre série
+                const docKey = d.vkOwnerId && d.vkDocId ? `${d.vkOwnerId}_${d.vkDocId}` : undefined;
+                batch.push(
+                    resolveTarget()
+                        .then(({ targetDirectory, targetName }) => ({
+                            id: d.id, url: d.url!, directory: targetDirectory, fileName: targetName,
+                            token: vkToken, docKey, size: d.sizeBytes, comicInfo,
+                        }))
+                        .catch(() => {
+                            markFailed([d.id]);
+                            return null;
+                        }),
+                );
+                continue;
+            }
+
             const enqueue = async () => {
                 try {
-                    await tauriFs.queueDownload(d.id, d.url!, targetPath, fileName, vkToken);
+                    const { targetDirectory, targetName } = await resolveTarget();
+
+                    if (d.parts && d.parts.length > 0) {
+                        // Archive découpée : le backend ne valide qu'une fois toutes les parties présentes
+                        await tauriFs.queueMultipartDownload(d.id, d.parts, targetDirectory, vkToken);
+                    } else {
+                        // Album photo : le backend télécharge les images et les empaquette en CBZ
+                        if (!vkToken) throw new Error("A VK token is required to download an album");
+                        await tauriFs.queueAlbumDownload(
+                            d.id, d.vkOwnerId!, d.vkAlbumId!, targetDirectory, targetName, vkToken, comicInfo,
+                        );
+                    }
                 } catch {
-                    enqueued.delete(d.id);
-                    setDownloads((prev) =>
-                        prev.map((item) => {
-                            if (item.id !== d.id) return item;
-                            if (item.status !== "pending") return item;
-                            return { ...item, status: "canceled", speed: "Error" };
-                        })
-                    );
+                    markFailed([d.id]);
                 }
             };
             void enqueue();
         }
-    }, [downloadStatusKey, downloadsHydrated, downloadPath]);
+
+        if (batch.length > 0) {
+            void Promise.all(batch).then(async (resolved) => {
+                const requests = resolved.filter((r): r is DownloadRequest => r !== null);
+                if (requests.length === 0) return;
+                try {
+                    await tauriFs.queueDownloads(requests);
+                } catch {
+                    markFailed(requests.map((r) => r.id));
+                }
+            });
+        }
+    }, [downloadStatusKey, downloadsHydrated, downloadPath, writeComicInfo]);
 
     // 4. Progress Listeners
     useEffect(() => {
@@ -222,7 +306,7 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
 
     useEffect(() => {
         const unlisten = tauriEvents.onDownloadResult((payload: any) => {
-            const { id, ok, status, path, size } = payload || {};
+            const { id, ok, status, path, size, conflict } = payload || {};
             if (!id) return;
             const formattedSize = typeof size === "number" ? formatBytes(size) || undefined : undefined;
 
@@ -234,6 +318,8 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
                         ...(path ? { path } : {}),
                         ...(formattedSize ? { size: formattedSize } : {}),
                     };
+                    if (ok && status === "duplicate") return { ...next, status: "completed", progress: 100, speed: "Déjà présent" };
+                    if (ok && conflict === "skipped") return { ...next, status: "completed", progress: 100, speed: "Déjà présent" };
                     if (ok) return { ...next, status: "completed", speed: "0 MB/s" };
                     if (next.status === "paused" || next.status === "canceled") return next;
                     if (status === "aborted") return { ...next, status: "error", speed: "Interrompu" };
@@ -244,6 +330,32 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
         return () => { unlisten.then(f => f()); };
     }, []);
 
+    // État de la file du backend (ordre, priorités), pour l'affichage et les déplacements
+    useEffect(() => {
+        tauriFs.getQueueState().then(setQueueState).catch(() => setQueueState(null));
+        const unlisten = tauriEvents.onDownloadQueueChanged(setQueueState);
+        return () => { unlisten.then(f => f()); };
+    }, []);
+
+    // Disque presque plein : le backend remet la tâche en tête de file et met la file en pause
+    useEffect(() => {
+        const unlistenLow = tauriEvents.onDownloadLowSpace((payload) => {
+            setLowDiskSpace(payload);
+            // Déjà dans la file du backend : ne pas la renvoyer en repassant à "pending"
+            enqueuedPendingDownloadsRef.current.add(payload.id);
+            setDownloads((prev) =>
+                prev.map((d) =>
+                    d.id === payload.id ? { ...d, status: "pending", speed: "Espace disque insuffisant" } : d
+                )
+            );
+        });
+        const unlistenResumed = tauriEvents.onDownloadQueueResumed(() => setLowDiskSpace(null));
+        return () => {
+            unlistenLow.then(f => f());
+            unlistenResumed.then(f => f());
+        };
+    }, []);
+
     // 5. Persistence
     const lastPersistRef = useRef<number>(0);
     const lastPersistStatusKeyRef = useRef<string>("");
@@ -324,6 +436,29 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
         );
     }, []);
 
+    const moveDownloadToTop = useCallback((id: string) => {
+        tauriFs.moveDownload(id, { to: "top" }).catch(console.error);
+    }, []);
+
+    // Les tâches annulées par le backend passent en "canceled" ; la liste reste éditable
+    const cancelDownloads = useCallback(async (filter: QueueFilter) => {
+        try {
+            const ids = await tauriFs.cancelDownloads(filter);
+            ids.forEach((id) => enqueuedPendingDownloadsRef.current.delete(id));
+            setDownloads((prev) =>
+                prev.map((d) => (ids.includes(d.id) ? { ...d, status: "canceled", speed: "0 MB/s" } : d))
+            );
+            return ids.length;
+        } catch (e) {
+            console.error(e);
+            return 0;
+        }
+    }, []);
+
+    const resumeQueue = useCallback(() => {
+        tauriFs.resumeDownloadQueue().catch(console.error);
+    }, []);
+
     const clearDownloads = useCallback(() => {
         tauriFs.clearDownloadQueue().catch(console.error);
         enqueuedPendingDownloadsRef.current.clear();
@@ -338,5 +473,10 @@ export const useDownloads = (downloadPath: string, vkToken?: string) => {
         cancelDownload,
         retryDownload,
         clearDownloads,
+        lowDiskSpace,
+        resumeQueue,
+        queueState,
+        moveDownloadToTop,
+        cancelDownloads,
     };
 };
@@ -47,6 +47,21 @@ export const en: Translations = {
     browse: "Browse",
     folderDialogWarning:
       "Folder selection is only available in the desktop app. Please enter the path manually.",
+    namingTemplate: "Naming template",
+    namingTemplateDescription:
+      "Fields: {category}, {parent}, {series}, {title}, {volume:02}, {issue}, {number}, {year}, {language}, {ext}. \"/\" creates a folder, <...> is dropped when a field is empty. Empty: VK folder then document title.",
+    namingTemplatePreview: "Example:",
+    conflictPolicy: "When the file already exists",
+    conflictPolicies: {
+      verify: "Check the size (skip, resume or rename)",
+      skip: "Skip the download",
+      resume: "Resume from the end of the file",
+      overwrite: "Overwrite",
+      rename: "Rename (name (2).cbz)",
+    },
+    writeComicInfo: "Write ComicInfo.xml",
+    writeComicInfoDescription:
+      "Adds series, number, title, language and VK link to downloaded CBZ/ZIP files (read by Komga, Kavita...).",
 
     // Save button
     saveAll: "Save",
@@ -91,6 +106,7 @@ export const en: Translations = {
     openFolderOnVk: "view on VK",
     openFile: "Open file",
     downloadFile: "Download",
+    duplicate: "Duplicate",
     downloadAll: "Download All",
     cancelAll: "Cancel All",
     emptyFolder: "This folder is empty.",
@@ -118,6 +134,10 @@ export const en: Translations = {
     statusDownloading: "Downloading...",
     statusPending: "Pending",
     statusPaused: "Paused",
+    diskFree: "{free} free of {total}",
+    lowSpace: "Disk almost full: {available} available, {needed} needed. The queue is paused, free some space then resume.",
+    resumeQueue: "Resume queue",
+    moveToTop: "Move to top",
   },
 
   // Tooltips
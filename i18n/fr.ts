@@ -45,6 +45,21 @@ export const fr = {
     browse: "Parcourir",
     folderDialogWarning:
       "La sélection de dossier est disponible uniquement dans la version bureau. Veuillez saisir le chemin manuellement.",
+    namingTemplate: "Modèle de nommage",
+    namingTemplateDescription:
+      "Champs : {category}, {parent}, {series}, {title}, {volume:02}, {issue}, {number}, {year}, {language}, {ext}. « / » crée un dossier, <...> disparaît si un champ est vide. Vide : dossier VK puis titre du document.",
+    namingTemplatePreview: "Exemple :",
+    conflictPolicy: "Si le fichier existe déjà",
+    conflictPolicies: {
+      verify: "Vérifier la taille (ignorer, reprendre ou renommer)",
+      skip: "Ignorer le téléchargement",
+      resume: "Reprendre à la fin du fichier",
+      overwrite: "Écraser",
+      rename: "Renommer (nom (2).cbz)",
+    },
+    writeComicInfo: "Écrire ComicInfo.xml",
+    writeComicInfoDescription:
+      "Ajoute série, numéro, titre, langue et lien VK dans les CBZ/ZIP téléchargés (lu par Komga, Kavita...).",
 
     // Save button
     saveAll: "Sauvegarder",
@@ -91,6 +106,7 @@ export const fr = {
     openFolderOnVk: "Voir sur VK",
     openFile: "Ouvrir le fichier",
     downloadFile: "Télécharger",
+    duplicate: "Doublon",
     downloadAll: "Tout télécharger",
     cancelAll: "Tout annuler",
     emptyFolder: "Ce dossier est vide.",
@@ -118,6 +134,10 @@ export const fr = {
     statusDownloading: "Téléchargement...",
     statusPending: "En attente",
     statusPaused: "Pause",
+    diskFree: "{free} libres sur {total}",
+    lowSpace: "Disque presque plein : {available} disponibles, {needed} nécessaires. La file est en pause, libérez de l'espace puis reprenez.",
+    resumeQueue: "Reprendre la file",
+    moveToTop: "En premier",
   },
 
   // Tooltips
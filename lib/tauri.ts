@@ -1,9 +1,12 @@
-import { invoke } from "@tauri-apps/api/core";
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { open as openExternal } from "@tauri-apps/plugin-shell";
 import { open as selectFolder } from "@tauri-apps/plugin-dialog";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { VkNode } from "../types";
+import {
+    ArchiveCheck, BoardTopic, ComicInfoSource, ConflictPolicy, ConversionProgress, ConversionResult, DiskSpace, DownloadPart, DownloadPriority, DownloadRequest, DuplicateScan, HistoryPage, HistoryQuery, HistoryStats, HistoryStatsQuery, FolderProgress, LibraryChange, LibraryEntry, LibraryQuery, LibraryScanSummary,
+    LibrarySeries, LowDiskSpace, QueueFilter, QueueMove, QueueState, ReaderPageList, ReadingState, ResolvedPath, SeriesReport, VkNode,
+} from "../types";
 
 // --- VK API Commands ---
 export const tauriVk = {
@@ -12,10 +15,24 @@ export const tauriVk = {
         invoke<VkNode[]>("vk_fetch_root_index", { token, groupId, topicId }),
     fetchFullIndex: (token: string, groupId: string, topicId: string) =>
         invoke<VkNode[]>("vk_fetch_full_index", { token, groupId, topicId }),
-    fetchNodeContent: (token: string, groupId: string, topicId: string) =>
-        invoke<VkNode>("vk_fetch_node_content", { token, groupId, topicId }),
+    fetchNodeContent: (token: string, groupId: string, topicId: string, postId?: string) =>
+        invoke<VkNode>("vk_fetch_node_content", { token, groupId, topicId, postId }),
     refreshCounts: (token: string, groupId: string, topicIds: string[]) =>
         invoke<Record<string, number>>("vk_refresh_counts", { token, groupId, topicIds }),
+    fetchWallContent: (token: string, groupId: string) =>
+        invoke<VkNode>("vk_fetch_wall_content", { token, groupId }),
+    fetchWallPost: (token: string, ownerId: string, postId: string) =>
+        invoke<VkNode>("vk_fetch_wall_post", { token, ownerId, postId }),
+    fetchAlbums: (token: string, groupId: string) =>
+        invoke<VkNode[]>("vk_fetch_albums", { token, groupId }),
+    fetchBoardTopics: (token: string, groupId: string) =>
+        invoke<BoardTopic[]>("vk_fetch_board_topics", { token, groupId }),
+    findOrphanTopics: (token: string, groupId: string, topicId: string, tree: VkNode[]) =>
+        invoke<BoardTopic[]>("vk_find_orphan_topics", { token, groupId, topicId, tree }),
+    seriesReport: (series: VkNode, directory?: string) =>
+        invoke<SeriesReport>("vk_series_report", { series, directory }),
+    markDuplicates: (tree: VkNode[]) =>
+        invoke<DuplicateScan>("vk_mark_duplicates", { tree }),
 };
 
 // --- Filesystem Commands ---
@@ -23,10 +40,89 @@ export const tauriFs = {
     listDirectory: (path: string) => invoke<any>("fs_list_directory", { path }),
     openPath: (path: string) => invoke<void>("fs_open_path", { path }),
     revealPath: (path: string) => invoke<void>("fs_reveal_path", { path }),
-    queueDownload: (id: string, url: string, directory: string, fileName: string, token?: string) =>
-        invoke<void>("fs_queue_download", { id, url, directory, fileName, token }),
+    // Chemin donné par le modèle de nommage (null : pas de modèle, disposition historique)
+    resolveDownloadPath: (root: string, title: string, extension?: string, ancestors: string[] = [], template?: string) =>
+        invoke<ResolvedPath | null>("fs_resolve_download_path", { root, title, extension, ancestors, template }),
+    queueDownload: (
+        id: string, url: string, directory: string, fileName: string, token?: string,
+        docKey?: string, size?: number, comicInfo?: ComicInfoSource, conflictPolicy?: ConflictPolicy,
+        priority?: DownloadPriority,
+    ) =>
+        invoke<void>("fs_queue_download", {
+            id, url, directory, fileName, token, docKey, size, comicInfo, conflictPolicy, priority,
+        }),
+    // Une série entière en un seul appel (un seul événement download-queue-changed)
+    queueDownloads: (requests: DownloadRequest[]) => invoke<number>("fs_queue_downloads", { requests }),
+    queueAlbumDownload: (
+        id: string, ownerId: string, albumId: string, directory: string, fileName: string, token: string,
+        comicInfo?: ComicInfoSource, conflictPolicy?: ConflictPolicy, priority?: DownloadPriority,
+    ) =>
+        invoke<void>("fs_queue_album_download", {
+            id, ownerId, albumId, directory, fileName, token, comicInfo, conflictPolicy, priority,
+        }),
+    queueMultipartDownload: (
+        id: string, parts: DownloadPart[], directory: string, token?: string,
+        conflictPolicy?: ConflictPolicy, priority?: DownloadPriority,
+    ) =>
+        invoke<void>("fs_queue_multipart_download", { id, parts, directory, token, conflictPolicy, priority }),
     cancelDownload: (id: string) => invoke<boolean>("fs_cancel_download", { id }),
     clearDownloadQueue: () => invoke<number>("fs_clear_download_queue"),
+    getQueueState: () => invoke<QueueState>("fs_get_queue_state"),
+    moveDownload: (id: string, to: QueueMove) => invoke<boolean>("fs_move_download", { id, to }),
+    setDownloadPriority: (id: string, priority: DownloadPriority) =>
+        invoke<boolean>("fs_set_download_priority", { id, priority }),
+    cancelDownloads: (filter: QueueFilter) => invoke<string[]>("fs_cancel_downloads", { filter }),
+    getDiskSpace: () => invoke<DiskSpace>("fs_get_disk_space"),
+    resumeDownloadQueue: () => invoke<void>("fs_resume_download_queue"),
+};
+
+// --- Library Commands ---
+export const tauriLibrary = {
+    scan: (root: string) => invoke<LibraryScanSummary>("library_scan", { root }),
+    query: (query: LibraryQuery = {}) => invoke<LibraryEntry[]>("library_query", { query }),
+    series: () => invoke<LibrarySeries[]>("library_series"),
+    checkArchive: (path: string) => invoke<ArchiveCheck>("library_check_archive", { path }),
+    convertToCbz: (id: string, source: string, deleteSource = false) =>
+        invoke<void>("library_convert_to_cbz", { id, source, deleteSource }),
+    cancelConversion: (id: string) => invoke<boolean>("library_cancel_conversion", { id }),
+    watchStart: (root: string) => invoke<void>("library_watch_start", { root }),
+    watchStop: () => invoke<void>("library_watch_stop"),
+    // Miniature servie par le schéma vkomic-thumb:// (générée puis mise en cache côté Rust)
+    thumbnailUrl: (path: string) => convertFileSrc(path, "vkomic-thumb"),
+};
+
+// --- Reader (schéma vkomic-page://, fonctionne aussi sur Android) ---
+export const tauriReader = {
+    pagesUrl: (path: string) => convertFileSrc(path, "vkomic-page"),
+    pageUrl: (path: string, index: number) => `${convertFileSrc(path, "vkomic-page")}/${index}`,
+    listPages: async (path: string): Promise<ReaderPageList> => {
+        const response = await fetch(tauriReader.pagesUrl(path));
+        if (!response.ok) throw new Error(await response.text());
+        return response.json();
+    },
+};
+
+// --- Reading progress (indexé par hash du contenu) ---
+export const tauriReading = {
+    update: (path: string, page: number, totalPages: number) =>
+        invoke<ReadingState>("reading_update", { path, page, totalPages }),
+    setCompleted: (path: string, completed: boolean) =>
+        invoke<ReadingState>("reading_set_completed", { path, completed }),
+    get: (paths: string[]) => invoke<Record<string, ReadingState>>("reading_get", { paths }),
+    recent: (limit?: number) => invoke<ReadingState[]>("reading_recent", { limit }),
+    folderProgress: (directories: string[]) =>
+        invoke<FolderProgress[]>("reading_folder_progress", { directories }),
+};
+
+// --- Download History Commands ---
+export const tauriHistory = {
+    query: (query: HistoryQuery = {}) => invoke<HistoryPage>("history_query", { query }),
+    // Jours découpés à minuit heure locale
+    stats: (query: HistoryStatsQuery = {}) =>
+        invoke<HistoryStats>("history_stats", {
+            query: { utcOffsetMinutes: -new Date().getTimezoneOffset(), ...query },
+        }),
+    clear: () => invoke<number>("history_clear"),
 };
 
 // --- Settings Commands ---
@@ -35,11 +131,26 @@ export interface AppSettings {
     vk_group_id: string;
     vk_topic_id: string;
     vk_download_path: string;
+    write_comic_info: boolean;
+    naming_template: string;
+    conflict_policy: ConflictPolicy;
+}
+
+// Fichier parsing_rules.json (dossier de données de l'app)
+export interface RulesSummary {
+    path: string;
+    fileFound: boolean;
+    linkPatterns: number;
+    titleCleanup: number;
+    ignoreTopics: number;
+    ignoreTitles: number;
+    typeOverrides: number;
 }
 
 export const tauriSettings = {
     load: () => invoke<AppSettings>("settings_load"),
     save: (settings: AppSettings) => invoke<void>("settings_save", { settings }),
+    reloadParsingRules: () => invoke<RulesSummary>("parser_reload_rules"),
 };
 
 // --- Shell Commands ---
@@ -67,6 +178,18 @@ export const tauriEvents = {
         listen("download-progress", (event) => callback(event.payload)),
     onDownloadResult: (callback: (payload: any) => void) =>
         listen("download-result", (event) => callback(event.payload)),
+    onDownloadQueueChanged: (callback: (state: QueueState) => void) =>
+        listen<QueueState>("download-queue-changed", (event) => callback(event.payload)),
+    onDownloadLowSpace: (callback: (payload: LowDiskSpace) => void) =>
+        listen<LowDiskSpace>("download-low-space", (event) => callback(event.payload)),
+    onDownloadQueueResumed: (callback: () => void) =>
+        listen("download-queue-resumed", () => callback()),
+    onConversionProgress: (callback: (payload: ConversionProgress) => void) =>
+        listen<ConversionProgress>("conversion-progress", (event) => callback(event.payload)),
+    onConversionResult: (callback: (payload: ConversionResult) => void) =>
+        listen<ConversionResult>("conversion-result", (event) => callback(event.payload)),
+    onLibraryChanged: (callback: (changes: LibraryChange[]) => void) =>
+        listen<LibraryChange[]>("library-changed", (event) => callback(event.payload)),
 };
 
 // --- VK API Helpers (avec valeurs par défaut) ---
@@ -82,9 +205,13 @@ export const fetchRootIndex = async (token: string, groupId?: string, topicId?:
 };
 
 export const fetchNodeContent = async (token: string, node: VkNode): Promise<VkNode> => {
-    if (!node.vkGroupId || !node.vkTopicId) return { ...node, isLoaded: true, children: [] };
+    // Post du mur lié depuis un topic
+    const isWallPost = !node.vkTopicId && !!node.vkOwnerId && !!node.vkWallPostId;
+    if (!isWallPost && (!node.vkGroupId || !node.vkTopicId)) return { ...node, isLoaded: true, children: [] };
     try {
-        const result = await tauriVk.fetchNodeContent(token, node.vkGroupId, node.vkTopicId);
+        const result = isWallPost
+            ? await tauriVk.fetchWallPost(token, node.vkOwnerId!, node.vkWallPostId!)
+            : await tauriVk.fetchNodeContent(token, node.vkGroupId!, node.vkTopicId!, node.vkPostId);
         return { ...result, title: node.title };
     } catch (error) {
         console.error("VK API Error (Node):", error);
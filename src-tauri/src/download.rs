@@ -1,13 +1,26 @@
+use crate::fs_ops::{self, DiskSpace};
+use crate::history::{DownloadHistory, HistoryRecord, HistoryResult};
+use crate::library::comicinfo::{self, ComicInfo, ComicInfoSource};
+use crate::library::{file_stat, now_millis, Library};
+use crate::settings;
+use crate::vk_api::VkApi;
 use anyhow::Result;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
 use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
 const MAX_ACTIVE_DOWNLOADS: usize = 2;
+// Left free on top of what the task still has to write (ComicInfo rewrite, library index)
+const FREE_SPACE_MARGIN: u64 = 32 * 1024 * 1024;
+// Free space is checked again after this many bytes written
+const SPACE_CHECK_INTERVAL: u64 = 64 * 1024 * 1024;
 const VKOMIC_USER_AGENT: &str = "KateMobileAndroid/110.1 lite-x86_64 (Android 11; SDK 30; x86_64; en)";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -17,6 +30,326 @@ pub struct DownloadTask {
     pub directory: String,
     pub file_name: String,
     pub token: Option<String>,
+    #[serde(default)]
+    pub album: Option<AlbumSource>,
+    #[serde(default)]
+    pub parts: Option<Vec<DownloadPart>>,
+    // Duplicate detection: VK document "owner_doc" and expected size
+    #[serde(default)]
+    pub doc_key: Option<String>,
+    #[serde(default)]
+    pub size: Option<u64>,
+    // Download root, every path of the task is checked to stay below it
+    #[serde(default)]
+    pub library_root: Option<String>,
+    // Written as ComicInfo.xml into CBZ/ZIP files once downloaded
+    #[serde(default)]
+    pub comic_info: Option<ComicInfo>,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    #[serde(default)]
+    pub priority: DownloadPriority,
+    // Partial file left by a low-space pause, under the name chosen then (maybe renamed)
+    #[serde(default)]
+    pub resume_at: Option<PathBuf>,
+}
+
+/// Higher priorities start first, FIFO within a priority
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum DownloadPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// What to do when the target file already exists
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    Skip, // Keep the existing file, whatever it is
+    #[default]
+    Verify, // Skip if the size matches the document, resume if smaller, else rename
+    Resume, // Continue from the existing bytes (previous behaviour)
+    Overwrite,
+    Rename, // "name (2).ext"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConflictAction {
+    Skip,
+    Resume(u64),
+    Overwrite,
+    Rename,
+}
+
+impl ConflictAction {
+    /// Reported as `conflict` in download-result
+    fn label(self) -> &'static str {
+        match self {
+            ConflictAction::Skip => "skipped",
+            ConflictAction::Resume(_) => "resumed",
+            ConflictAction::Overwrite => "overwritten",
+            ConflictAction::Rename => "renamed",
+        }
+    }
+}
+
+/// The file already at the target path
+#[derive(Debug, Clone, Copy, Default)]
+struct Existing {
+    len: u64,
+    own_partial: bool, // Left by an unfinished attempt of the same document
+    stamped: bool,     // This document, finished: its ComicInfo links back to it
+}
+
+impl Existing {
+    fn of_len(len: u64) -> Self {
+        Self {
+            len,
+            ..Default::default()
+        }
+    }
+}
+
+/// `existing`: the file already at the target path, `expected`: size of the document
+fn resolve_conflict(
+    policy: ConflictPolicy,
+    existing: Option<Existing>,
+    expected: Option<u64>,
+) -> Option<ConflictAction> {
+    let Existing {
+        len,
+        own_partial,
+        stamped,
+    } = existing?;
+    let action = match policy {
+        ConflictPolicy::Skip => ConflictAction::Skip,
+        ConflictPolicy::Overwrite => ConflictAction::Overwrite,
+        ConflictPolicy::Rename => ConflictAction::Rename,
+        // ComicInfo réécrit après le téléchargement : la taille ne prouve plus rien, le lien si
+        ConflictPolicy::Verify | ConflictPolicy::Resume if stamped => ConflictAction::Skip,
+        ConflictPolicy::Verify => match expected {
+            Some(size) if size == len => ConflictAction::Skip,
+            Some(size) if len < size => ConflictAction::Resume(len),
+            // Taille inconnue (lien dans le texte) : on reprend seulement notre propre essai
+            None if own_partial => ConflictAction::Resume(len),
+            // Plus gros que le document, ou fichier d'origine inconnue : c'est peut-être un autre tome
+            _ => ConflictAction::Rename,
+        },
+        ConflictPolicy::Resume => match expected {
+            Some(size) if size == len => ConflictAction::Skip,
+            Some(size) if len > size => ConflictAction::Overwrite,
+            _ => ConflictAction::Resume(len),
+        },
+    };
+    Some(action)
+}
+
+/// `last`: newest history record at the target path, `modified_at`: mtime of the file there
+fn is_own_partial(task: &DownloadTask, last: &HistoryRecord, modified_at: f64) -> bool {
+    let same_document = match &task.doc_key {
+        Some(key) => last.doc_id.as_ref() == Some(key),
+        None => last.url == task.url,
+    };
+    // Un fichier plus ancien que l'essai n'a pas été écrit par lui (ex. : renommé en "(2)")
+    same_document && !last.result.is_success() && modified_at >= last.started_at
+}
+
+/// The file is this document, downloaded before and stamped with its ComicInfo (same Web link)
+fn is_stamped_copy(path: &Path, task: &DownloadTask) -> bool {
+    let Some(web) = task.comic_info.as_ref().and_then(|info| info.web.as_ref()) else {
+        return false;
+    };
+    comicinfo::read(path)
+        .ok()
+        .flatten()
+        .and_then(|info| info.web)
+        .is_some_and(|stamped| &stamped == web)
+}
+
+fn existing_len(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .ok()
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+}
+
+impl DownloadTask {
+    fn download_root(&self) -> Result<&Path> {
+        self.library_root
+            .as_deref()
+            .filter(|root| !root.trim().is_empty())
+            .map(Path::new)
+            .ok_or_else(|| anyhow::anyhow!("Download folder is not configured"))
+    }
+
+    /// ComicInfo series, else the name of the folder the file goes into
+    fn series(&self) -> Option<String> {
+        self.comic_info
+            .as_ref()
+            .and_then(|info| info.series.clone())
+            .or_else(|| {
+                Path::new(&self.directory)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+    }
+
+    /// Final location of the file, checked to stay under the download root
+    fn target_path(&self) -> Result<PathBuf> {
+        fs_ops::resolve_under(
+            self.download_root()?,
+            Path::new(&self.directory),
+            &self.file_name,
+        )
+    }
+
+    /// File a single-file task writes: the partial left by a low-space pause, else target_path
+    fn file_target(&self) -> Result<PathBuf> {
+        match &self.resume_at {
+            Some(partial) => fs_ops::ensure_within(
+                &self.download_root()?.to_string_lossy(),
+                &partial.to_string_lossy(),
+            ),
+            None => self.target_path(),
+        }
+    }
+
+    /// Requeued after a low-space pause: the partial it was writing is resumed, not re-resolved
+    fn resume_after(&mut self, low: &LowSpace) {
+        match &low.partial {
+            Some(partial) => self.resume_at = Some(partial.clone()),
+            // Volumes déjà reçus : gardés s'ils ont la bonne taille
+            None if self.parts.is_some() && self.conflict_policy == ConflictPolicy::Overwrite => {
+                self.conflict_policy = ConflictPolicy::Verify
+            }
+            None => {}
+        }
+    }
+}
+
+/// One document of fs_queue_downloads, with the parameters of fs_queue_download
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadRequest {
+    pub id: String,
+    pub url: String,
+    pub directory: String,
+    pub file_name: String,
+    pub token: Option<String>,
+    pub doc_key: Option<String>,
+    pub size: Option<u64>,
+    pub comic_info: Option<ComicInfoSource>,
+    pub conflict_policy: Option<ConflictPolicy>,
+    pub priority: Option<DownloadPriority>,
+}
+
+/// One volume of a split archive, saved next to the others in the task directory
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadPart {
+    pub url: String,
+    pub file_name: String,
+    pub size: Option<u64>,
+}
+
+/// A VK photo album downloaded as a set of images packed into a CBZ
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AlbumSource {
+    pub owner_id: String,
+    pub album_id: String,
+}
+
+/// The target volume can't take the rest of a download; the queue pauses on it
+#[derive(Debug, Clone)]
+struct LowSpace {
+    space: DiskSpace,
+    needed: u64,
+    partial: Option<PathBuf>, // File the task was writing, resumed once space is freed
+}
+
+impl std::fmt::Display for LowSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Not enough disk space on {} ({} MB available, {} MB needed)",
+            self.space.path,
+            self.space.available / (1024 * 1024),
+            self.needed / (1024 * 1024)
+        )
+    }
+}
+
+impl std::error::Error for LowSpace {}
+
+/// The task was cancelled by the user (stop_active), its result is already emitted
+#[derive(Debug)]
+struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Download cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// `remaining`: bytes still to write, when known. FREE_SPACE_MARGIN must be left afterwards.
+fn check_space(dir: &Path, remaining: Option<u64>) -> Result<()> {
+    let space = match fs_ops::disk_space(dir) {
+        Ok(space) => space,
+        Err(e) => {
+            // Lecteur réseau, volume inconnu... on ne bloque pas le téléchargement pour autant
+            println!("DEBUG: Free space unknown for {:?}: {}", dir, e);
+            return Ok(());
+        }
+    };
+    let needed = remaining.unwrap_or(0).saturating_add(FREE_SPACE_MARGIN);
+    if space.available < needed {
+        return Err(LowSpace {
+            space,
+            needed,
+            partial: None,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Re-checks free space every SPACE_CHECK_INTERVAL bytes while a worker writes
+struct SpaceWatch {
+    dir: PathBuf,
+    next_check: u64,
+}
+
+impl SpaceWatch {
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            next_check: SPACE_CHECK_INTERVAL,
+        }
+    }
+
+    fn wrote(&mut self, written: u64, remaining: Option<u64>) -> Result<()> {
+        if written < self.next_check {
+            return Ok(());
+        }
+        self.next_check = written + SPACE_CHECK_INTERVAL;
+        check_space(&self.dir, remaining)
+    }
+}
+
+/// `None` as soon as the task is cancelled by stop_active, without waiting for `fut`
+async fn until_cancelled<F: std::future::Future>(
+    cancel_rx: &mut tokio::sync::watch::Receiver<bool>,
+    fut: F,
+) -> Option<F::Output> {
+    tokio::select! {
+        output = fut => Some(output),
+        Ok(_) = cancel_rx.wait_for(|cancelled| *cancelled) => None,
+    }
 }
 
 #[derive(Clone, Serialize)]
@@ -29,60 +362,462 @@ pub struct ProgressPayload {
     pub speed_bytes: f64,
 }
 
+/// Where fs_move_download puts a queued task: `{ to: "top" }`, `{ to: "position", index: 2 }`
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "to", content = "index", rename_all = "camelCase")]
+pub enum QueueMove {
+    Top,
+    Bottom,
+    Position(usize),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QueueStatus {
+    Queued,
+    Active,
+}
+
+/// Tasks cancelled by fs_cancel_downloads: every field set must match
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueFilter {
+    pub series: Option<String>,
+    pub folder: Option<String>, // The folder itself and everything below it
+    pub status: Option<QueueStatus>,
+}
+
+impl QueueFilter {
+    fn matches(&self, task: &DownloadTask, status: QueueStatus) -> bool {
+        let series = self.series.as_ref().is_none_or(|wanted| {
+            task.series()
+                .is_some_and(|series| series.to_lowercase() == wanted.trim().to_lowercase())
+        });
+        let folder = self
+            .folder
+            .as_ref()
+            .is_none_or(|folder| Path::new(&task.directory).starts_with(folder));
+        series && folder && self.status.is_none_or(|wanted| wanted == status)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueItem {
+    pub id: String,
+    pub file_name: String,
+    pub directory: String,
+    pub series: Option<String>,
+    pub priority: DownloadPriority,
+    pub size: Option<u64>,
+    pub status: QueueStatus,
+}
+
+impl QueueItem {
+    fn of(task: &DownloadTask, status: QueueStatus) -> Self {
+        QueueItem {
+            id: task.id.clone(),
+            file_name: task.file_name.clone(),
+            directory: task.directory.clone(),
+            series: task.series(),
+            priority: task.priority,
+            size: task.size,
+            status,
+        }
+    }
+}
+
+/// Snapshot returned by fs_get_queue_state and sent with download-queue-changed
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueState {
+    pub paused: bool,
+    pub max_active: usize,
+    pub active: Vec<QueueItem>,
+    pub queued: Vec<QueueItem>, // In start order
+}
+
+/// After every task of the same or a higher priority
+fn insert_by_priority(queue: &mut VecDeque<DownloadTask>, task: DownloadTask) {
+    let index = queue
+        .iter()
+        .position(|queued| queued.priority < task.priority)
+        .unwrap_or(queue.len());
+    queue.insert(index, task);
+}
+
+/// The moved task takes a priority between its new neighbours, so the queue stays
+/// sorted and later additions don't jump over a task that was put on top by hand
+fn move_in_queue(queue: &mut VecDeque<DownloadTask>, id: &str, to: QueueMove) -> bool {
+    let Some(mut task) = queue
+        .iter()
+        .position(|queued| queued.id == id)
+        .and_then(|from| queue.remove(from))
+    else {
+        return false;
+    };
+    let index = match to {
+        QueueMove::Top => 0,
+        QueueMove::Bottom => queue.len(),
+        QueueMove::Position(index) => index.min(queue.len()),
+    };
+    let upper = index
+        .checked_sub(1)
+        .and_then(|i| queue.get(i))
+        .map_or(DownloadPriority::High, |t| t.priority);
+    let lower = queue
+        .get(index)
+        .map_or(DownloadPriority::Low, |t| t.priority);
+    task.priority = task.priority.max(lower).min(upper);
+    queue.insert(index, task);
+    true
+}
+
+struct RunningTask {
+    task: DownloadTask,
+    started_at: f64,
+}
+
+/// What a worker leaves on disk
+struct Finished {
+    path: PathBuf,
+    received: u64, // Bytes downloaded by this run
+    skipped: bool, // Already there, nothing downloaded
+}
+
+fn history_record(
+    task: &DownloadTask,
+    started_at: f64,
+    result: HistoryResult,
+    path: Option<PathBuf>,
+    received: u64,
+    error: Option<String>,
+) -> HistoryRecord {
+    let finished_at = now_millis();
+    let duration_ms = (finished_at - started_at).max(0.0) as u64;
+    let on_disk = match result {
+        HistoryResult::Completed | HistoryResult::Skipped => path.as_deref().and_then(existing_len),
+        _ => None,
+    };
+    HistoryRecord {
+        task_id: task.id.clone(),
+        doc_id: task.doc_key.clone(),
+        url: task.url.clone(),
+        // Pour un échec, l'endroit où le fichier aurait dû aller
+        path: path
+            .or_else(|| task.target_path().ok())
+            .map(|p| p.to_string_lossy().to_string()),
+        series: task.series(),
+        size: on_disk.or(task.size),
+        received,
+        started_at,
+        finished_at,
+        duration_ms,
+        average_speed: if duration_ms > 0 {
+            received as f64 * 1000.0 / duration_ms as f64
+        } else {
+            0.0
+        },
+        result,
+        error,
+    }
+}
+
 #[derive(Clone)]
 pub struct DownloadManager {
     queue: Arc<Mutex<VecDeque<DownloadTask>>>,
-    active: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>,
+    active: Arc<Mutex<HashMap<String, RunningTask>>>,
     cancel_tokens: Arc<Mutex<HashMap<String, tokio::sync::watch::Sender<bool>>>>,
+    known_files: Arc<Mutex<HashMap<String, PathBuf>>>, // doc_key -> downloaded file
+    paused: Arc<AtomicBool>,                           // Set on low disk space, until resume_queue
+    history: DownloadHistory,
+    library: Library, // Index used to find copies downloaded before a restart
 }
 
 impl DownloadManager {
-    pub fn new() -> Self {
+    pub fn new(library: Library) -> Self {
         println!("DEBUG: DownloadManager created");
         Self {
             queue: Arc::new(Mutex::new(VecDeque::new())),
             active: Arc::new(Mutex::new(HashMap::new())),
             cancel_tokens: Arc::new(Mutex::new(HashMap::new())),
+            known_files: Arc::new(Mutex::new(HashMap::new())),
+            paused: Arc::new(AtomicBool::new(false)),
+            history: DownloadHistory::new(),
+            library,
+        }
+    }
+
+    pub fn history(&self) -> &DownloadHistory {
+        &self.history
+    }
+
+    /// Append the attempt to download_history.json
+    async fn record(&self, app: &AppHandle, record: HistoryRecord) {
+        let Some(store_path) = settings::download_history_path(app) else {
+            return;
+        };
+        let history = self.history.clone();
+        let result = tokio::task::spawn_blocking(move || history.record(&store_path, record))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|r| r);
+        if let Err(e) = result {
+            println!("DEBUG: Download history not saved: {}", e);
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Start scheduling again once space has been freed
+    pub async fn resume_queue(&self, app: AppHandle) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            println!("DEBUG: Queue resumed");
+            let _ = app.emit("download-queue-resumed", ());
+        }
+        self.schedule_next(app).await;
+    }
+
+    /// Put the task back at the head of the queue and stop starting new ones.
+    /// Running downloads go on until they hit the limit themselves.
+    async fn pause_for_low_space(&self, app: &AppHandle, mut task: DownloadTask, low: &LowSpace) {
+        println!("DEBUG: Queue paused: {}", low);
+        self.paused.store(true, Ordering::SeqCst);
+        task.resume_after(low);
+        let id = task.id.clone();
+        let mut queue = self.queue.lock().await;
+        // Déjà commencée : devant les autres tâches de même priorité
+        let index = queue
+            .iter()
+            .position(|queued| queued.priority <= task.priority)
+            .unwrap_or(queue.len());
+        queue.insert(index, task);
+        drop(queue);
+        let _ = app.emit(
+            "download-low-space",
+            serde_json::json!({
+                "id": id,
+                "path": low.space.path,
+                "total": low.space.total,
+                "available": low.space.available,
+                "used": low.space.used,
+                "needed": low.needed
+            }),
+        );
+    }
+
+    /// Same document already downloaded into another folder of the library
+    async fn find_existing_copy(&self, app: &AppHandle, task: &DownloadTask) -> Option<PathBuf> {
+        if task.album.is_some() || task.parts.is_some() {
+            return None;
+        }
+        let target = task.target_path().ok()?;
+        let target_dir = target.parent()?.to_path_buf();
+
+        if let Some(key) = &task.doc_key {
+            if let Some(path) = self.known_files.lock().await.get(key) {
+                if path.exists() && path.parent() != Some(target_dir.as_path()) {
+                    return Some(path.clone());
+                }
+            }
+        }
+
+        // Fallback after a restart: the library index, same normalised name and size
+        let index_path = settings::library_index_path(app)?;
+        let root = task.download_root().ok()?.to_path_buf();
+        let size = task.size?;
+        let file_name = target.file_name()?.to_string_lossy().to_string();
+        let library = self.library.clone();
+        tokio::task::spawn_blocking(move || {
+            library.find_copy(&index_path, &root, &file_name, size, &target_dir)
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// The file at the target path comes from an earlier, unfinished attempt of this task
+    async fn owns_partial(&self, app: &AppHandle, task: &DownloadTask) -> bool {
+        let (Some(store_path), Ok(target)) =
+            (settings::download_history_path(app), task.target_path())
+        else {
+            return false;
+        };
+        let Some((_, modified_at)) = file_stat(&target) else {
+            return false;
+        };
+        let history = self.history.clone();
+        let target = target.to_string_lossy().to_string();
+        tokio::task::spawn_blocking(move || history.last_at_path(&store_path, &target))
+            .await
+            .ok()
+            .flatten()
+            .is_some_and(|last| is_own_partial(task, &last, modified_at))
+    }
+
+    async fn remember_file(&self, task: &DownloadTask, path: PathBuf) {
+        if let Some(key) = &task.doc_key {
+            self.known_files.lock().await.insert(key.clone(), path);
         }
     }
 
     pub async fn add_task(&self, app: AppHandle, task: DownloadTask) {
         println!("DEBUG: add_task called for {}", task.id);
         let mut queue = self.queue.lock().await;
-        queue.push_back(task);
+        insert_by_priority(&mut queue, task);
         println!("DEBUG: Task pushed to queue. Queue size: {}", queue.len());
         drop(queue);
+        self.emit_queue_state(&app).await;
         self.schedule_next(app).await;
     }
 
-    pub async fn cancel_task(&self, app: AppHandle, task_id: String) -> bool {
-        let mut cancel_tokens = self.cancel_tokens.lock().await;
-        let mut active = self.active.lock().await;
+    /// A whole series at once: one download-queue-changed event for all of it
+    pub async fn add_tasks(&self, app: AppHandle, tasks: Vec<DownloadTask>) -> usize {
+        let count = tasks.len();
+        let mut queue = self.queue.lock().await;
+        for task in tasks {
+            insert_by_priority(&mut queue, task);
+        }
+        println!(
+            "DEBUG: {} tasks pushed to queue. Queue size: {}",
+            count,
+            queue.len()
+        );
+        drop(queue);
+        self.emit_queue_state(&app).await;
+        self.schedule_next(app).await;
+        count
+    }
 
-        if let Some(sender) = cancel_tokens.remove(&task_id) {
-            let _ = sender.send(true);
+    pub async fn queue_state(&self) -> QueueState {
+        let queue = self.queue.lock().await;
+        let active = self.active.lock().await;
+        QueueState {
+            paused: self.is_paused(),
+            max_active: MAX_ACTIVE_DOWNLOADS,
+            active: active
+                .values()
+                .map(|running| QueueItem::of(&running.task, QueueStatus::Active))
+                .collect(),
+            queued: queue
+                .iter()
+                .map(|task| QueueItem::of(task, QueueStatus::Queued))
+                .collect(),
+        }
+    }
 
-            if let Some(handle) = active.remove(&task_id) {
-                handle.abort();
-            }
+    async fn emit_queue_state(&self, app: &AppHandle) {
+        let _ = app.emit("download-queue-changed", self.queue_state().await);
+    }
 
-            let _ = app.emit(
-                "download-result",
-                serde_json::json!({
-                    "id": task_id,
-                    "ok": false,
-                    "status": "aborted"
-                }),
-            );
+    /// Only queued tasks can move; false when `id` isn't waiting in the queue
+    pub async fn move_task(&self, app: AppHandle, id: &str, to: QueueMove) -> bool {
+        let moved = move_in_queue(&mut *self.queue.lock().await, id, to);
+        if moved {
+            self.emit_queue_state(&app).await;
+        }
+        moved
+    }
 
-            drop(cancel_tokens);
-            drop(active);
+    pub async fn set_priority(&self, app: AppHandle, id: &str, priority: DownloadPriority) -> bool {
+        let mut queue = self.queue.lock().await;
+        let Some(mut task) = queue
+            .iter()
+            .position(|queued| queued.id == id)
+            .and_then(|index| queue.remove(index))
+        else {
+            return false;
+        };
+        task.priority = priority;
+        insert_by_priority(&mut queue, task);
+        drop(queue);
+        self.emit_queue_state(&app).await;
+        true
+    }
+
+    pub async fn cancel_task(&self, app: AppHandle, task_id: String) -> bool {
+        let mut queue = self.queue.lock().await;
+        let before = queue.len();
+        queue.retain(|task| task.id != task_id);
+        let dequeued = queue.len() != before;
+        drop(queue);
+
+        if dequeued {
+            // Même résultat que pour une tâche active (stop_active)
+            emit_aborted(&app, &task_id);
+        }
+
+        let cancelled = dequeued || self.stop_active(&app, &task_id).await;
+        if cancelled {
+            self.emit_queue_state(&app).await;
             self.schedule_next(app).await;
+        }
+        cancelled
+    }
+
+    /// Cancel every queued and running task matching `filter`, returns their ids
+    pub async fn cancel_matching(&self, app: AppHandle, filter: &QueueFilter) -> Vec<String> {
+        let mut cancelled = Vec::new();
+        self.queue.lock().await.retain(|task| {
+            let hit = filter.matches(task, QueueStatus::Queued);
+            if hit {
+                cancelled.push(task.id.clone());
+            }
+            !hit
+        });
+        for id in &cancelled {
+            emit_aborted(&app, id);
+        }
 
-            return true;
+        let running: Vec<String> = self
+            .active
+            .lock()
+            .await
+            .values()
+            .filter(|running| filter.matches(&running.task, QueueStatus::Active))
+            .map(|running| running.task.id.clone())
+            .collect();
+        for id in running {
+            if self.stop_active(&app, &id).await {
+                cancelled.push(id);
+            }
         }
 
-        false
+        println!("DEBUG: {} tasks cancelled by filter", cancelled.len());
+        self.emit_queue_state(&app).await;
+        self.schedule_next(app).await;
+        cancelled
+    }
+
+    async fn stop_active(&self, app: &AppHandle, task_id: &str) -> bool {
+        // Même ordre que schedule_next (active puis cancel_tokens)
+        let mut active = self.active.lock().await;
+        let mut cancel_tokens = self.cancel_tokens.lock().await;
+
+        let Some(sender) = cancel_tokens.remove(task_id) else {
+            return false;
+        };
+        // Le worker s'arrête de lui-même et nettoie ses fichiers partiels
+        let _ = sender.send(true);
+        let running = active.remove(task_id);
+        drop(cancel_tokens);
+        drop(active);
+
+        emit_aborted(app, task_id);
+        if let Some(running) = running {
+            let record = history_record(
+                &running.task,
+                running.started_at,
+                HistoryResult::Cancelled,
+                None,
+                0,
+                None,
+            );
+            self.record(app, record).await;
+        }
+        true
     }
 
     pub async fn clear_queue(&self, app: AppHandle) -> usize {
@@ -91,31 +826,17 @@ impl DownloadManager {
         queue.clear();
         drop(queue);
 
-        let mut cancel_tokens = self.cancel_tokens.lock().await;
-        let mut active = self.active.lock().await;
-        let active_count = active.len();
-
-        for (id, sender) in cancel_tokens.drain() {
-            let _ = sender.send(true);
-            if let Some(handle) = active.remove(&id) {
-                handle.abort();
+        let running: Vec<String> = self.active.lock().await.keys().cloned().collect();
+        let mut active_count = 0;
+        for id in running {
+            if self.stop_active(&app, &id).await {
+                active_count += 1;
             }
-
-            let _ = app.emit(
-                "download-result",
-                serde_json::json!({
-                    "id": id,
-                    "ok": false,
-                    "status": "aborted"
-                }),
-            );
         }
 
-        drop(cancel_tokens);
-        drop(active);
-
         let total_cancelled = queue_count + active_count;
 
+        self.emit_queue_state(&app).await;
         self.schedule_next(app).await;
 
         total_cancelled
@@ -136,6 +857,13 @@ impl DownloadManager {
             println!("DEBUG: Queue empty, nothing to schedule.");
             return;
         }
+        if self.is_paused() {
+            println!(
+                "DEBUG: Queue paused (low disk space), {} waiting.",
+                queue.len()
+            );
+            return;
+        }
 
         // Lock Active SECOND
         let mut active = self.active.lock().await;
@@ -153,6 +881,8 @@ impl DownloadManager {
         if let Some(task) = queue.pop_front() {
             println!("DEBUG: Popped task {} from queue. Starting...", task.id);
             let id = task.id.clone();
+            let running_task = task.clone();
+            let started_at = now_millis();
 
             let manager_clone = self.clone();
             let app_clone = app.clone();
@@ -160,9 +890,46 @@ impl DownloadManager {
 
             let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
 
-            let handle = tokio::spawn(async move {
+            tokio::spawn(async move {
                 println!("DEBUG: Worker started for task {}", id_for_closure);
-                let result = download_file_worker(app_clone.clone(), task, cancel_rx).await;
+                let result = match manager_clone.find_existing_copy(&app_clone, &task).await {
+                    Some(existing) => {
+                        println!(
+                            "DEBUG: Task {} already on disk at {:?}",
+                            id_for_closure, existing
+                        );
+                        let _ = app_clone.emit(
+                            "download-result",
+                            serde_json::json!({
+                                "id": id_for_closure,
+                                "ok": true,
+                                "status": "duplicate",
+                                "path": existing.to_string_lossy()
+                            }),
+                        );
+                        Ok(Finished {
+                            path: existing,
+                            received: 0,
+                            skipped: true,
+                        })
+                    }
+                    None => {
+                        let own_partial = manager_clone.owns_partial(&app_clone, &task).await;
+                        let result = download_file_worker(
+                            app_clone.clone(),
+                            task.clone(),
+                            cancel_rx,
+                            own_partial,
+                        )
+                        .await;
+                        if let Ok(finished) = &result {
+                            manager_clone
+                                .remember_file(&task, finished.path.clone())
+                                .await;
+                        }
+                        result
+                    }
+                };
                 println!("DEBUG: Worker finished for task {}", id_for_closure);
 
                 // Cleanup
@@ -174,26 +941,81 @@ impl DownloadManager {
                 cancel_tokens.remove(&id_for_closure);
                 drop(cancel_tokens);
 
-                if let Err(e) = result {
-                    println!("DEBUG: Task {} failed: {}", id_for_closure, e);
-                    let _ = app_clone.emit(
-                        "download-result",
-                        serde_json::json!({
-                            "id": id_for_closure,
-                            "ok": false,
-                            "error": e.to_string()
-                        }),
-                    );
-                } else {
-                    println!("DEBUG: Task {} success", id_for_closure);
+                let low_space = result
+                    .as_ref()
+                    .err()
+                    .and_then(|e| e.downcast_ref::<LowSpace>())
+                    .cloned();
+                let record = match &result {
+                    Ok(finished) => {
+                        println!("DEBUG: Task {} success", id_for_closure);
+                        let outcome = if finished.skipped {
+                            HistoryResult::Skipped
+                        } else {
+                            HistoryResult::Completed
+                        };
+                        Some(history_record(
+                            &task,
+                            started_at,
+                            outcome,
+                            Some(finished.path.clone()),
+                            finished.received,
+                            None,
+                        ))
+                    }
+                    // Déjà enregistrée par stop_active
+                    Err(e) if e.is::<Cancelled>() => None,
+                    Err(e) => {
+                        let outcome = if low_space.is_some() {
+                            HistoryResult::Interrupted
+                        } else {
+                            HistoryResult::Failed
+                        };
+                        Some(history_record(
+                            &task,
+                            started_at,
+                            outcome,
+                            None,
+                            0,
+                            Some(e.to_string()),
+                        ))
+                    }
+                };
+                if let Some(record) = record {
+                    manager_clone.record(&app_clone, record).await;
+                }
+
+                if let Some(low) = low_space {
+                    manager_clone
+                        .pause_for_low_space(&app_clone, task, &low)
+                        .await;
+                } else if let Err(e) = result {
+                    // Annulée : stop_active a déjà émis "aborted"
+                    if !e.is::<Cancelled>() {
+                        println!("DEBUG: Task {} failed: {}", id_for_closure, e);
+                        let _ = app_clone.emit(
+                            "download-result",
+                            serde_json::json!({
+                                "id": id_for_closure,
+                                "ok": false,
+                                "error": e.to_string()
+                            }),
+                        );
+                    }
                 }
 
                 // Trigger next loop
                 manager_clone.trigger_next(app_clone);
             });
 
-            // Insert handle to active map
-            active.insert(id.clone(), handle);
+            // Insert task to active map
+            active.insert(
+                id.clone(),
+                RunningTask {
+                    task: running_task,
+                    started_at,
+                },
+            );
 
             // Register cancel token
             let mut cancel_tokens = self.cancel_tokens.lock().await;
@@ -210,24 +1032,37 @@ impl DownloadManager {
     }
 }
 
+/// download-result of a task cancelled by the user, queued or running
+fn emit_aborted(app: &AppHandle, task_id: &str) {
+    let _ = app.emit(
+        "download-result",
+        serde_json::json!({
+            "id": task_id,
+            "ok": false,
+            "status": "aborted"
+        }),
+    );
+}
+
 async fn download_file_worker(
     app: AppHandle,
     task: DownloadTask,
-    cancel_rx: tokio::sync::watch::Receiver<bool>,
-) -> Result<()> {
+    mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+    own_partial: bool,
+) -> Result<Finished> {
+    if let Some(album) = task.album.clone() {
+        return download_album_worker(app, task, album, cancel_rx).await;
+    }
+    if let Some(parts) = task.parts.clone() {
+        return download_parts_worker(app, task, parts, cancel_rx).await;
+    }
+
     println!("DEBUG: Worker processing URL: {}", task.url);
     let client = reqwest::Client::builder()
         .user_agent(VKOMIC_USER_AGENT)
         .build()?;
 
-    // Sanitization du nom de fichier pour Windows (remplace les caractères interdits par _)
-    let safe_file_name: String = task
-        .file_name
-        .chars()
-        .map(|c| if "<>:\"/\\|?*".contains(c) { '_' } else { c })
-        .collect();
-
-    let path = std::path::Path::new(&task.directory).join(&safe_file_name);
+    let mut path = task.file_target()?;
     println!("DEBUG: Target file path: {:?}", path);
 
     // Ensure directory exists
@@ -238,26 +1073,68 @@ async fn download_file_worker(
         }
     }
 
+    let existing = match existing_len(&path) {
+        Some(len) => {
+            let (target, stamp_task) = (path.clone(), task.clone());
+            let stamped =
+                tokio::task::spawn_blocking(move || is_stamped_copy(&target, &stamp_task))
+                    .await
+                    .unwrap_or(false);
+            Some(Existing {
+                len,
+                own_partial: own_partial || task.resume_at.is_some(),
+                stamped,
+            })
+        }
+        None => None,
+    };
+    let policy = match task.resume_at {
+        // Fichier écrit par cette tâche avant la pause : jamais renommé ni écrasé
+        Some(_) => ConflictPolicy::Resume,
+        None => task.conflict_policy,
+    };
+    let conflict = resolve_conflict(policy, existing, task.size);
     let mut start_byte = 0;
-    if path.exists() {
-        start_byte = std::fs::metadata(&path)?.len();
+    match conflict {
+        Some(ConflictAction::Skip) => {
+            println!("DEBUG: {:?} already on disk, skipping", path);
+            emit_done(&app, &task, &path, conflict)?;
+            return Ok(Finished {
+                path,
+                received: 0,
+                skipped: true,
+            });
+        }
+        Some(ConflictAction::Resume(len)) => start_byte = len,
+        Some(ConflictAction::Rename) => path = fs_ops::unique_path(&path, |p| p.exists()),
+        // Sans Range, le serveur renvoie tout et le fichier est tronqué
+        Some(ConflictAction::Overwrite) | None => {}
     }
 
+    let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    check_space(
+        &directory,
+        task.size.map(|size| size.saturating_sub(start_byte)),
+    )?;
+
     let mut request = client.get(&task.url);
     if start_byte > 0 {
         request = request.header("Range", format!("bytes={}-", start_byte));
     }
 
-    let response = request.send().await?;
+    let Some(response) = until_cancelled(&mut cancel_rx, request.send()).await else {
+        return Err(anyhow::Error::new(Cancelled));
+    };
+    // Page d'erreur (403, 416, 5xx) : jamais écrite dans le fichier ni comptée comme terminée
+    let response = response?.error_for_status()?;
 
-    if *cancel_rx.borrow() {
-        return Err(anyhow::anyhow!("Download cancelled"));
+    if task.size.is_none() {
+        // Taille connue seulement maintenant : ce qui reste à recevoir
+        check_space(&directory, response.content_length())?;
     }
 
-    let total_size = response.content_length().map(|l| l + start_byte);
-
-    // Détermine le mode d'ouverture selon le code HTTP
-    let mut file = if response.status() == 206 {
+    // Détermine le mode d'ouverture selon le code HTTP : on n'ajoute qu'à une reprise acceptée
+    let mut file = if start_byte > 0 && response.status() == 206 {
         // Contenu partiel (Resume) : On ouvre en append pour ne pas pèter le début
         println!("DEBUG: Status 206 (Partial) - Resuming download");
         tokio::fs::OpenOptions::new()
@@ -282,31 +1159,35 @@ async fn download_file_worker(
     };
 
     // Plus besoin de seek/set_len manuel car géré par les flags OpenOptions
+    let total_size = response.content_length().map(|l| l + start_byte);
 
     let mut stream = response.bytes_stream();
     let mut downloaded: u64 = 0;
     let start_time = std::time::Instant::now();
     let mut last_emit = std::time::Instant::now();
+    let mut space = SpaceWatch::new(&directory);
 
-    while let Some(item) = stream.next().await {
-        if *cancel_rx.borrow() {
-            let _ = app.emit(
-                "download-result",
-                serde_json::json!({
-                    "id": task.id.clone(),
-                    "ok": false,
-                    "status": "aborted"
-                }),
-            );
-            return Err(anyhow::anyhow!("Download cancelled"));
-        }
-
+    while let Some(Some(item)) = until_cancelled(&mut cancel_rx, stream.next()).await {
         let chunk = item?;
         file.write_all(&chunk).await?;
         downloaded += chunk.len() as u64;
 
         let total_downloaded = start_byte + downloaded;
 
+        let remaining = total_size.map(|total| total.saturating_sub(total_downloaded));
+        if let Err(e) = space.wrote(downloaded, remaining) {
+            // Tout ce qui est reçu doit être sur le disque pour la reprise
+            file.flush().await?;
+            return Err(match e.downcast::<LowSpace>() {
+                Ok(low) => LowSpace {
+                    partial: Some(path),
+                    ..low
+                }
+                .into(),
+                Err(e) => e,
+            });
+        }
+
         // Throttle updates to avoid flooding frontend
         if last_emit.elapsed().as_millis() > 100 {
             let elapsed = start_time.elapsed().as_secs_f64();
@@ -332,15 +1213,688 @@ async fn download_file_worker(
             last_emit = std::time::Instant::now();
         }
     }
+    file.flush().await?;
+    drop(file);
+
+    if *cancel_rx.borrow() {
+        // Annulé : pas de fichier tronqué sous le nom définitif, sauf s'il existait avant cet essai
+        if !matches!(conflict, Some(ConflictAction::Resume(_))) {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        return Err(anyhow::Error::new(Cancelled));
+    }
 
+    if let Some(info) = task.comic_info.clone() {
+        embed_comic_info(&path, info).await;
+    }
+
+    emit_done(&app, &task, &path, conflict)?;
+    Ok(Finished {
+        path,
+        received: downloaded,
+        skipped: false,
+    })
+}
+
+fn emit_done(
+    app: &AppHandle,
+    task: &DownloadTask,
+    path: &Path,
+    conflict: Option<ConflictAction>,
+) -> Result<()> {
     app.emit(
         "download-result",
         serde_json::json!({
             "id": task.id,
             "ok": true,
-            "path": path.to_string_lossy()
+            "path": path.to_string_lossy(),
+            "conflict": conflict.map(ConflictAction::label)
         }),
     )?;
-
     Ok(())
 }
+
+/// Metadata is a bonus: a file that can't take it is still a good download
+async fn embed_comic_info(path: &Path, info: ComicInfo) {
+    let is_zip = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .is_some_and(|e| e == "cbz" || e == "zip");
+    if !is_zip {
+        return;
+    }
+    let target = path.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || comicinfo::embed(&target, &info))
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|r| r);
+    if let Err(e) = result {
+        println!("DEBUG: ComicInfo.xml not written to {:?}: {}", path, e);
+    }
+}
+
+async fn download_album_worker(
+    app: AppHandle,
+    task: DownloadTask,
+    album: AlbumSource,
+    cancel_rx: tokio::sync::watch::Receiver<bool>,
+) -> Result<Finished> {
+    println!(
+        "DEBUG: Worker processing album {}_{}",
+        album.owner_id, album.album_id
+    );
+    let token = task
+        .token
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("A VK token is required to download an album"))?;
+
+    // Le CBZ n'apparaît qu'une fois complet (renommé depuis le .part) : sa taille fait foi
+    let mut path = task.target_path()?;
+    let existing = existing_len(&path);
+    let conflict = resolve_conflict(
+        task.conflict_policy,
+        existing.map(Existing::of_len),
+        existing,
+    );
+    match conflict {
+        Some(ConflictAction::Skip) | Some(ConflictAction::Resume(_)) => {
+            emit_done(&app, &task, &path, Some(ConflictAction::Skip))?;
+            return Ok(Finished {
+                path,
+                received: 0,
+                skipped: true,
+            });
+        }
+        Some(ConflictAction::Rename) => path = fs_ops::unique_path(&path, |p| p.exists()),
+        Some(ConflictAction::Overwrite) | None => {}
+    }
+
+    let photo_urls = VkApi::new(token)
+        .fetch_album_photo_urls(&album.owner_id, &album.album_id)
+        .await?;
+    if photo_urls.is_empty() {
+        return Err(anyhow::anyhow!("Album is empty"));
+    }
+
+    let client = reqwest::Client::builder()
+        .user_agent(VKOMIC_USER_AGENT)
+        .build()?;
+
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+    }
+    // Taille de l'album inconnue : on vérifie seulement la réserve, puis en cours de route
+    let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    check_space(&directory, None)?;
+    let mut space = SpaceWatch::new(&directory);
+
+    // On écrit dans un .part puis on renomme, pour ne jamais laisser un CBZ tronqué
+    let part_path = part_path_of(&path);
+    let written = write_album_archive(
+        &app,
+        &task,
+        &client,
+        &photo_urls,
+        &part_path,
+        &mut space,
+        cancel_rx,
+    )
+    .await;
+    let downloaded = match written {
+        Ok(downloaded) => downloaded,
+        Err(e) => {
+            // Un album ne se reprend pas : le .part ne servirait plus
+            let _ = tokio::fs::remove_file(&part_path).await;
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&part_path, &path).await?;
+
+    emit_done(&app, &task, &path, conflict)?;
+    Ok(Finished {
+        path,
+        received: downloaded,
+        skipped: false,
+    })
+}
+
+/// Fetch every photo into the `.part` CBZ. The archive is closed before returning,
+/// so the caller can remove the file on error.
+async fn write_album_archive(
+    app: &AppHandle,
+    task: &DownloadTask,
+    client: &reqwest::Client,
+    photo_urls: &[String],
+    part_path: &Path,
+    space: &mut SpaceWatch,
+    mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+) -> Result<u64> {
+    // ZipWriter écrit avec std::fs : tout passe par spawn_blocking
+    let target = part_path.to_path_buf();
+    let mut archive =
+        tokio::task::spawn_blocking(move || std::fs::File::create(target).map(zip::ZipWriter::new))
+            .await??;
+    // Les images sont déjà compressées, inutile de les dégonfler
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    let total_pages = photo_urls.len();
+    let mut downloaded: u64 = 0;
+    let start_time = std::time::Instant::now();
+
+    for (index, url) in photo_urls.iter().enumerate() {
+        let fetch = async {
+            client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        };
+        let Some(bytes) = until_cancelled(&mut cancel_rx, fetch).await else {
+            return Err(anyhow::Error::new(Cancelled));
+        };
+        let bytes = bytes?;
+        downloaded += bytes.len() as u64;
+        let entry_name = format!("{:04}.{}", index + 1, image_extension(url));
+        archive = tokio::task::spawn_blocking(move || -> Result<_> {
+            archive.start_file(entry_name, options)?;
+            archive.write_all(&bytes)?;
+            Ok(archive)
+        })
+        .await??;
+        space.wrote(downloaded, None)?;
+
+        let elapsed = start_time.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            downloaded as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        app.emit(
+            "download-progress",
+            ProgressPayload {
+                id: task.id.clone(),
+                progress: ((index + 1) as f64 / total_pages as f64) * 100.0,
+                received_bytes: downloaded,
+                total_bytes: None,
+                speed_bytes: speed,
+            },
+        )?;
+    }
+
+    let comic_info = task.comic_info.clone().map(|info| ComicInfo {
+        page_count: Some(total_pages),
+        ..info
+    });
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        if let Some(info) = &comic_info {
+            comicinfo::write_entry(&mut archive, info)?;
+        }
+        archive.finish()?;
+        Ok(())
+    })
+    .await??;
+
+    Ok(downloaded)
+}
+
+/// Download every volume of a split archive as a single job.
+/// The task only succeeds once all parts are complete on disk.
+async fn download_parts_worker(
+    app: AppHandle,
+    task: DownloadTask,
+    parts: Vec<DownloadPart>,
+    mut cancel_rx: tokio::sync::watch::Receiver<bool>,
+) -> Result<Finished> {
+    println!(
+        "DEBUG: Worker processing {} parts for {}",
+        parts.len(),
+        task.id
+    );
+    if parts.is_empty() {
+        return Err(anyhow::anyhow!("No parts to download"));
+    }
+
+    let client = reqwest::Client::builder()
+        .user_agent(VKOMIC_USER_AGENT)
+        .build()?;
+
+    // Chaque partie est vérifiée ; deux noms identiques une fois nettoyés sont départagés
+    let root = task.download_root()?;
+    let mut paths: Vec<PathBuf> = Vec::with_capacity(parts.len());
+    for part in &parts {
+        let path = fs_ops::resolve_under(root, Path::new(&task.directory), &part.file_name)?;
+        paths.push(fs_ops::unique_path(&path, |p| {
+            paths.iter().any(|taken| taken == p)
+        }));
+    }
+    let directory = paths[0].parent().map(Path::to_path_buf).unwrap_or_default();
+    if !directory.exists() {
+        tokio::fs::create_dir_all(&directory).await?;
+    }
+
+    // La progression globale n'a de sens que si toutes les tailles sont connues
+    let total_size = parts.iter().map(|p| p.size).sum::<Option<u64>>();
+    let mut done_bytes: u64 = 0;
+    let mut downloaded: u64 = 0;
+    let start_time = std::time::Instant::now();
+    let mut last_emit = std::time::Instant::now();
+    let mut first_path = None;
+    let mut space = SpaceWatch::new(&directory);
+    let remaining = |received: u64| total_size.map(|total| total.saturating_sub(received));
+
+    for (index, (part, path)) in parts.iter().zip(paths).enumerate() {
+        // Les parties finies sont renommées, un .part est donc toujours incomplet
+        let part_path = part_path_of(&path);
+        first_path.get_or_insert_with(|| path.clone());
+
+        if let Some(len) = existing_len(&path) {
+            let keep = match task.conflict_policy {
+                ConflictPolicy::Skip => true,
+                ConflictPolicy::Overwrite => false,
+                // Renommer une seule partie casserait l'archive : on vérifie la taille
+                _ => part.size.is_none_or(|size| size == len),
+            };
+            if keep {
+                println!("DEBUG: Part {} already on disk, skipping", index + 1);
+                done_bytes += len;
+                continue;
+            }
+        }
+
+        let mut start_byte = if part_path.exists() {
+            std::fs::metadata(&part_path)?.len()
+        } else {
+            0
+        };
+        check_space(&directory, remaining(done_bytes + start_byte))?;
+
+        let mut request = client.get(&part.url);
+        if start_byte > 0 {
+            request = request.header("Range", format!("bytes={}-", start_byte));
+        }
+        let Some(response) = until_cancelled(&mut cancel_rx, request.send()).await else {
+            return Err(anyhow::Error::new(Cancelled));
+        };
+        let response = response?.error_for_status()?;
+
+        let mut file = if response.status() == 206 {
+            tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&part_path)
+                .await?
+        } else {
+            start_byte = 0;
+            tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&part_path)
+                .await?
+        };
+
+        let mut part_bytes = start_byte;
+        let mut stream = response.bytes_stream();
+        while let Some(Some(item)) = until_cancelled(&mut cancel_rx, stream.next()).await {
+            let chunk = item?;
+            file.write_all(&chunk).await?;
+            part_bytes += chunk.len() as u64;
+            downloaded += chunk.len() as u64;
+            if let Err(e) = space.wrote(downloaded, remaining(done_bytes + part_bytes)) {
+                file.flush().await?;
+                return Err(e);
+            }
+
+            if last_emit.elapsed().as_millis() > 100 {
+                let elapsed = start_time.elapsed().as_secs_f64();
+                let speed = if elapsed > 0.0 {
+                    downloaded as f64 / elapsed
+                } else {
+                    0.0
+                };
+                let received = done_bytes + part_bytes;
+                let progress = match total_size {
+                    Some(total) if total > 0 => (received as f64 / total as f64) * 100.0,
+                    _ => (index as f64 / parts.len() as f64) * 100.0,
+                };
+
+                app.emit(
+                    "download-progress",
+                    ProgressPayload {
+                        id: task.id.clone(),
+                        // 100 est réservé à la fin de la dernière partie
+                        progress: progress.min(99.9),
+                        received_bytes: received,
+                        total_bytes: total_size,
+                        speed_bytes: speed,
+                    },
+                )?;
+                last_emit = std::time::Instant::now();
+            }
+        }
+
+        file.flush().await?;
+        drop(file);
+
+        if *cancel_rx.borrow() {
+            // Les parties déjà finies restent : elles sont complètes et vérifiées
+            let _ = tokio::fs::remove_file(&part_path).await;
+            return Err(anyhow::Error::new(Cancelled));
+        }
+
+        if let Some(size) = part.size {
+            if part_bytes != size {
+                return Err(anyhow::anyhow!(
+                    "Part {} is incomplete ({} / {} bytes)",
+                    index + 1,
+                    part_bytes,
+                    size
+                ));
+            }
+        }
+        tokio::fs::rename(&part_path, &path).await?;
+        done_bytes += part_bytes;
+    }
+
+    app.emit(
+        "download-result",
+        serde_json::json!({
+            "id": task.id,
+            "ok": true,
+            "path": first_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+            "size": done_bytes
+        }),
+    )?;
+
+    Ok(Finished {
+        path: first_path.unwrap_or_default(),
+        received: downloaded,
+        skipped: downloaded == 0, // Toutes les parties étaient déjà là
+    })
+}
+
+fn part_path_of(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Image extension from a VK photo URL (userapi URLs carry it before the query string)
+fn image_extension(url: &str) -> &str {
+    let path = url.split('?').next().unwrap_or(url);
+    match path.rsplit_once('.') {
+        Some((_, ext))
+            if matches!(
+                ext.to_lowercase().as_str(),
+                "jpg" | "jpeg" | "png" | "gif" | "webp"
+            ) =>
+        {
+            ext
+        }
+        _ => "jpg",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conflict_policy_decides_from_sizes() {
+        use ConflictAction::*;
+        use ConflictPolicy as P;
+        let disk = Existing::of_len;
+
+        assert_eq!(resolve_conflict(P::Verify, None, Some(10)), None);
+        assert_eq!(
+            resolve_conflict(P::Verify, Some(disk(10)), Some(10)),
+            Some(Skip)
+        );
+        assert_eq!(
+            resolve_conflict(P::Verify, Some(disk(4)), Some(10)),
+            Some(Resume(4))
+        );
+        // Même nom, autre tome : jamais de Range sur un fichier étranger
+        assert_eq!(
+            resolve_conflict(P::Verify, Some(disk(40)), Some(10)),
+            Some(Rename)
+        );
+        // Taille inconnue : reprise de notre propre essai, jamais d'un fichier étranger
+        assert_eq!(
+            resolve_conflict(P::Verify, Some(disk(4)), None),
+            Some(Rename)
+        );
+        assert_eq!(
+            resolve_conflict(
+                P::Verify,
+                Some(Existing {
+                    own_partial: true,
+                    ..disk(4)
+                }),
+                None
+            ),
+            Some(Resume(4))
+        );
+
+        assert_eq!(
+            resolve_conflict(P::Resume, Some(disk(4)), None),
+            Some(Resume(4))
+        );
+        assert_eq!(
+            resolve_conflict(P::Resume, Some(disk(40)), Some(10)),
+            Some(Overwrite)
+        );
+        assert_eq!(
+            resolve_conflict(P::Skip, Some(disk(4)), Some(10)),
+            Some(Skip)
+        );
+        assert_eq!(
+            resolve_conflict(P::Overwrite, Some(disk(10)), Some(10)),
+            Some(Overwrite)
+        );
+        assert_eq!(
+            resolve_conflict(P::Rename, Some(disk(10)), Some(10)),
+            Some(Rename)
+        );
+    }
+
+    #[test]
+    fn low_space_is_reported_with_the_volume() {
+        let dir = std::env::temp_dir()
+            .join("vkomic-space-check")
+            .join("new-series");
+        // Le dossier n'existe pas encore : c'est son parent existant qui est interrogé
+        assert!(fs_ops::disk_space(&dir).is_ok());
+
+        let err = check_space(&dir, Some(u64::MAX / 2)).unwrap_err();
+        let low = err.downcast_ref::<LowSpace>().expect("LowSpace error");
+        assert_eq!(low.needed, u64::MAX / 2 + FREE_SPACE_MARGIN);
+        assert!(low.space.available < low.needed);
+        assert!(low.space.used <= low.space.total);
+    }
+
+    fn queued(id: &str, priority: DownloadPriority, directory: &str) -> DownloadTask {
+        DownloadTask {
+            id: id.to_string(),
+            url: String::new(),
+            directory: directory.to_string(),
+            file_name: format!("{}.cbz", id),
+            token: None,
+            album: None,
+            parts: None,
+            doc_key: None,
+            size: None,
+            library_root: None,
+            comic_info: None,
+            conflict_policy: ConflictPolicy::default(),
+            priority,
+            resume_at: None,
+        }
+    }
+
+    #[test]
+    fn comic_info_stamped_copy_is_skipped() {
+        use crate::test_util::{write_zip, TempDir};
+
+        let dir = TempDir::new("stamped");
+        let path = dir.join("Blacksad 1.cbz");
+        write_zip(&path, &["p1.png", "p2.png"]);
+        let downloaded = existing_len(&path).unwrap();
+
+        let mut task = queued("t1", DownloadPriority::Normal, "/bd/Blacksad");
+        task.size = Some(downloaded);
+        task.comic_info = Some(ComicInfo::for_document(&ComicInfoSource {
+            series: Some("Blacksad".to_string()),
+            title: "Blacksad 1.cbz".to_string(),
+            web: Some("https://vk.com/doc-1_2".to_string()),
+            group: Some("1".to_string()),
+        }));
+        assert!(!is_stamped_copy(&path, &task));
+
+        comicinfo::embed(&path, task.comic_info.as_ref().unwrap()).unwrap();
+        let len = existing_len(&path).unwrap();
+        assert_ne!(len, downloaded);
+        assert!(is_stamped_copy(&path, &task));
+
+        let existing = Existing {
+            len,
+            stamped: true,
+            ..Default::default()
+        };
+        for policy in [ConflictPolicy::Verify, ConflictPolicy::Resume] {
+            assert_eq!(
+                resolve_conflict(policy, Some(existing), task.size),
+                Some(ConflictAction::Skip)
+            );
+        }
+
+        // Un autre document du même nom n'est pas pris pour celui-ci
+        task.comic_info.as_mut().unwrap().web = Some("https://vk.com/doc-1_3".to_string());
+        assert!(!is_stamped_copy(&path, &task));
+    }
+
+    #[test]
+    fn low_space_pause_resumes_the_renamed_partial() {
+        use crate::test_util::TempDir;
+
+        let root = TempDir::new("requeue");
+        let directory = root.join("Blacksad");
+        let mut task = queued("t1", DownloadPriority::Normal, &directory.to_string_lossy());
+        task.library_root = Some(root.path().to_string_lossy().to_string());
+        task.conflict_policy = ConflictPolicy::Verify;
+        task.size = Some(10);
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // Un autre tome porte déjà ce nom : la tâche écrit « t1 (2).cbz »
+        let foreign = task.target_path().unwrap();
+        std::fs::write(&foreign, [7; 40]).unwrap();
+        let existing = existing_len(&foreign).map(Existing::of_len);
+        assert_eq!(
+            resolve_conflict(task.conflict_policy, existing, task.size),
+            Some(ConflictAction::Rename)
+        );
+        let renamed = fs_ops::unique_path(&foreign, |p| p.exists());
+        std::fs::write(&renamed, [1; 4]).unwrap();
+
+        let space = fs_ops::disk_space(root.path()).unwrap();
+        task.resume_after(&LowSpace {
+            space,
+            needed: u64::MAX,
+            partial: Some(renamed.clone()),
+        });
+        assert_eq!(task.conflict_policy, ConflictPolicy::Verify);
+        assert_eq!(task.file_target().unwrap(), renamed);
+
+        // Reprise du « (2) », le fichier étranger n'est pas touché
+        let existing = Existing {
+            len: existing_len(&renamed).unwrap(),
+            own_partial: true,
+            stamped: false,
+        };
+        assert_eq!(
+            resolve_conflict(ConflictPolicy::Resume, Some(existing), task.size),
+            Some(ConflictAction::Resume(4))
+        );
+        assert_eq!(existing_len(&foreign), Some(40));
+
+        // Partiel hors du dossier de téléchargement : refusé
+        task.resume_at = Some(std::env::temp_dir().join("elsewhere.cbz"));
+        assert!(task.file_target().is_err());
+    }
+
+    #[test]
+    fn partial_file_is_ours_after_an_unfinished_attempt() {
+        let mut task = queued("t1", DownloadPriority::Normal, "/bd/Blacksad");
+        task.doc_key = Some("1_2".to_string());
+        let failed = history_record(&task, 1_000.0, HistoryResult::Failed, None, 0, None);
+
+        assert!(is_own_partial(&task, &failed, 2_000.0));
+        // Déjà là avant l'essai : c'est le fichier de quelqu'un d'autre
+        assert!(!is_own_partial(&task, &failed, 500.0));
+
+        let completed = history_record(&task, 1_000.0, HistoryResult::Completed, None, 0, None);
+        assert!(!is_own_partial(&task, &completed, 2_000.0));
+
+        let mut other = task.clone();
+        other.doc_key = Some("1_3".to_string());
+        assert!(!is_own_partial(&other, &failed, 2_000.0));
+    }
+
+    #[test]
+    fn queue_keeps_priority_order_when_moving() {
+        use DownloadPriority::*;
+        let ids = |queue: &VecDeque<DownloadTask>| {
+            queue
+                .iter()
+                .map(|t| t.id.clone())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut queue = VecDeque::new();
+        for (id, priority) in [("t1", Normal), ("t2", Normal), ("t3", Low), ("t4", High)] {
+            insert_by_priority(&mut queue, queued(id, priority, "/bd/Blacksad"));
+        }
+        insert_by_priority(&mut queue, queued("t5", Normal, "/bd/Blake"));
+        assert_eq!(ids(&queue), "t4 t1 t2 t5 t3");
+
+        // Le tome à lire tout de suite passe devant, priorité relevée comme son voisin
+        assert!(move_in_queue(&mut queue, "t3", QueueMove::Top));
+        assert_eq!(ids(&queue), "t3 t4 t1 t2 t5");
+        assert_eq!(queue[0].priority, High);
+        insert_by_priority(&mut queue, queued("t6", High, "/bd/Blake"));
+        assert_eq!(ids(&queue), "t3 t4 t6 t1 t2 t5");
+
+        assert!(move_in_queue(&mut queue, "t4", QueueMove::Bottom));
+        assert_eq!(queue.back().unwrap().priority, Normal);
+        assert!(move_in_queue(&mut queue, "t5", QueueMove::Position(1)));
+        assert_eq!(ids(&queue), "t3 t5 t6 t1 t2 t4");
+        assert_eq!(queue[1].priority, High);
+        assert!(!move_in_queue(&mut queue, "missing", QueueMove::Top));
+
+        let filter = QueueFilter {
+            series: Some("blacksad".to_string()),
+            ..Default::default()
+        };
+        let matched: Vec<&str> = queue
+            .iter()
+            .filter(|t| filter.matches(t, QueueStatus::Queued))
+            .map(|t| t.id.as_str())
+            .collect();
+        assert_eq!(matched, vec!["t3", "t1", "t2", "t4"]);
+
+        let filter = QueueFilter {
+            folder: Some("/bd/Blake".to_string()),
+            status: Some(QueueStatus::Active),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&queue[1], QueueStatus::Queued));
+        assert!(filter.matches(&queue[1], QueueStatus::Active));
+    }
+}
@@ -1,7 +1,23 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::Path;
-use anyhow::Result;
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{anyhow, Result};
+use unicode_normalization::UnicodeNormalization;
+
+// Windows refuses these as file names, even with an extension ("CON.cbz")
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+// Most filesystems allow 255 bytes per component; keep room for ".part" and suffixes
+pub const MAX_COMPONENT_BYTES: usize = 200;
+// Windows MAX_PATH (260) minus room for ".part" / ".comicinfo.tmp" suffixes
+pub const MAX_PATH_CHARS: usize = 240;
+// A file name is never shortened below this to fit a long path
+const MIN_NAME_CHARS: usize = 16;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -99,3 +115,201 @@ pub fn reveal_path(target_path: &str) -> Result<()> {
     Ok(())
 }
 
+
+/// One portable path component: NFC-normalised, forbidden and control characters
+/// replaced, no trailing dots or spaces, no Windows device name, at most `max_bytes`.
+/// Shortened names get a short hash of the full name so they stay distinct.
+pub fn portable_name(name: &str, max_bytes: usize) -> String {
+    let replaced: String = name
+        .nfc()
+        .map(|c| if c.is_control() || "<>:\"/\\|?*".contains(c) { '_' } else { c })
+        .collect();
+    let mut name = replaced.trim().trim_end_matches(['.', ' ']).to_string();
+    if name.is_empty() {
+        name = "_".to_string();
+    }
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+        name.insert(0, '_');
+    }
+
+    if name.len() > max_bytes {
+        name = shorten_name(&name, max_bytes);
+    }
+    name
+}
+
+/// "very long title.cbz" -> "very lo~3fa2c1.cbz" (extension kept, UTF-8 boundaries respected)
+pub fn shorten_name(name: &str, max_bytes: usize) -> String {
+    let hash: String = Sha256::digest(name.as_bytes())
+        .iter()
+        .take(3)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && ext.len() <= 5 => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    let mut end = max_bytes.saturating_sub(ext.len() + hash.len() + 1).min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}~{}{}", stem[..end].trim_end_matches(['.', ' ']), hash, ext)
+}
+
+/// Reject names that would leave their folder: "..", "a/../b", "/etc/x", "C:\x".
+/// Anything else is made portable (other separators become "_").
+pub fn checked_file_name(name: &str) -> Result<String> {
+    let trimmed = name.trim();
+    let bytes = trimmed.as_bytes();
+    let absolute = trimmed.starts_with(['/', '\\'])
+        || (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && (bytes[2] == b'/' || bytes[2] == b'\\'));
+    if trimmed.is_empty() || absolute || trimmed.split(['/', '\\']).any(|part| part.trim() == "..") {
+        return Err(anyhow!("Unsafe file name: {:?}", name));
+    }
+    Ok(portable_name(trimmed, MAX_COMPONENT_BYTES))
+}
+
+/// Where a download goes: `directory` must be `root` or below it, each folder under the
+/// root is made portable and the file name is checked, then shortened if the whole path
+/// would exceed MAX_PATH_CHARS. Titles scraped from VK end up in both, never trust them.
+pub fn resolve_under(root: &Path, directory: &Path, file_name: &str) -> Result<PathBuf> {
+    let root = normalize(root);
+    if !root.is_absolute() {
+        return Err(anyhow!("Download folder must be an absolute path"));
+    }
+    let relative = relative_to(&root, &normalize(directory))
+        .ok_or_else(|| anyhow!("Directory is outside the download folder: {}", directory.display()))?;
+
+    let mut path = root;
+    for component in relative.components() {
+        path.push(portable_name(&component.as_os_str().to_string_lossy(), MAX_COMPONENT_BYTES));
+    }
+
+    let mut name = checked_file_name(file_name)?;
+    let dir_chars = path.to_string_lossy().chars().count() + 1;
+    if dir_chars + name.chars().count() > MAX_PATH_CHARS {
+        let budget = MAX_PATH_CHARS.saturating_sub(dir_chars);
+        if budget < MIN_NAME_CHARS {
+            return Err(anyhow!("Path too long: {}", path.display()));
+        }
+        name = shorten_name(&name, budget);
+    }
+    Ok(path.join(name))
+}
+
+/// `target` must be inside `root` (the configured download folder) for the file commands
+pub fn ensure_within(root: &str, target: &str) -> Result<PathBuf> {
+    if root.trim().is_empty() {
+        return Err(anyhow!("Download folder is not configured"));
+    }
+    let target = normalize(Path::new(target));
+    relative_to(&normalize(Path::new(root)), &target)
+        .ok_or_else(|| anyhow!("Path is outside the download folder: {}", target.display()))?;
+    Ok(target)
+}
+
+/// `name.ext`, else `name (2).ext`, `name (3).ext`... the first one not `taken`
+pub fn unique_path(path: &Path, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    if !taken(path) {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (2..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, ext)))
+        .find(|candidate| !taken(candidate))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpace {
+    pub path: String,
+    pub total: u64,
+    pub available: u64, // What this user can still write (quotas, reserved blocks)
+    pub used: u64,
+}
+
+/// Space of the volume holding `path`. The folder may not exist yet
+/// (first download of a series): its nearest existing parent is queried.
+pub fn disk_space(path: &Path) -> Result<DiskSpace> {
+    let existing = path.ancestors()
+        .find(|p| !p.as_os_str().is_empty() && p.exists())
+        .ok_or_else(|| anyhow!("No existing folder for {}", path.display()))?;
+    let total = fs2::total_space(existing)?;
+    let available = fs2::available_space(existing)?;
+    let free = fs2::free_space(existing)?;
+    Ok(DiskSpace {
+        path: path.to_string_lossy().to_string(),
+        total,
+        available,
+        used: total.saturating_sub(free),
+    })
+}
+
+// Lexical only: "." and ".." are resolved without touching the disk (symlinks are trusted)
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+fn relative_to(root: &Path, path: &Path) -> Option<PathBuf> {
+    let mut rest = path.components();
+    for expected in root.components() {
+        if !same_component(expected.as_os_str(), rest.next()?.as_os_str()) {
+            return None;
+        }
+    }
+    Some(rest.as_path().to_path_buf())
+}
+
+fn same_component(a: &OsStr, b: &OsStr) -> bool {
+    // NTFS ne tient pas compte de la casse
+    if cfg!(windows) {
+        a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+    } else {
+        a == b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_paths_stay_under_the_root() {
+        let root = std::env::temp_dir().join("vkomic_root");
+        let ok = resolve_under(&root, &root.join("Blacksad."), "CON.cbz").unwrap();
+        assert_eq!(ok, root.join("Blacksad").join("_CON.cbz"));
+        assert_eq!(resolve_under(&root, &root, "AC/DC: live?.pdf").unwrap(), root.join("AC_DC_ live_.pdf"));
+
+        assert!(resolve_under(&root, &root.join("../elsewhere"), "a.cbz").is_err());
+        assert!(resolve_under(&root, &root.join("a/../../.."), "a.cbz").is_err());
+        assert!(resolve_under(&root, &root, "../a.cbz").is_err());
+        assert!(resolve_under(&root, &root, "/etc/passwd").is_err());
+        assert!(resolve_under(&root, &root, "C:\\Windows\\a.exe").is_err());
+        assert!(resolve_under(Path::new("relative"), Path::new("relative"), "a.cbz").is_err());
+
+        let long = resolve_under(&root, &root, &format!("{}.cbz", "é".repeat(300))).unwrap();
+        assert!(long.to_string_lossy().chars().count() <= MAX_PATH_CHARS);
+        assert!(long.to_string_lossy().ends_with(".cbz"));
+
+        assert!(ensure_within(&root.to_string_lossy(), &root.join("x/y.cbz").to_string_lossy()).is_ok());
+        assert!(ensure_within(&root.to_string_lossy(), "/tmp").is_err());
+        assert!(ensure_within("", &root.to_string_lossy()).is_err());
+
+        let taken = [root.join("a.cbz"), root.join("a (2).cbz")];
+        assert_eq!(unique_path(&root.join("a.cbz"), |p| taken.iter().any(|t| t == p)), root.join("a (3).cbz"));
+    }
+}
@@ -0,0 +1,470 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Bump when HistoryRecord changes incompatibly
+const HISTORY_VERSION: u32 = 1;
+// Oldest attempts are dropped past this, the file is rewritten after each download
+const MAX_RECORDS: usize = 20_000;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const DAY_MILLIS: f64 = 86_400_000.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryResult {
+    Completed,
+    Skipped, // Already on disk (conflict policy or same document in another series)
+    Failed,
+    Cancelled,
+    Interrupted, // Low disk space, the task went back to the queue
+}
+
+impl HistoryResult {
+    pub fn is_success(self) -> bool {
+        matches!(self, HistoryResult::Completed | HistoryResult::Skipped)
+    }
+
+    /// Cancelled and interrupted attempts say nothing about the file itself
+    fn counts_for_rate(self) -> bool {
+        !matches!(self, HistoryResult::Cancelled | HistoryResult::Interrupted)
+    }
+}
+
+/// One download attempt, successful or not
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    pub task_id: String,
+    pub doc_id: Option<String>, // VK "owner_doc"
+    pub url: String,
+    pub path: Option<String>,
+    pub series: Option<String>,
+    pub size: Option<u64>, // File size on disk
+    pub received: u64,     // Bytes downloaded by this attempt (less than size when resumed)
+    pub started_at: f64,
+    pub finished_at: f64,
+    pub duration_ms: u64,
+    pub average_speed: f64, // Bytes per second
+    pub result: HistoryResult,
+    pub error: Option<String>,
+}
+
+/// download_history.json in the app data dir, oldest first
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStore {
+    pub version: u32,
+    pub records: VecDeque<HistoryRecord>,
+}
+
+/// Every field set must match; records come newest first
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryQuery {
+    pub result: Option<HistoryResult>,
+    pub series: Option<String>,
+    pub doc_id: Option<String>,
+    pub search: Option<String>, // In the path, URL and series, case-insensitive
+    pub since: Option<f64>,     // finished_at, ms since epoch
+    pub until: Option<f64>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPage {
+    pub total: usize, // Matching records, before paging
+    pub records: Vec<HistoryRecord>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStatsQuery {
+    pub since: Option<f64>,
+    pub until: Option<f64>,
+    pub utc_offset_minutes: Option<i32>, // Local time minus UTC, days are cut at local midnight
+    pub top: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DayBytes {
+    pub day: String, // YYYY-MM-DD
+    pub bytes: u64,
+    pub completed: usize,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesCount {
+    pub series: String,
+    pub downloads: usize,
+    pub bytes: u64,
+}
+
+/// A document (or URL when the doc id is unknown) that failed more than once
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RepeatedFailure {
+    pub key: String,
+    pub failures: usize,
+    pub last_error: Option<String>,
+    pub last_path: Option<String>,
+    pub last_attempt: f64,
+    pub since_succeeded: bool, // A later attempt worked
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    pub attempts: usize,
+    pub completed: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub success_rate: f64, // 0..1, cancelled and interrupted attempts left out
+    pub total_bytes: u64,  // Received by every attempt
+    pub bytes_per_day: Vec<DayBytes>,
+    pub top_series: Vec<SeriesCount>,
+    pub repeated_failures: Vec<RepeatedFailure>,
+}
+
+impl HistoryQuery {
+    fn matches(&self, record: &HistoryRecord) -> bool {
+        let contains = |value: &Option<String>, needle: &str| {
+            value
+                .as_ref()
+                .is_some_and(|v| v.to_lowercase().contains(needle))
+        };
+        let search = self.search.as_ref().map(|s| s.trim().to_lowercase());
+        self.result.is_none_or(|r| r == record.result)
+            && self.series.as_ref().is_none_or(|series| {
+                record
+                    .series
+                    .as_ref()
+                    .is_some_and(|s| s.to_lowercase() == series.trim().to_lowercase())
+            })
+            && self
+                .doc_id
+                .as_ref()
+                .is_none_or(|id| record.doc_id.as_ref() == Some(id))
+            && search.is_none_or(|needle| {
+                record.url.to_lowercase().contains(&needle)
+                    || contains(&record.path, &needle)
+                    || contains(&record.series, &needle)
+            })
+            && self.since.is_none_or(|since| record.finished_at >= since)
+            && self.until.is_none_or(|until| record.finished_at < until)
+    }
+}
+
+impl HistoryStore {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<HistoryStore>(&content).ok())
+            .filter(|store| store.version == HISTORY_VERSION)
+            .unwrap_or_else(|| HistoryStore {
+                version: HISTORY_VERSION,
+                records: VecDeque::new(),
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn push(&mut self, record: HistoryRecord) {
+        self.records.push_back(record);
+        while self.records.len() > MAX_RECORDS {
+            self.records.pop_front();
+        }
+    }
+
+    /// Newest attempt that wrote (or would have written) to `path`
+    pub fn last_at_path(&self, path: &str) -> Option<&HistoryRecord> {
+        self.records
+            .iter()
+            .rev()
+            .find(|r| r.path.as_deref() == Some(path))
+    }
+
+    pub fn query(&self, query: &HistoryQuery) -> HistoryPage {
+        let matching = self.records.iter().rev().filter(|r| query.matches(r));
+        let total = matching.clone().count();
+        let records = matching
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(DEFAULT_PAGE_SIZE))
+            .cloned()
+            .collect();
+        HistoryPage { total, records }
+    }
+
+    pub fn stats(&self, query: &HistoryStatsQuery) -> HistoryStats {
+        let offset_ms = query.utc_offset_minutes.unwrap_or(0) as f64 * 60_000.0;
+        let top = query.top.unwrap_or(10);
+        let mut stats = HistoryStats::default();
+        let mut days: BTreeMap<i64, DayBytes> = BTreeMap::new();
+        let mut series: HashMap<String, SeriesCount> = HashMap::new();
+        let mut failures: HashMap<String, RepeatedFailure> = HashMap::new();
+        let (mut rated, mut succeeded) = (0, 0);
+
+        let range = HistoryQuery {
+            since: query.since,
+            until: query.until,
+            ..Default::default()
+        };
+        for record in self.records.iter().filter(|r| range.matches(r)) {
+            stats.attempts += 1;
+            stats.total_bytes += record.received;
+            match record.result {
+                HistoryResult::Completed => stats.completed += 1,
+                HistoryResult::Skipped => stats.skipped += 1,
+                HistoryResult::Failed => stats.failed += 1,
+                HistoryResult::Cancelled => stats.cancelled += 1,
+                HistoryResult::Interrupted => {}
+            }
+            if record.result.counts_for_rate() {
+                rated += 1;
+                succeeded += record.result.is_success() as usize;
+            }
+
+            let day = ((record.finished_at + offset_ms) / DAY_MILLIS).floor() as i64;
+            let entry = days.entry(day).or_insert_with(|| DayBytes {
+                day: civil_date(day),
+                bytes: 0,
+                completed: 0,
+            });
+            entry.bytes += record.received;
+            entry.completed += (record.result == HistoryResult::Completed) as usize;
+
+            if record.result == HistoryResult::Completed {
+                if let Some(name) = &record.series {
+                    let entry = series
+                        .entry(name.to_lowercase())
+                        .or_insert_with(|| SeriesCount {
+                            series: name.clone(),
+                            downloads: 0,
+                            bytes: 0,
+                        });
+                    entry.downloads += 1;
+                    entry.bytes += record.size.unwrap_or(record.received);
+                }
+            }
+
+            let key = record.doc_id.clone().unwrap_or_else(|| record.url.clone());
+            if record.result == HistoryResult::Failed {
+                let entry = failures
+                    .entry(key)
+                    .or_insert_with_key(|key| RepeatedFailure {
+                        key: key.clone(),
+                        failures: 0,
+                        last_error: None,
+                        last_path: None,
+                        last_attempt: 0.0,
+                        since_succeeded: false,
+                    });
+                entry.failures += 1;
+                entry.last_error = record.error.clone();
+                entry.last_path = record.path.clone().or(entry.last_path.take());
+                entry.last_attempt = record.finished_at;
+                entry.since_succeeded = false;
+            } else if record.result.is_success() {
+                if let Some(entry) = failures.get_mut(&key) {
+                    entry.since_succeeded = true;
+                }
+            }
+        }
+
+        stats.success_rate = if rated > 0 {
+            succeeded as f64 / rated as f64
+        } else {
+            0.0
+        };
+        stats.bytes_per_day = days.into_values().collect();
+
+        let mut series: Vec<SeriesCount> = series.into_values().collect();
+        series.sort_by(|a, b| b.downloads.cmp(&a.downloads).then(b.bytes.cmp(&a.bytes)));
+        series.truncate(top);
+        stats.top_series = series;
+
+        let mut failures: Vec<RepeatedFailure> =
+            failures.into_values().filter(|f| f.failures > 1).collect();
+        failures.sort_by(|a, b| {
+            b.failures
+                .cmp(&a.failures)
+                .then(b.last_attempt.total_cmp(&a.last_attempt))
+        });
+        stats.repeated_failures = failures;
+        stats
+    }
+}
+
+/// YYYY-MM-DD of a day number since 1970-01-01 (proleptic Gregorian calendar)
+fn civil_date(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Clone, Default)]
+pub struct DownloadHistory {
+    store: Arc<Mutex<Option<HistoryStore>>>,
+}
+
+impl DownloadHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_store<R>(&self, store_path: &Path, f: impl FnOnce(&mut HistoryStore) -> R) -> R {
+        let mut guard = self.store.lock().unwrap_or_else(|e| e.into_inner());
+        let store = guard.get_or_insert_with(|| HistoryStore::load(store_path));
+        f(store)
+    }
+
+    pub fn record(&self, store_path: &Path, record: HistoryRecord) -> Result<()> {
+        self.with_store(store_path, |store| {
+            store.push(record);
+            store.save(store_path)
+        })
+    }
+
+    pub fn last_at_path(&self, store_path: &Path, path: &str) -> Option<HistoryRecord> {
+        self.with_store(store_path, |store| store.last_at_path(path).cloned())
+    }
+
+    pub fn query(&self, store_path: &Path, query: &HistoryQuery) -> HistoryPage {
+        self.with_store(store_path, |store| store.query(query))
+    }
+
+    pub fn stats(&self, store_path: &Path, query: &HistoryStatsQuery) -> HistoryStats {
+        self.with_store(store_path, |store| store.stats(query))
+    }
+
+    pub fn clear(&self, store_path: &Path) -> Result<usize> {
+        self.with_store(store_path, |store| {
+            let count = store.records.len();
+            store.records.clear();
+            store.save(store_path)?;
+            Ok(count)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(doc: &str, series: &str, result: HistoryResult, finished_at: f64) -> HistoryRecord {
+        HistoryRecord {
+            task_id: doc.to_string(),
+            doc_id: Some(doc.to_string()),
+            url: format!("https://vk.com/doc{}", doc),
+            path: Some(format!("/bd/{}/{}.cbz", series, doc)),
+            series: Some(series.to_string()),
+            size: Some(1_000),
+            received: if result == HistoryResult::Completed {
+                1_000
+            } else {
+                0
+            },
+            started_at: finished_at - 2_000.0,
+            finished_at,
+            duration_ms: 2_000,
+            average_speed: 500.0,
+            result,
+            error: (result == HistoryResult::Failed).then(|| "HTTP 404".to_string()),
+        }
+    }
+
+    #[test]
+    fn history_is_paged_and_aggregated() {
+        use HistoryResult::*;
+        // 2024-03-01T00:00:00Z
+        let day = 1_709_251_200_000.0;
+        let mut store = HistoryStore::default();
+        store.push(record("-1_1", "Blacksad", Completed, day + 1_000.0));
+        store.push(record("-1_2", "Blacksad", Failed, day + 2_000.0));
+        store.push(record(
+            "-1_2",
+            "Blacksad",
+            Failed,
+            day + DAY_MILLIS + 1_000.0,
+        ));
+        store.push(record(
+            "-1_3",
+            "Blake et Mortimer",
+            Completed,
+            day + DAY_MILLIS + 2_000.0,
+        ));
+        store.push(record(
+            "-1_4",
+            "Blacksad",
+            Cancelled,
+            day + DAY_MILLIS + 3_000.0,
+        ));
+
+        let page = store.query(&HistoryQuery {
+            series: Some("blacksad".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        });
+        assert_eq!(page.total, 4);
+        let ids: Vec<&str> = page.records.iter().map(|r| r.task_id.as_str()).collect();
+        assert_eq!(ids, vec!["-1_4", "-1_2"]);
+        let failed = store.query(&HistoryQuery {
+            result: Some(Failed),
+            search: Some("DOC-1_2".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(failed.total, 2);
+
+        let stats = store.stats(&HistoryStatsQuery::default());
+        assert_eq!((stats.attempts, stats.completed, stats.failed), (5, 2, 2));
+        // L'annulation ne compte pas : 2 réussites sur 4
+        assert_eq!(stats.success_rate, 0.5);
+        assert_eq!(
+            stats.bytes_per_day,
+            vec![
+                DayBytes {
+                    day: "2024-03-01".to_string(),
+                    bytes: 1_000,
+                    completed: 1
+                },
+                DayBytes {
+                    day: "2024-03-02".to_string(),
+                    bytes: 1_000,
+                    completed: 1
+                },
+            ]
+        );
+        assert_eq!(stats.top_series.len(), 2);
+        assert_eq!(stats.repeated_failures.len(), 1);
+        assert_eq!(stats.repeated_failures[0].key, "-1_2");
+        assert_eq!(stats.repeated_failures[0].failures, 2);
+
+        // UTC-5 : le premier jour est encore le 29 février en heure locale
+        let local = store.stats(&HistoryStatsQuery {
+            utc_offset_minutes: Some(-300),
+            ..Default::default()
+        });
+        assert_eq!(local.bytes_per_day[0].day, "2024-02-29");
+    }
+}
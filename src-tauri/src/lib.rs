@@ -1,17 +1,53 @@
 mod download;
 mod fs_ops;
+mod history;
+mod library;
+mod naming;
 mod vk_api;
 mod vk_parser;
 mod settings;
-
-use crate::download::{DownloadManager, DownloadTask};
-use crate::fs_ops::{list_directory, open_path, reveal_path, DirList};
+#[cfg(test)]
+mod test_util;
+use crate::download::{
+    AlbumSource, ConflictPolicy, DownloadManager, DownloadPart, DownloadPriority, DownloadRequest,
+    DownloadTask, QueueFilter, QueueMove, QueueState,
+};
+use crate::fs_ops::{
+    disk_space, ensure_within, list_directory, open_path, reveal_path, DirList, DiskSpace,
+};
+use crate::history::{HistoryPage, HistoryQuery, HistoryStats, HistoryStatsQuery};
+use crate::library::archive::{self, ArchiveCheck};
+use crate::library::comicinfo::{ComicInfo, ComicInfoSource};
+use crate::library::convert::{ConversionJob, ConversionManager};
+use crate::library::progress::{FolderProgress, ReadingProgress, ReadingState};
+use crate::library::watcher::LibraryWatcher;
+use crate::library::{Library, LibraryEntry, LibraryQuery, ScanSummary, SeriesSummary};
+use crate::naming::{NamingContext, NamingTemplate, ResolvedPath};
 use crate::vk_api::VkApi;
-use crate::vk_parser::VkNode;
-use tauri::{AppHandle, State};
+use crate::vk_parser::duplicates::{mark_duplicates, DuplicateScan};
+use crate::vk_parser::report::{build_series_report, SeriesReport};
+use crate::vk_parser::rules::RulesSummary;
+use crate::vk_parser::{BoardTopic, VkNode};
+use tauri::{AppHandle, Manager, State};
 
 struct AppState {
     download_manager: DownloadManager,
+    library: Library,
+    library_watcher: LibraryWatcher,
+    reading: ReadingProgress,
+    conversions: ConversionManager,
+}
+
+/// (Re)start watching the download root for the library index
+fn watch_library(app: &AppHandle, state: &AppState, root: &str) -> anyhow::Result<()> {
+    let index_path = settings::library_index_path(app)
+        .ok_or_else(|| anyhow::anyhow!("Cannot resolve app data dir"))?;
+    state.library_watcher.start(
+        app.clone(),
+        state.library.clone(),
+        index_path,
+        std::path::PathBuf::from(root),
+    )
 }
 
 #[tauri::command]
@@ -39,9 +75,12 @@ async fn vk_fetch_full_index(
     topic_id: String,
 ) -> Result<Vec<VkNode>, String> {
     let api = VkApi::new(token);
-    api.fetch_folder_tree_recursive(&group_id, &topic_id, 4)
+    let tree = api
+        .fetch_folder_tree_recursive(&group_id, &topic_id, 4)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    // Documents ré-attachés dans plusieurs topics : marqués dès la synchro
+    Ok(mark_duplicates(tree).tree)
 }
 
 #[tauri::command]
@@ -49,9 +88,10 @@ async fn vk_fetch_node_content(
     token: String,
     group_id: String,
     topic_id: String,
+    post_id: Option<String>,
 ) -> Result<VkNode, String> {
     let api = VkApi::new(token);
-    api.fetch_node_content(&group_id, &topic_id)
+    api.fetch_node_content(&group_id, &topic_id, post_id.as_deref())
         .await
         .map_err(|e| e.to_string())
 }
@@ -69,21 +109,167 @@ async fn vk_refresh_counts(
 }
 
 #[tauri::command]
-async fn fs_list_directory(path: String) -> Result<DirList, String> {
+async fn vk_fetch_wall_content(token: String, group_id: String) -> Result<VkNode, String> {
+    let api = VkApi::new(token);
+    api.fetch_wall_content(&group_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vk_fetch_wall_post(
+    token: String,
+    owner_id: String,
+    post_id: String,
+) -> Result<VkNode, String> {
+    let api = VkApi::new(token);
+    api.fetch_wall_post_content(&owner_id, &post_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vk_fetch_albums(token: String, group_id: String) -> Result<Vec<VkNode>, String> {
+    let api = VkApi::new(token);
+    api.fetch_albums(&group_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vk_fetch_board_topics(token: String, group_id: String) -> Result<Vec<BoardTopic>, String> {
+    let api = VkApi::new(token);
+    api.fetch_board_topics(&group_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vk_find_orphan_topics(
+    token: String,
+    group_id: String,
+    topic_id: String,
+    tree: Vec<VkNode>,
+) -> Result<Vec<BoardTopic>, String> {
+    let api = VkApi::new(token);
+    api.find_orphan_topics(&group_id, &topic_id, &tree)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn vk_series_report(
+    app: AppHandle,
+    series: VkNode,
+    directory: Option<String>,
+) -> Result<SeriesReport, String> {
+    let directory = directory.map(|dir| checked_path(&app, &dir)).transpose()?;
+    // Dossier absent = rien de téléchargé pour cette série
+    let local_files = match directory {
+        Some(dir) if std::path::Path::new(&dir).is_dir() => list_directory(&dir)
+            .map_err(|e| e.to_string())?
+            .entries
+            .into_iter()
+            .filter(|e| !e.is_directory)
+            .map(|e| e.name)
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(build_series_report(&series, &local_files))
+}
+
+#[tauri::command]
+async fn vk_mark_duplicates(tree: Vec<VkNode>) -> Result<DuplicateScan, String> {
+    Ok(mark_duplicates(tree))
+}
+
+/// Configured download folder: downloads and file commands never leave it
+fn download_root(app: &AppHandle) -> Option<String> {
+    configured_root(app, &settings::load_settings(app))
+}
+
+/// Download folder from the settings, else "<Downloads>/vkomic"
+fn configured_root(app: &AppHandle, settings: &settings::AppSettings) -> Option<String> {
+    Some(settings.vk_download_path.clone())
+        .filter(|root| !root.trim().is_empty())
+        .or_else(|| {
+            let dir = app.path().download_dir().ok()?.join("vkomic");
+            Some(dir.to_string_lossy().to_string())
+        })
+}
+
+fn checked_path(app: &AppHandle, path: &str) -> Result<String, String> {
+    let root = download_root(app).unwrap_or_default();
+    ensure_within(&root, path)
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn fs_list_directory(app: AppHandle, path: String) -> Result<DirList, String> {
+    let path = checked_path(&app, &path)?;
     list_directory(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn fs_open_path(path: String) -> Result<(), String> {
+async fn fs_open_path(app: AppHandle, path: String) -> Result<(), String> {
+    let path = checked_path(&app, &path)?;
     open_path(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn fs_reveal_path(path: String) -> Result<(), String> {
+async fn fs_reveal_path(app: AppHandle, path: String) -> Result<(), String> {
+    let path = checked_path(&app, &path)?;
     reveal_path(&path).map_err(|e| e.to_string())
 }
 
+/// Where a document goes under `root` according to the naming template
+/// (`template`, or the saved one). None when no template is configured.
+#[tauri::command]
+async fn fs_resolve_download_path(
+    app: AppHandle,
+    root: String,
+    title: String,
+    extension: Option<String>,
+    ancestors: Vec<String>,
+    template: Option<String>,
+) -> Result<Option<ResolvedPath>, String> {
+    let template = template.unwrap_or_else(|| settings::load_settings(&app).naming_template);
+    if template.trim().is_empty() {
+        return Ok(None);
+    }
+    let template = NamingTemplate::parse(&template).map_err(|e| e.to_string())?;
+    let context = NamingContext::from_document(&title, extension.as_deref(), &ancestors);
+    Ok(Some(
+        template.resolve(std::path::Path::new(&root), &context),
+    ))
+}
+
+/// Task for a VK document, missing choices taken from the saved settings
+fn document_task(
+    app: &AppHandle,
+    settings: &settings::AppSettings,
+    request: DownloadRequest,
+) -> DownloadTask {
+    DownloadTask {
+        id: request.id,
+        url: request.url,
+        directory: request.directory,
+        file_name: request.file_name,
+        token: request.token,
+        album: None,
+        parts: None,
+        doc_key: request.doc_key,
+        size: request.size,
+        library_root: configured_root(app, settings),
+        comic_info: request.comic_info.as_ref().map(ComicInfo::for_document),
+        // Sans choix explicite pour la tâche, le réglage global s'applique
+        conflict_policy: request.conflict_policy.unwrap_or(settings.conflict_policy),
+        priority: request.priority.unwrap_or_default(),
+        resume_at: None,
+    }
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn fs_queue_download(
     app: AppHandle,
     state: State<'_, AppState>,
@@ -92,13 +278,109 @@ async fn fs_queue_download(
     directory: String,
     file_name: String,
     token: Option<String>,
+    doc_key: Option<String>,
+    size: Option<u64>,
+    comic_info: Option<ComicInfoSource>,
+    conflict_policy: Option<ConflictPolicy>,
+    priority: Option<DownloadPriority>,
 ) -> Result<(), String> {
-    let task = DownloadTask {
+    let request = DownloadRequest {
         id,
         url,
         directory,
         file_name,
         token,
+        doc_key,
+        size,
+        comic_info,
+        conflict_policy,
+        priority,
+    };
+    let task = document_task(&app, &settings::load_settings(&app), request);
+    state.download_manager.add_task(app, task).await;
+    Ok(())
+}
+
+/// Queue many documents (a whole series) with a single download-queue-changed event
+#[tauri::command]
+async fn fs_queue_downloads(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    requests: Vec<DownloadRequest>,
+) -> Result<usize, String> {
+    let settings = settings::load_settings(&app);
+    let tasks = requests
+        .into_iter()
+        .map(|request| document_task(&app, &settings, request))
+        .collect();
+    Ok(state.download_manager.add_tasks(app, tasks).await)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn fs_queue_album_download(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    owner_id: String,
+    album_id: String,
+    directory: String,
+    file_name: String,
+    token: String,
+    comic_info: Option<ComicInfoSource>,
+    conflict_policy: Option<ConflictPolicy>,
+    priority: Option<DownloadPriority>,
+) -> Result<(), String> {
+    let task = DownloadTask {
+        id,
+        url: format!("https://vk.com/album{}_{}", owner_id, album_id),
+        directory,
+        file_name,
+        token: Some(token),
+        album: Some(AlbumSource { owner_id, album_id }),
+        parts: None,
+        doc_key: None,
+        size: None,
+        library_root: download_root(&app),
+        comic_info: comic_info.as_ref().map(ComicInfo::for_document),
+        conflict_policy: conflict_policy
+            .unwrap_or_else(|| settings::load_settings(&app).conflict_policy),
+        priority: priority.unwrap_or_default(),
+        resume_at: None,
+    };
+    state.download_manager.add_task(app, task).await;
+    Ok(())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn fs_queue_multipart_download(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    parts: Vec<DownloadPart>,
+    directory: String,
+    token: Option<String>,
+    conflict_policy: Option<ConflictPolicy>,
+    priority: Option<DownloadPriority>,
+) -> Result<(), String> {
+    let first = parts.first().ok_or("No parts to download")?;
+    let task = DownloadTask {
+        id,
+        url: first.url.clone(),
+        directory,
+        file_name: first.file_name.clone(),
+        token,
+        album: None,
+        parts: Some(parts),
+        doc_key: None,
+        size: None,
+        library_root: download_root(&app),
+        comic_info: None,
+        conflict_policy: conflict_policy
+            .unwrap_or_else(|| settings::load_settings(&app).conflict_policy),
+        priority: priority.unwrap_or_default(),
+        resume_at: None,
     };
     state.download_manager.add_task(app, task).await;
     Ok(())
@@ -123,21 +405,345 @@ async fn fs_clear_download_queue(
     Ok(count)
 }
 
+#[tauri::command]
+async fn fs_get_queue_state(state: State<'_, AppState>) -> Result<QueueState, String> {
+    Ok(state.download_manager.queue_state().await)
+}
+
+/// Move a waiting task to the top, the bottom or a given position of the queue
+#[tauri::command]
+async fn fs_move_download(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    to: QueueMove,
+) -> Result<bool, String> {
+    Ok(state.download_manager.move_task(app, &id, to).await)
+}
+
+#[tauri::command]
+async fn fs_set_download_priority(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    priority: DownloadPriority,
+) -> Result<bool, String> {
+    Ok(state
+        .download_manager
+        .set_priority(app, &id, priority)
+        .await)
+}
+
+/// Cancel the queued / running tasks matching `filter`, returns their ids
+#[tauri::command]
+async fn fs_cancel_downloads(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    filter: QueueFilter,
+) -> Result<Vec<String>, String> {
+    Ok(state.download_manager.cancel_matching(app, &filter).await)
+}
+
+/// Past download attempts, newest first
+#[tauri::command]
+async fn history_query(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    query: HistoryQuery,
+) -> Result<HistoryPage, String> {
+    let store_path = settings::download_history_path(&app).ok_or("Cannot resolve app data dir")?;
+    Ok(state.download_manager.history().query(&store_path, &query))
+}
+
+/// Bytes per day, success rate, top series and documents that keep failing
+#[tauri::command]
+async fn history_stats(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    query: HistoryStatsQuery,
+) -> Result<HistoryStats, String> {
+    let store_path = settings::download_history_path(&app).ok_or("Cannot resolve app data dir")?;
+    Ok(state.download_manager.history().stats(&store_path, &query))
+}
+
+#[tauri::command]
+async fn history_clear(app: AppHandle, state: State<'_, AppState>) -> Result<usize, String> {
+    let store_path = settings::download_history_path(&app).ok_or("Cannot resolve app data dir")?;
+    state
+        .download_manager
+        .history()
+        .clear(&store_path)
+        .map_err(|e| e.to_string())
+}
+
+/// Free and used space of the volume holding the download folder
+#[tauri::command]
+async fn fs_get_disk_space(app: AppHandle) -> Result<DiskSpace, String> {
+    let root = download_root(&app).ok_or("Download folder is not configured")?;
+    disk_space(std::path::Path::new(&root)).map_err(|e| e.to_string())
+}
+
+/// Restart a queue paused by download-low-space
+#[tauri::command]
+async fn fs_resume_download_queue(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    state.download_manager.resume_queue(app).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn library_scan(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    root: String,
+) -> Result<ScanSummary, String> {
+    let root = checked_path(&app, &root)?;
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    let library = state.library.clone();
+    // Le hash des nouveaux fichiers peut prendre du temps : hors du runtime async
+    tokio::task::spawn_blocking(move || library.scan(&index_path, std::path::Path::new(&root)))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn library_query(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    query: LibraryQuery,
+) -> Result<Vec<LibraryEntry>, String> {
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    Ok(state.library.query(&index_path, &query))
+}
+
+#[tauri::command]
+async fn library_series(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<SeriesSummary>, String> {
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    Ok(state.library.series(&index_path))
+}
+
+#[tauri::command]
+async fn library_check_archive(app: AppHandle, path: String) -> Result<ArchiveCheck, String> {
+    let path = checked_path(&app, &path)?;
+    // Décompression complète : hors du runtime async
+    tokio::task::spawn_blocking(move || archive::check(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn library_convert_to_cbz(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+    source: String,
+    delete_source: Option<bool>,
+) -> Result<(), String> {
+    // La conversion peut supprimer la source : jamais hors du dossier de téléchargement
+    let source = checked_path(&app, &source)?;
+    let job = ConversionJob {
+        id,
+        source,
+        delete_source: delete_source.unwrap_or(false),
+    };
+    state.conversions.add_job(app, job);
+    Ok(())
+}
+
+#[tauri::command]
+async fn library_cancel_conversion(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<bool, String> {
+    Ok(state.conversions.cancel_job(app, &id))
+}
+
+#[tauri::command]
+async fn library_watch_start(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    root: String,
+) -> Result<(), String> {
+    let root = checked_path(&app, &root)?;
+    watch_library(&app, &state, &root).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn library_watch_stop(state: State<'_, AppState>) -> Result<(), String> {
+    state.library_watcher.stop();
+    Ok(())
+}
+
+#[tauri::command]
+async fn reading_update(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    page: usize,
+    total_pages: usize,
+) -> Result<ReadingState, String> {
+    let path = checked_path(&app, &path)?;
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    let store_path = settings::reading_progress_path(&app).ok_or("Cannot resolve app data dir")?;
+    let (library, reading) = (state.library.clone(), state.reading.clone());
+    // Un fichier absent de l'index doit être haché : hors du runtime async
+    tokio::task::spawn_blocking(move || {
+        let hash = library.content_hash(&index_path, std::path::Path::new(&path))?;
+        reading.update(&store_path, &hash, &path, page, total_pages)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reading_set_completed(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    completed: bool,
+) -> Result<ReadingState, String> {
+    let path = checked_path(&app, &path)?;
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    let store_path = settings::reading_progress_path(&app).ok_or("Cannot resolve app data dir")?;
+    let (library, reading) = (state.library.clone(), state.reading.clone());
+    tokio::task::spawn_blocking(move || {
+        let hash = library.content_hash(&index_path, std::path::Path::new(&path))?;
+        reading.set_completed(&store_path, &hash, &path, completed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reading_get(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+) -> Result<std::collections::HashMap<String, ReadingState>, String> {
+    // Les chemins hors du dossier de téléchargement sont ignorés, comme les fichiers illisibles
+    let root = download_root(&app).unwrap_or_default();
+    let paths: Vec<String> = paths
+        .into_iter()
+        .filter(|path| ensure_within(&root, path).is_ok())
+        .collect();
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    let store_path = settings::reading_progress_path(&app).ok_or("Cannot resolve app data dir")?;
+    let (library, reading) = (state.library.clone(), state.reading.clone());
+    tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let hash = library
+                    .content_hash(&index_path, std::path::Path::new(&path))
+                    .ok()?;
+                let reading_state = reading.get(&store_path, &hash)?;
+                Some((path, reading_state))
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reading_recent(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<ReadingState>, String> {
+    let store_path = settings::reading_progress_path(&app).ok_or("Cannot resolve app data dir")?;
+    Ok(state.reading.recent(&store_path, limit.unwrap_or(20)))
+}
+
+#[tauri::command]
+async fn reading_folder_progress(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    directories: Vec<String>,
+) -> Result<Vec<FolderProgress>, String> {
+    let directories = directories
+        .iter()
+        .map(|dir| checked_path(&app, dir))
+        .collect::<Result<Vec<_>, _>>()?;
+    let index_path = settings::library_index_path(&app).ok_or("Cannot resolve app data dir")?;
+    let store_path = settings::reading_progress_path(&app).ok_or("Cannot resolve app data dir")?;
+    let (library, reading) = (state.library.clone(), state.reading.clone());
+    tokio::task::spawn_blocking(move || {
+        directories
+            .iter()
+            .map(|dir| {
+                let files = library.comic_hashes(&index_path, std::path::Path::new(dir));
+                reading.folder_progress(&store_path, dir, &files)
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn settings_load(app: AppHandle) -> Result<settings::AppSettings, String> {
     Ok(settings::load_settings(&app))
 }
 
 #[tauri::command]
-async fn settings_save(app: AppHandle, settings: settings::AppSettings) -> Result<(), String> {
-    settings::save_settings(&app, &settings).map_err(|e| e.to_string())
+async fn settings_save(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    settings: settings::AppSettings,
+) -> Result<(), String> {
+    let previous = settings::load_settings(&app).vk_download_path;
+    settings::save_settings(&app, &settings).map_err(|e| e.to_string())?;
+    // Nouveau dossier de téléchargement : on le suit. Sinon, pas de rescan à chaque sauvegarde
+    if settings.vk_download_path != previous && !settings.vk_download_path.is_empty() {
+        if let Err(e) = watch_library(&app, &state, &settings.vk_download_path) {
+            log::warn!("Library watcher not started: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn parser_reload_rules(app: AppHandle) -> Result<RulesSummary, String> {
+    settings::load_parsing_rules(&app).map_err(|e| e.to_string())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let library = Library::new();
     tauri::Builder::default()
         .manage(AppState {
-            download_manager: DownloadManager::new(),
+            download_manager: DownloadManager::new(library.clone()),
+            library,
+            library_watcher: LibraryWatcher::new(),
+            reading: ReadingProgress::new(),
+            conversions: ConversionManager::new(),
+        })
+        // Miniatures de couverture pour la grille de la bibliothèque
+        .register_asynchronous_uri_scheme_protocol("vkomic-thumb", |ctx, request, responder| {
+            let cache_dir = settings::thumbnail_cache_dir(ctx.app_handle());
+            let root = download_root(ctx.app_handle()).unwrap_or_default();
+            std::thread::spawn(move || {
+                let cache_dir =
+                    cache_dir.unwrap_or_else(|| std::env::temp_dir().join("vkomic_thumbnails"));
+                responder.respond(library::thumbnails::serve(&cache_dir, &request, &root));
+            });
+        })
+        // Lecteur intégré : liste des pages et images, sans visionneuse externe (Android)
+        .register_asynchronous_uri_scheme_protocol("vkomic-page", |ctx, request, responder| {
+            let root = download_root(ctx.app_handle()).unwrap_or_default();
+            std::thread::spawn(move || {
+                responder.respond(library::reader::serve(&request, &root));
+            });
         })
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
@@ -147,14 +753,48 @@ pub fn run() {
             vk_fetch_full_index,
             vk_fetch_node_content,
             vk_refresh_counts,
+            vk_fetch_wall_content,
+            vk_fetch_wall_post,
+            vk_fetch_albums,
+            vk_fetch_board_topics,
+            vk_find_orphan_topics,
+            vk_series_report,
+            vk_mark_duplicates,
             fs_list_directory,
             fs_open_path,
             fs_reveal_path,
+            fs_resolve_download_path,
             fs_queue_download,
+            fs_queue_downloads,
+            fs_queue_album_download,
+            fs_queue_multipart_download,
             fs_cancel_download,
             fs_clear_download_queue,
+            fs_get_queue_state,
+            fs_move_download,
+            fs_set_download_priority,
+            fs_cancel_downloads,
+            fs_get_disk_space,
+            fs_resume_download_queue,
+            history_query,
+            history_stats,
+            history_clear,
+            library_scan,
+            library_query,
+            library_series,
+            library_check_archive,
+            library_convert_to_cbz,
+            library_cancel_conversion,
+            library_watch_start,
+            library_watch_stop,
+            reading_update,
+            reading_set_completed,
+            reading_get,
+            reading_recent,
+            reading_folder_progress,
             settings_load,
-            settings_save
+            settings_save,
+            parser_reload_rules
         ])
         .setup(|app| {
             // Plugin HTTP pour les requêtes sans CORS
@@ -167,6 +807,19 @@ pub fn run() {
                         .build(),
                 )?;
             }
+
+            // Règles de parsing utilisateur : un fichier invalide ne bloque pas le démarrage
+            if let Err(e) = settings::load_parsing_rules(app.handle()) {
+                log::warn!("Parsing rules not loaded: {}", e);
+            }
+
+            let download_path = settings::load_settings(app.handle()).vk_download_path;
+            if !download_path.is_empty() {
+                let state = app.state::<AppState>();
+                if let Err(e) = watch_library(app.handle(), &state, &download_path) {
+                    log::warn!("Library watcher not started: {}", e);
+                }
+            }
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,584 @@
+use crate::vk_parser::duplicates::normalize_title;
+use crate::vk_parser::metadata::parse_title;
+use anyhow::Result;
+use comicinfo::ComicInfo;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+pub mod archive;
+pub mod comicinfo;
+pub mod convert;
+pub mod progress;
+pub mod reader;
+pub mod thumbnails;
+pub mod watcher;
+
+// Bump when LibraryEntry changes so old index files are rebuilt
+const INDEX_VERSION: u32 = 2;
+
+pub const COMIC_EXTENSIONS: &[&str] = &[
+    "cbz", "cbr", "cb7", "cbt", "pdf", "zip", "rar", "7z", "epub",
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryEntry {
+    pub path: String,
+    pub relative_path: String,
+    pub file_name: String,
+    pub folder: String, // Parent folder, relative to the root ("" at the root)
+    pub series: Option<String>,
+    pub volume: Option<u32>,
+    pub format: String,
+    pub size: u64,
+    pub modified_at: f64,
+    pub hash: String, // SHA-256 of the content
+    #[serde(default)]
+    pub comic_info: Option<ComicInfo>, // Embedded ComicInfo.xml
+}
+
+/// Persistent index of the download root (library_index.json in the app data dir)
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryIndex {
+    pub version: u32,
+    pub root: String,
+    pub scanned_at: f64,
+    pub entries: BTreeMap<String, LibraryEntry>, // Keyed by relative path
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanSummary {
+    pub root: String,
+    pub total: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LibraryQuery {
+    pub series: Option<String>,
+    pub format: Option<String>,
+    pub text: Option<String>,
+    pub modified_after: Option<f64>,
+    pub modified_before: Option<f64>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesSummary {
+    pub series: String,
+    pub files: usize,
+    pub total_size: u64,
+    pub formats: Vec<String>,
+    pub volumes: Vec<u32>,
+    pub last_modified: f64,
+}
+
+pub(crate) fn now_millis() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as f64)
+        .unwrap_or(0.0)
+}
+
+fn is_comic_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| COMIC_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Size and mtime (ms) of a file
+pub(crate) fn file_stat(path: &Path) -> Option<(u64, f64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as f64)
+        .unwrap_or(0.0);
+    Some((metadata.len(), modified_at))
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn normalize(text: &str) -> String {
+    text.trim().to_lowercase()
+}
+
+impl LibraryIndex {
+    /// Missing, unreadable or outdated index files give an empty index
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<LibraryIndex>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Écriture atomique : un crash pendant la sauvegarde ne corrompt pas l'index
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn build_entry(root: &Path, path: &Path, size: u64, modified_at: f64) -> Result<LibraryEntry> {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let folder = relative
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let meta = parse_title(&file_name);
+        // ComicInfo.xml illisible : on retombe sur le nom de fichier
+        let comic_info =
+            archive::ArchiveFormat::detect(path).and_then(|_| comicinfo::read(path).ok().flatten());
+        // Ordre de priorité : ComicInfo, nom de fichier, dossier de la série
+        let series = comic_info
+            .as_ref()
+            .and_then(|info| info.series.clone())
+            .or_else(|| meta.series.clone())
+            .or_else(|| {
+                relative
+                    .parent()
+                    .and_then(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+            });
+        let volume = comic_info
+            .as_ref()
+            .and_then(|info| info.number.as_deref())
+            .and_then(|n| n.trim().parse::<f64>().ok())
+            .map(|n| n as u32)
+            .or_else(|| meta.number());
+
+        Ok(LibraryEntry {
+            path: path.to_string_lossy().to_string(),
+            relative_path: relative.to_string_lossy().to_string(),
+            format: path
+                .extension()
+                .map(|e| e.to_string_lossy().to_uppercase())
+                .unwrap_or_default(),
+            file_name,
+            folder,
+            series,
+            volume,
+            size,
+            modified_at,
+            hash: hash_file(path)?,
+            comic_info,
+        })
+    }
+
+    /// Rescan `root`. Files whose size and mtime didn't change keep their entry (and hash).
+    pub fn scan(&mut self, root: &Path) -> Result<ScanSummary> {
+        let start = Instant::now();
+        if !root.is_dir() {
+            return Err(anyhow::anyhow!("Path is not a directory"));
+        }
+
+        let root_str = root.to_string_lossy().to_string();
+        if self.root != root_str || self.version != INDEX_VERSION {
+            self.entries.clear();
+            self.root = root_str.clone();
+            self.version = INDEX_VERSION;
+        }
+
+        let mut summary = ScanSummary {
+            root: root_str,
+            ..Default::default()
+        };
+        let mut previous = std::mem::take(&mut self.entries);
+
+        for entry in WalkDir::new(root).follow_links(false).into_iter().flatten() {
+            if !entry.file_type().is_file() || !is_comic_file(entry.path()) {
+                continue;
+            }
+            let Some((size, modified_at)) = file_stat(entry.path()) else {
+                continue;
+            };
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .to_string();
+
+            match previous.remove(&relative) {
+                Some(old) if old.size == size && old.modified_at == modified_at => {
+                    summary.unchanged += 1;
+                    self.entries.insert(relative, old);
+                }
+                old => {
+                    // Fichier verrouillé ou supprimé entre-temps : on le reprendra au prochain scan
+                    let Ok(new_entry) = Self::build_entry(root, entry.path(), size, modified_at)
+                    else {
+                        continue;
+                    };
+                    if old.is_some() {
+                        summary.updated += 1;
+                    } else {
+                        summary.added += 1;
+                    }
+                    self.entries.insert(relative, new_entry);
+                }
+            }
+        }
+
+        summary.removed = previous.len();
+        summary.total = self.entries.len();
+        summary.duration_ms = start.elapsed().as_millis() as u64;
+        self.scanned_at = now_millis();
+        Ok(summary)
+    }
+
+    /// Bring the entries at or below `path` in line with the disk (watcher events).
+    /// Returns how many entries were added, updated or removed.
+    pub fn refresh_path(&mut self, root: &Path, path: &Path) -> usize {
+        let Ok(relative) = path.strip_prefix(root) else {
+            return 0;
+        };
+        let relative = relative.to_string_lossy().to_string();
+        if relative.is_empty() {
+            return 0;
+        }
+
+        // Gone: the file itself, or everything below a removed / renamed folder
+        let prefix = format!("{}{}", relative, std::path::MAIN_SEPARATOR);
+        let stale: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|k| (**k == relative || k.starts_with(&prefix)) && !root.join(k).is_file())
+            .cloned()
+            .collect();
+        let mut changed = stale.len();
+        for key in stale {
+            self.entries.remove(&key);
+        }
+
+        let files: Vec<PathBuf> = if path.is_dir() {
+            WalkDir::new(path)
+                .into_iter()
+                .flatten()
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.into_path())
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        for file in files {
+            if !is_comic_file(&file) {
+                continue;
+            }
+            let Some((size, modified_at)) = file_stat(&file) else {
+                continue;
+            };
+            let key = file
+                .strip_prefix(root)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .to_string();
+            let unchanged = self
+                .entries
+                .get(&key)
+                .is_some_and(|e| e.size == size && e.modified_at == modified_at);
+            if unchanged {
+                continue;
+            }
+            if let Ok(entry) = Self::build_entry(root, &file, size, modified_at) {
+                self.entries.insert(key, entry);
+                changed += 1;
+            }
+        }
+
+        changed
+    }
+
+    pub fn query(&self, query: &LibraryQuery) -> Vec<LibraryEntry> {
+        let series = query.series.as_deref().map(normalize);
+        let format = query.format.as_deref().map(|f| f.trim().to_uppercase());
+        let text = query.text.as_deref().map(normalize);
+
+        let mut results: Vec<&LibraryEntry> = self
+            .entries
+            .values()
+            .filter(|e| {
+                series
+                    .as_ref()
+                    .is_none_or(|s| e.series.as_deref().map(normalize).as_ref() == Some(s))
+            })
+            .filter(|e| format.as_ref().is_none_or(|f| &e.format == f))
+            .filter(|e| {
+                text.as_ref()
+                    .is_none_or(|t| normalize(&e.relative_path).contains(t.as_str()))
+            })
+            .filter(|e| query.modified_after.is_none_or(|d| e.modified_at >= d))
+            .filter(|e| query.modified_before.is_none_or(|d| e.modified_at <= d))
+            .collect();
+
+        // Par série puis par tome, les fichiers sans numéro en dernier
+        results.sort_by(|a, b| {
+            a.series
+                .as_deref()
+                .map(normalize)
+                .cmp(&b.series.as_deref().map(normalize))
+                .then(a.volume.is_none().cmp(&b.volume.is_none()))
+                .then(a.volume.cmp(&b.volume))
+                .then(a.relative_path.cmp(&b.relative_path))
+        });
+
+        results
+            .into_iter()
+            .skip(query.offset.unwrap_or(0))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    pub fn series(&self) -> Vec<SeriesSummary> {
+        let mut groups: BTreeMap<String, (SeriesSummary, BTreeSet<String>, BTreeSet<u32>)> =
+            BTreeMap::new();
+
+        for entry in self.entries.values() {
+            let name = entry.series.clone().unwrap_or_default();
+            let (summary, formats, volumes) = groups.entry(normalize(&name)).or_insert_with(|| {
+                (
+                    SeriesSummary {
+                        series: name,
+                        ..Default::default()
+                    },
+                    BTreeSet::new(),
+                    BTreeSet::new(),
+                )
+            });
+            summary.files += 1;
+            summary.total_size += entry.size;
+            summary.last_modified = summary.last_modified.max(entry.modified_at);
+            formats.insert(entry.format.clone());
+            volumes.extend(entry.volume);
+        }
+
+        groups
+            .into_values()
+            .map(|(mut summary, formats, volumes)| {
+                summary.formats = formats.into_iter().collect();
+                summary.volumes = volumes.into_iter().collect();
+                summary
+            })
+            .collect()
+    }
+}
+
+/// Shared, lazily loaded library index
+#[derive(Clone, Default)]
+pub struct Library {
+    index: Arc<Mutex<Option<LibraryIndex>>>,
+    scanning: Arc<Mutex<()>>, // Held by scan, watcher updates wait for it
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_index<R>(&self, index_path: &Path, f: impl FnOnce(&mut LibraryIndex) -> R) -> R {
+        let mut guard = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let index = guard.get_or_insert_with(|| LibraryIndex::load(index_path));
+        f(index)
+    }
+
+    pub fn query(&self, index_path: &Path, query: &LibraryQuery) -> Vec<LibraryEntry> {
+        self.with_index(index_path, |index| index.query(query))
+    }
+
+    pub fn series(&self, index_path: &Path) -> Vec<SeriesSummary> {
+        self.with_index(index_path, |index| index.series())
+    }
+
+    /// Apply watcher events and save the index if anything changed.
+    /// Returns the number of changed entries for each path.
+    pub fn refresh_paths(
+        &self,
+        index_path: &Path,
+        root: &Path,
+        paths: &[PathBuf],
+    ) -> Result<Vec<usize>> {
+        // Appliqués au résultat du scan en cours, sinon il les écraserait
+        let _scanning = self.scanning.lock().unwrap_or_else(|e| e.into_inner());
+        self.with_index(index_path, |index| {
+            if Path::new(&index.root) != root {
+                return Ok(vec![0; paths.len()]);
+            }
+            let counts: Vec<usize> = paths.iter().map(|p| index.refresh_path(root, p)).collect();
+            if counts.iter().any(|c| *c > 0) {
+                index.scanned_at = now_millis();
+                index.save(index_path)?;
+            }
+            Ok(counts)
+        })
+    }
+
+    /// Content hash of a file, taken from the index while size and mtime still match
+    pub fn content_hash(&self, index_path: &Path, path: &Path) -> Result<String> {
+        let (size, modified_at) =
+            file_stat(path).ok_or_else(|| anyhow::anyhow!("File not found"))?;
+        let indexed = self.with_index(index_path, |index| {
+            let relative = path.strip_prefix(&index.root).ok()?;
+            index
+                .entries
+                .get(relative.to_string_lossy().as_ref())
+                .filter(|e| e.size == size && e.modified_at == modified_at)
+                .map(|e| e.hash.clone())
+        });
+        match indexed {
+            Some(hash) => Ok(hash),
+            None => hash_file(path),
+        }
+    }
+
+    /// (path, hash) of the comic files found under `directory`
+    pub fn comic_hashes(&self, index_path: &Path, directory: &Path) -> Vec<(String, String)> {
+        WalkDir::new(directory)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file() && is_comic_file(e.path()))
+            .filter_map(|e| {
+                let hash = self.content_hash(index_path, e.path()).ok()?;
+                Some((e.path().to_string_lossy().to_string(), hash))
+            })
+            .collect()
+    }
+
+    /// Indexed file under `root` with the same size and normalised name, outside
+    /// `exclude_dir` (same key as the duplicate finder in vk_parser)
+    pub fn find_copy(
+        &self,
+        index_path: &Path,
+        root: &Path,
+        file_name: &str,
+        size: u64,
+        exclude_dir: &Path,
+    ) -> Option<PathBuf> {
+        let wanted = normalize_title(file_name);
+        if wanted.is_empty() {
+            return None;
+        }
+        self.with_index(index_path, |index| {
+            if Path::new(&index.root) != root {
+                return None;
+            }
+            index
+                .entries
+                .values()
+                .filter(|e| e.size == size && normalize_title(&e.file_name) == wanted)
+                .map(|e| PathBuf::from(&e.path))
+                .find(|path| path.parent() != Some(exclude_dir) && path.is_file())
+        })
+    }
+
+    /// Scan a copy so queries keep answering from the previous index meanwhile.
+    /// Watcher updates (refresh_paths) are held back until the copy is in place.
+    pub fn scan(&self, index_path: &Path, root: &Path) -> Result<ScanSummary> {
+        let _scanning = self.scanning.lock().unwrap_or_else(|e| e.into_inner());
+        let mut snapshot = self.with_index(index_path, |index| index.clone());
+        let summary = snapshot.scan(root)?;
+        snapshot.save(index_path)?;
+        *self.index.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn rescan_is_incremental() {
+        let root = TempDir::new("library");
+        std::fs::create_dir_all(root.join("XIII")).unwrap();
+        std::fs::write(root.join("XIII/XIII T01.cbz"), b"one").unwrap();
+        std::fs::write(root.join("XIII/XIII T02.pdf"), b"two").unwrap();
+        std::fs::write(root.join("XIII/notes.txt"), b"ignored").unwrap();
+
+        let mut index = LibraryIndex::default();
+        let first = index.scan(root.path()).unwrap();
+        assert_eq!((first.added, first.total), (2, 2));
+
+        std::fs::remove_file(root.join("XIII/XIII T02.pdf")).unwrap();
+        std::fs::write(root.join("Thorgal T03.cbr"), b"three").unwrap();
+        let second = index.scan(root.path()).unwrap();
+        assert_eq!((second.added, second.removed, second.unchanged), (1, 1, 1));
+
+        let xiii = index.query(&LibraryQuery {
+            series: Some("xiii".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(xiii.len(), 1);
+        assert_eq!(xiii[0].volume, Some(1));
+        assert_eq!(xiii[0].folder, "XIII");
+
+        let series: Vec<_> = index.series().into_iter().map(|s| s.series).collect();
+        assert_eq!(series, vec!["Thorgal", "XIII"]);
+    }
+
+    #[test]
+    fn refresh_follows_folder_moves() {
+        let root = TempDir::new("watch");
+        std::fs::create_dir_all(root.join("Blake")).unwrap();
+        std::fs::write(root.join("Blake/Blake T01.cbz"), b"one").unwrap();
+
+        let mut index = LibraryIndex::default();
+        index.scan(root.path()).unwrap();
+
+        // Dossier renommé par un autre outil
+        std::fs::rename(root.join("Blake"), root.join("Blake et Mortimer")).unwrap();
+        assert_eq!(index.refresh_path(root.path(), &root.join("Blake")), 1);
+        assert_eq!(
+            index.refresh_path(root.path(), &root.join("Blake et Mortimer")),
+            1
+        );
+        assert!(index.entries.contains_key(&format!(
+            "Blake et Mortimer{}Blake T01.cbz",
+            std::path::MAIN_SEPARATOR
+        )));
+
+        // Fichier hors bibliothèque : rien ne change
+        std::fs::write(root.join("readme.txt"), b"x").unwrap();
+        assert_eq!(index.refresh_path(root.path(), &root.join("readme.txt")), 0);
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+// Au-delà, on refuse de charger une entrée en mémoire
+const MAX_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "rar")]
+    Rar,
+    #[serde(rename = "7z")]
+    SevenZ,
+}
+
+impl ArchiveFormat {
+    /// Detected from the magic bytes first: many ".cbr" on the board are really ZIPs
+    pub fn detect(path: &Path) -> Option<Self> {
+        let mut magic = [0u8; 6];
+        let read = File::open(path)
+            .and_then(|mut f| f.read(&mut magic))
+            .unwrap_or(0);
+        let magic = &magic[..read];
+        if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+            return Some(Self::Zip);
+        }
+        if magic.starts_with(b"Rar!\x1a\x07") {
+            return Some(Self::Rar);
+        }
+        if magic.starts_with(b"7z\xbc\xaf\x27\x1c") {
+            return Some(Self::SevenZ);
+        }
+
+        // Fichier vide ou tronqué : l'extension tranche
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "cbz" | "zip" => Some(Self::Zip),
+            "cbr" | "rar" => Some(Self::Rar),
+            "cb7" | "7z" => Some(Self::SevenZ),
+            _ => None,
+        }
+    }
+}
+
+/// A file stored in an archive (directories are not listed)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Result of a full decompression pass
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveCheck {
+    pub path: String,
+    pub format: Option<ArchiveFormat>,
+    pub entries: usize,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+fn detect(path: &Path) -> Result<ArchiveFormat> {
+    ArchiveFormat::detect(path).ok_or_else(|| {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        anyhow!("Unsupported format: {}", ext)
+    })
+}
+
+/// Files of the archive, in archive order
+pub fn list_entries(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    match detect(path)? {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+            let mut entries = Vec::new();
+            for i in 0..archive.len() {
+                let entry = archive.by_index_raw(i)?;
+                if entry.is_file() {
+                    entries.push(ArchiveEntry {
+                        name: entry.name().to_string(),
+                        size: entry.size(),
+                    });
+                }
+            }
+            Ok(entries)
+        }
+        ArchiveFormat::Rar => {
+            let mut entries = Vec::new();
+            for header in unrar::Archive::new(path).open_for_listing()? {
+                let header = header?;
+                if header.is_file() {
+                    entries.push(ArchiveEntry {
+                        name: header.filename.to_string_lossy().to_string(),
+                        size: header.unpacked_size,
+                    });
+                }
+            }
+            Ok(entries)
+        }
+        ArchiveFormat::SevenZ => {
+            let reader = sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty())?;
+            Ok(reader
+                .archive()
+                .files
+                .iter()
+                .filter(|f| !f.is_directory())
+                .map(|f| ArchiveEntry {
+                    name: f.name().to_string(),
+                    size: f.size(),
+                })
+                .collect())
+        }
+    }
+}
+
+/// Image entries in reading order (natural sort, hidden files skipped)
+pub fn list_images(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let mut images: Vec<ArchiveEntry> = list_entries(path)?
+        .into_iter()
+        .filter(|e| is_image_name(&e.name))
+        .collect();
+    images.sort_by(|a, b| natural_cmp(&a.name, &b.name));
+    Ok(images)
+}
+
+/// Decompress one entry in memory
+pub fn read_entry(path: &Path, name: &str) -> Result<Vec<u8>> {
+    match detect(path)? {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+            let entry = archive.by_name(name)?;
+            if entry.size() > MAX_ENTRY_BYTES {
+                return Err(anyhow!("Entry too large: {}", name));
+            }
+            let mut bytes = Vec::with_capacity(entry.size() as usize);
+            entry.take(MAX_ENTRY_BYTES).read_to_end(&mut bytes)?;
+            Ok(bytes)
+        }
+        ArchiveFormat::Rar => {
+            let mut archive = unrar::Archive::new(path).open_for_processing()?;
+            while let Some(header) = archive.read_header()? {
+                let entry = header.entry();
+                if entry.is_file() && entry.filename.to_string_lossy() == name {
+                    if entry.unpacked_size > MAX_ENTRY_BYTES {
+                        return Err(anyhow!("Entry too large: {}", name));
+                    }
+                    return Ok(header.read()?.0);
+                }
+                archive = header.skip()?;
+            }
+            Err(anyhow!("Entry not found: {}", name))
+        }
+        ArchiveFormat::SevenZ => {
+            let mut reader = sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty())?;
+            let mut found: Option<Vec<u8>> = None;
+            let mut too_large = false;
+            // Archives solides : les entrées précédentes doivent être lues pour avancer
+            reader.for_each_entries(|entry, data| {
+                if entry.is_directory() || entry.name() != name {
+                    std::io::copy(data, &mut std::io::sink())?;
+                    return Ok(true);
+                }
+                if entry.size() > MAX_ENTRY_BYTES {
+                    too_large = true;
+                    return Ok(false);
+                }
+                let mut bytes = Vec::with_capacity(entry.size() as usize);
+                data.read_to_end(&mut bytes)?;
+                found = Some(bytes);
+                Ok(false)
+            })?;
+            if too_large {
+                return Err(anyhow!("Entry too large: {}", name));
+            }
+            found.ok_or_else(|| anyhow!("Entry not found: {}", name))
+        }
+    }
+}
+
+/// Stream every file of the archive once, in archive order. `f` returns false to stop.
+/// Much cheaper than `read_entry` in a loop for solid RAR / 7z archives.
+pub fn for_each_entry(
+    path: &Path,
+    mut f: impl FnMut(&ArchiveEntry, &mut dyn Read) -> Result<bool>,
+) -> Result<()> {
+    match detect(path)? {
+        ArchiveFormat::Zip => {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+            for i in 0..archive.len() {
+                let mut file = archive.by_index(i)?;
+                if !file.is_file() {
+                    continue;
+                }
+                let entry = ArchiveEntry {
+                    name: file.name().to_string(),
+                    size: file.size(),
+                };
+                if !f(&entry, &mut file)? {
+                    break;
+                }
+            }
+        }
+        ArchiveFormat::Rar => {
+            let mut archive = unrar::Archive::new(path).open_for_processing()?;
+            while let Some(header) = archive.read_header()? {
+                if !header.entry().is_file() {
+                    archive = header.skip()?;
+                    continue;
+                }
+                let entry = ArchiveEntry {
+                    name: header.entry().filename.to_string_lossy().to_string(),
+                    size: header.entry().unpacked_size,
+                };
+                let (data, next) = header
+                    .read()
+                    .map_err(|e| anyhow!("{}: {}", entry.name, e))?;
+                if !f(&entry, &mut data.as_slice())? {
+                    break;
+                }
+                archive = next;
+            }
+        }
+        ArchiveFormat::SevenZ => {
+            let mut reader = sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty())?;
+            let mut failure = None;
+            reader.for_each_entries(|file, data| {
+                if !file.is_directory() {
+                    let entry = ArchiveEntry {
+                        name: file.name().to_string(),
+                        size: file.size(),
+                    };
+                    match f(&entry, data) {
+                        Ok(true) => {}
+                        Ok(false) => return Ok(false),
+                        Err(e) => {
+                            failure = Some(e);
+                            return Ok(false);
+                        }
+                    }
+                }
+                // Le flux doit être consommé pour passer à l'entrée suivante
+                std::io::copy(data, &mut std::io::sink())?;
+                Ok(true)
+            })?;
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decompress every entry and verify checksums; corruption is reported, not returned as Err
+pub fn check(path: &Path) -> ArchiveCheck {
+    let format = ArchiveFormat::detect(path);
+    let result = match format {
+        Some(ArchiveFormat::Zip) => check_zip(path),
+        Some(ArchiveFormat::Rar) => check_rar(path),
+        Some(ArchiveFormat::SevenZ) => check_seven_z(path),
+        None => detect(path).map(|_| 0),
+    };
+    let (entries, error) = match result {
+        Ok(entries) => (entries, None),
+        Err(e) => (0, Some(e.to_string())),
+    };
+    ArchiveCheck {
+        path: path.to_string_lossy().to_string(),
+        format,
+        entries,
+        ok: error.is_none(),
+        error,
+    }
+}
+
+fn check_zip(path: &Path) -> Result<usize> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+    for i in 0..archive.len() {
+        // Le CRC est vérifié par le crate zip en fin de lecture
+        let mut entry = archive.by_index(i)?;
+        let name = entry.name().to_string();
+        std::io::copy(&mut entry, &mut std::io::sink()).map_err(|e| anyhow!("{}: {}", name, e))?;
+    }
+    Ok(archive.len())
+}
+
+fn check_rar(path: &Path) -> Result<usize> {
+    let mut archive = unrar::Archive::new(path).open_for_processing()?;
+    let mut count = 0;
+    while let Some(header) = archive.read_header()? {
+        let name = header.entry().filename.to_string_lossy().to_string();
+        archive = header.test().map_err(|e| anyhow!("{}: {}", name, e))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+fn check_seven_z(path: &Path) -> Result<usize> {
+    let mut reader = sevenz_rust::SevenZReader::open(path, sevenz_rust::Password::empty())?;
+    let mut count = 0;
+    reader.for_each_entries(|_, data| {
+        std::io::copy(data, &mut std::io::sink())?;
+        count += 1;
+        Ok(true)
+    })?;
+    Ok(count)
+}
+
+pub(crate) fn is_image_name(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    let file_name = lower.rsplit(['/', '\\']).next().unwrap_or(&lower);
+    // Métadonnées macOS et fichiers cachés
+    if lower.starts_with("__macosx") || file_name.starts_with('.') {
+        return false;
+    }
+    file_name
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext))
+}
+
+/// Page order inside an archive: "page2" before "page10"
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (a, b) = (a.to_lowercase(), b.to_lowercase());
+    let (mut a, mut b) = (a.as_str(), b.as_str());
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, _) => return Ordering::Less,
+            (_, None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let a_len = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+                let b_len = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+                let a_num = a[..a_len].trim_start_matches('0');
+                let b_num = b[..b_len].trim_start_matches('0');
+                let ord = a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a = &a[a_len..];
+                b = &b[b_len..];
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a = &a[x.len_utf8()..];
+                b = &b[y.len_utf8()..];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page, write_zip, TempDir};
+
+    #[test]
+    fn archive_detection_and_corruption() {
+        let dir = TempDir::new("archive");
+
+        // Un ".cbr" qui est en réalité un ZIP doit rester lisible
+        let fake_cbr = dir.join("Tome 2.cbr");
+        write_zip(&fake_cbr, &["01.png", "02.png"]);
+        assert_eq!(ArchiveFormat::detect(&fake_cbr), Some(ArchiveFormat::Zip));
+        assert_eq!(list_images(&fake_cbr).unwrap().len(), 2);
+        assert_eq!(read_entry(&fake_cbr, "02.png").unwrap(), page(100));
+        let result = check(&fake_cbr);
+        assert!(result.ok && result.entries == 2, "{:?}", result);
+
+        // Octets corrompus au milieu des données compressées
+        let mut bytes = std::fs::read(&fake_cbr).unwrap();
+        let middle = bytes.len() / 4;
+        for b in &mut bytes[middle..middle + 64] {
+            *b = !*b;
+        }
+        let broken = dir.join("broken.cbz");
+        std::fs::write(&broken, bytes).unwrap();
+        let result = check(&broken);
+        assert!(!result.ok && result.error.is_some(), "{:?}", result);
+    }
+}
@@ -0,0 +1,261 @@
+use super::archive::{self, is_image_name, ArchiveFormat};
+use super::COMIC_EXTENSIONS;
+use crate::vk_parser::metadata::parse_title;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub const COMIC_INFO_NAME: &str = "ComicInfo.xml";
+
+/// Subset of the ComicInfo.xml schema (Anansi v2) read by Komga, Kavita and most readers
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicInfo {
+    pub title: Option<String>,
+    pub series: Option<String>,
+    pub number: Option<String>,
+    pub notes: Option<String>,
+    pub year: Option<u16>,
+    pub web: Option<String>,
+    pub page_count: Option<usize>,
+    pub language_iso: Option<String>,
+}
+
+/// What the frontend knows about a VK document when it queues the download
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicInfoSource {
+    pub series: Option<String>, // Title of the parent node
+    pub title: String,          // Document title
+    pub web: Option<String>,    // https://vk.com/doc...
+    pub group: Option<String>,  // VK group id, without the leading "-"
+}
+
+lazy_static::lazy_static! {
+    static ref RE_ELEMENT: Regex = Regex::new(r"(?s)<(\w+)>([^<]*)</(\w+)>").unwrap();
+}
+
+impl ComicInfo {
+    /// Metadata for a downloaded document; number, year and language come from its title
+    pub fn for_document(source: &ComicInfoSource) -> Self {
+        let meta = parse_title(&source.title);
+        let title = strip_extension(&source.title);
+        ComicInfo {
+            title: meta.volume_title.clone().or(Some(title)),
+            series: source
+                .series
+                .clone()
+                .filter(|s| !s.trim().is_empty())
+                .or(meta.series.clone()),
+            number: meta.number().map(|n| n.to_string()),
+            notes: source
+                .group
+                .as_ref()
+                .map(|group| format!("Source: VK group {} (https://vk.com/club{})", group, group)),
+            year: meta.year,
+            web: source.web.clone(),
+            page_count: None,
+            language_iso: meta.language.map(|l| l.to_lowercase()),
+        }
+    }
+
+    /// Elements are written in schema order, empty fields left out
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+        );
+        let year = self.year.map(|y| y.to_string());
+        let page_count = self.page_count.map(|c| c.to_string());
+        let fields = [
+            ("Title", self.title.as_ref()),
+            ("Series", self.series.as_ref()),
+            ("Number", self.number.as_ref()),
+            ("Notes", self.notes.as_ref()),
+            ("Year", year.as_ref()),
+            ("Web", self.web.as_ref()),
+            ("PageCount", page_count.as_ref()),
+            ("LanguageISO", self.language_iso.as_ref()),
+        ];
+        for (tag, value) in fields {
+            if let Some(value) = value {
+                xml.push_str(&format!("  <{}>{}</{}>\n", tag, escape(value), tag));
+            }
+        }
+        xml.push_str("</ComicInfo>\n");
+        xml
+    }
+
+    /// Lenient parser: unknown elements (Writer, Summary, Pages...) are ignored
+    pub fn parse(xml: &str) -> Self {
+        let mut info = ComicInfo::default();
+        for caps in RE_ELEMENT.captures_iter(xml) {
+            if caps[1] != caps[3] {
+                continue;
+            }
+            let value = unescape(caps[2].trim());
+            if value.is_empty() {
+                continue;
+            }
+            match &caps[1] {
+                "Title" => info.title = Some(value),
+                "Series" => info.series = Some(value),
+                "Number" => info.number = Some(value),
+                "Notes" => info.notes = Some(value),
+                "Year" => info.year = value.parse().ok(),
+                "Web" => info.web = Some(value),
+                "PageCount" => info.page_count = value.parse().ok(),
+                "LanguageISO" => info.language_iso = Some(value),
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+/// ComicInfo.xml at the root of a CBZ / CBR / CB7, if any
+pub fn read(path: &Path) -> Result<Option<ComicInfo>> {
+    let Some(entry) = archive::list_entries(path)?
+        .into_iter()
+        .find(|e| e.name.eq_ignore_ascii_case(COMIC_INFO_NAME))
+    else {
+        return Ok(None);
+    };
+    let bytes = archive::read_entry(path, &entry.name)?;
+    Ok(Some(ComicInfo::parse(&String::from_utf8_lossy(&bytes))))
+}
+
+/// Write (or replace) ComicInfo.xml in a ZIP-based comic. Pages are copied
+/// without recompression into a temporary file that replaces the original.
+pub fn embed(path: &Path, info: &ComicInfo) -> Result<()> {
+    if ArchiveFormat::detect(path) != Some(ArchiveFormat::Zip) {
+        return Err(anyhow!("Not a ZIP archive: {}", path.display()));
+    }
+    let tmp = path.with_extension("comicinfo.tmp");
+    if let Err(e) = rewrite_with(path, &tmp, info) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn rewrite_with(path: &Path, tmp: &Path, info: &ComicInfo) -> Result<()> {
+    let mut source = zip::ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let mut zip = zip::ZipWriter::new(BufWriter::new(File::create(tmp)?));
+    let mut pages = 0;
+    for i in 0..source.len() {
+        let entry = source.by_index_raw(i)?;
+        if entry.name().eq_ignore_ascii_case(COMIC_INFO_NAME) {
+            continue;
+        }
+        if entry.is_file() && is_image_name(entry.name()) {
+            pages += 1;
+        }
+        zip.raw_copy_file(entry)?;
+    }
+    let info = ComicInfo {
+        page_count: Some(pages),
+        ..info.clone()
+    };
+    write_entry(&mut zip, &info)?;
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Add ComicInfo.xml to an archive being written
+pub fn write_entry<W: Write + std::io::Seek>(
+    zip: &mut zip::ZipWriter<W>,
+    info: &ComicInfo,
+) -> Result<()> {
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file(COMIC_INFO_NAME, options)?;
+    zip.write_all(info.to_xml().as_bytes())?;
+    Ok(())
+}
+
+fn strip_extension(title: &str) -> String {
+    match title.rsplit_once('.') {
+        Some((stem, ext)) if COMIC_EXTENSIONS.contains(&ext.to_lowercase().as_str()) => {
+            stem.trim().to_string()
+        }
+        _ => title.trim().to_string(),
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape(value: &str) -> String {
+    // &amp; en dernier pour ne pas décoder deux fois "&amp;lt;"
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::library::LibraryIndex;
+    use crate::test_util::{write_zip, TempDir};
+
+    #[test]
+    fn comic_info_is_embedded_and_scanned() {
+        let root = TempDir::new("comicinfo");
+        std::fs::create_dir_all(root.join("Downloads")).unwrap();
+        let path = root.join("Downloads/lucky_luke_01.cbz");
+        write_zip(&path, &["p1.jpg", "p2.jpg", "p3.jpg"]);
+
+        let info = ComicInfo::for_document(&ComicInfoSource {
+            series: Some("Lucky Luke & Co".to_string()),
+            title: "Lucky Luke T01 - Arizona (1951) [FR].cbz".to_string(),
+            web: Some("https://vk.com/doc-1_2".to_string()),
+            group: Some("203785966".to_string()),
+        });
+        assert_eq!(info.number.as_deref(), Some("1"));
+        assert_eq!(info.year, Some(1951));
+        assert_eq!(info.language_iso.as_deref(), Some("fr"));
+        assert!(info
+            .to_xml()
+            .contains("<Series>Lucky Luke &amp; Co</Series>"));
+
+        // Deux écritures : l'ancien ComicInfo.xml est remplacé, pas dupliqué
+        embed(&path, &info).unwrap();
+        embed(&path, &info).unwrap();
+        let entries = archive::list_entries(&path).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[3].name, COMIC_INFO_NAME);
+        assert_eq!(archive::list_images(&path).unwrap().len(), 3);
+
+        let read = read(&path).unwrap().unwrap();
+        assert_eq!(read.page_count, Some(3));
+        assert_eq!(read.series.as_deref(), Some("Lucky Luke & Co"));
+        assert_eq!(
+            read,
+            ComicInfo {
+                page_count: Some(3),
+                ..info
+            }
+        );
+
+        // Le nom de fichier ne dit rien : la série vient du ComicInfo
+        let mut index = LibraryIndex::default();
+        index.scan(root.path()).unwrap();
+        let entry = &index.entries.values().next().unwrap();
+        assert_eq!(entry.series.as_deref(), Some("Lucky Luke & Co"));
+        assert_eq!(entry.volume, Some(1));
+        assert!(entry.comic_info.is_some());
+    }
+}
@@ -0,0 +1,460 @@
+use super::archive::{self, ArchiveFormat};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+/// Convert a downloaded CBR / CB7 / PDF into a CBZ next to it
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionJob {
+    pub id: String,
+    pub source: String,
+    #[serde(default)]
+    pub delete_source: bool, // Only once the CBZ has been verified
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ConversionProgress {
+    id: String,
+    page: usize,
+    total: usize,
+    progress: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Converted {
+    pub path: PathBuf,
+    pub pages: usize,
+}
+
+/// The job was cancelled, reported as "aborted" in conversion-result
+#[derive(Debug)]
+struct Aborted;
+
+impl std::fmt::Display for Aborted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Conversion aborted")
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// Build `<source stem>.cbz`, pages renamed 0001.jpg, 0002.png... in natural order.
+/// `on_page(done, total)` is called after each page; `cancel` stops between pages.
+pub fn convert_to_cbz(
+    source: &Path,
+    cancel: &AtomicBool,
+    mut on_page: impl FnMut(usize, usize),
+) -> Result<Converted> {
+    let is_pdf = source
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"));
+    let target = source.with_extension("cbz");
+    if target.exists() {
+        return Err(anyhow!("Target already exists: {}", target.display()));
+    }
+    let part = target.with_extension("cbz.part");
+
+    let result = if is_pdf {
+        pack_pdf(source, &part, cancel, &mut on_page)
+    } else {
+        pack_archive(source, &part, cancel, &mut on_page)
+    };
+    let pages = match result {
+        Ok(pages) => pages,
+        Err(e) => {
+            let _ = std::fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+
+    // Relecture complète avant de remplacer quoi que ce soit
+    let check = archive::check(&part);
+    if !check.ok || check.entries != pages {
+        let _ = std::fs::remove_file(&part);
+        return Err(anyhow!(
+            "Verification failed: {}",
+            check
+                .error
+                .unwrap_or_else(|| "page count mismatch".to_string())
+        ));
+    }
+    std::fs::rename(&part, &target)?;
+    Ok(Converted {
+        path: target,
+        pages,
+    })
+}
+
+fn page_name(index: usize, ext: &str) -> String {
+    format!("{:04}.{}", index + 1, ext)
+}
+
+fn page_options() -> zip::write::SimpleFileOptions {
+    // Les images sont déjà compressées
+    zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored)
+}
+
+fn pack_archive(
+    source: &Path,
+    part: &Path,
+    cancel: &AtomicBool,
+    on_page: &mut impl FnMut(usize, usize),
+) -> Result<usize> {
+    if ArchiveFormat::detect(source).is_none() {
+        return Err(anyhow!("Unsupported format: {}", source.display()));
+    }
+    // Déjà triées dans l'ordre naturel
+    let names: Vec<String> = archive::list_images(source)?
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    if names.is_empty() {
+        return Err(anyhow!("No image in archive"));
+    }
+
+    // Un seul passage sur l'archive (les RAR solides coûtent cher à relire),
+    // pages posées dans un dossier temporaire puis rangées dans l'ordre naturel
+    let staging = part.with_extension("pages");
+    std::fs::create_dir_all(&staging)?;
+    let result = stage_and_pack(source, part, &staging, &names, cancel, on_page);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn stage_and_pack(
+    source: &Path,
+    part: &Path,
+    staging: &Path,
+    names: &[String],
+    cancel: &AtomicBool,
+    on_page: &mut impl FnMut(usize, usize),
+) -> Result<usize> {
+    let total = names.len();
+    let mut staged: Vec<Option<PathBuf>> = vec![None; total];
+    let mut done = 0;
+    // Noms exacts : "P1.png" et "p1.png" sont deux pages
+    let positions: HashMap<&str, usize> = names
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.as_str(), index))
+        .collect();
+    archive::for_each_entry(source, |entry, data| {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(anyhow::Error::new(Aborted));
+        }
+        let Some(&index) = positions.get(entry.name.as_str()) else {
+            return Ok(true);
+        };
+        let file = staging.join(index.to_string());
+        std::io::copy(data, &mut BufWriter::new(File::create(&file)?))?;
+        staged[index] = Some(file);
+        done += 1;
+        on_page(done, total);
+        Ok(true)
+    })?;
+
+    let mut zip = zip::ZipWriter::new(BufWriter::new(File::create(part)?));
+    for (index, name) in names.iter().enumerate() {
+        let file = staged[index]
+            .as_ref()
+            .ok_or_else(|| anyhow!("Missing page: {}", name))?;
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, e)| e.to_lowercase())
+            .unwrap_or_default();
+        zip.start_file(page_name(index, &ext), page_options())?;
+        std::io::copy(&mut File::open(file)?, &mut zip)?;
+    }
+    zip.finish()?.flush()?;
+    Ok(total)
+}
+
+/// Image-only PDFs: one embedded image per page, taken in page-tree order
+fn pack_pdf(
+    source: &Path,
+    part: &Path,
+    cancel: &AtomicBool,
+    on_page: &mut impl FnMut(usize, usize),
+) -> Result<usize> {
+    let doc = lopdf::Document::load(source)?;
+    let pages = doc.get_pages();
+    let total = pages.len();
+    if total == 0 {
+        return Err(anyhow!("PDF has no pages"));
+    }
+
+    let mut zip = zip::ZipWriter::new(BufWriter::new(File::create(part)?));
+    for (index, (number, page_id)) in pages.into_iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(anyhow::Error::new(Aborted));
+        }
+        let (bytes, ext) =
+            pdf_page_image(&doc, page_id).map_err(|e| anyhow!("Page {}: {}", number, e))?;
+        zip.start_file(page_name(index, ext), page_options())?;
+        zip.write_all(&bytes)?;
+        on_page(index + 1, total);
+    }
+    zip.finish()?.flush()?;
+    Ok(total)
+}
+
+/// Largest image of the page: JPEG streams are copied as-is, raw pixels become PNG
+fn pdf_page_image(
+    doc: &lopdf::Document,
+    page_id: lopdf::ObjectId,
+) -> Result<(Vec<u8>, &'static str)> {
+    let images = doc.get_page_images(page_id).unwrap_or_default();
+    let image = images
+        .iter()
+        .max_by_key(|i| i.width * i.height)
+        .ok_or_else(|| anyhow!("no image (not an image-only PDF)"))?;
+    let filters = image.filters.clone().unwrap_or_default();
+
+    match filters
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["DCTDecode"] => Ok((image.content.to_vec(), "jpg")),
+        [] | ["FlateDecode"] => {
+            let stream = doc.get_object(image.id)?.as_stream()?;
+            let pixels = if filters.is_empty() {
+                stream.content.clone()
+            } else {
+                stream.decompressed_content()?
+            };
+            let (width, height) = (image.width as u32, image.height as u32);
+            let area = width as usize * height as usize;
+            // Espace colorimétrique déduit de la taille : DeviceRGB, DeviceGray ou ICCBased
+            let decoded = match image.bits_per_component {
+                Some(8) if pixels.len() == area * 3 => {
+                    image::RgbImage::from_raw(width, height, pixels).map(image::DynamicImage::from)
+                }
+                Some(8) if pixels.len() == area => {
+                    image::GrayImage::from_raw(width, height, pixels).map(image::DynamicImage::from)
+                }
+                _ => None,
+            }
+            .ok_or_else(|| anyhow!("unsupported pixel format"))?;
+            let mut png = std::io::Cursor::new(Vec::new());
+            decoded.write_to(&mut png, image::ImageFormat::Png)?;
+            Ok((png.into_inner(), "png"))
+        }
+        other => Err(anyhow!("unsupported image encoding {:?}", other)),
+    }
+}
+
+#[derive(Default)]
+struct ConversionQueue {
+    jobs: VecDeque<ConversionJob>,
+    running: Option<(String, Arc<AtomicBool>)>,
+    worker_active: bool,
+}
+
+/// Conversions run one at a time on a dedicated thread (CPU and disk bound)
+#[derive(Clone, Default)]
+pub struct ConversionManager {
+    queue: Arc<Mutex<ConversionQueue>>,
+}
+
+impl ConversionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ConversionQueue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn add_job(&self, app: AppHandle, job: ConversionJob) {
+        let mut queue = self.lock();
+        queue.jobs.push_back(job);
+        if !queue.worker_active {
+            queue.worker_active = true;
+            let manager = self.clone();
+            std::thread::spawn(move || manager.run_queue(app));
+        }
+    }
+
+    pub fn cancel_job(&self, app: AppHandle, id: &str) -> bool {
+        let mut queue = self.lock();
+        if let Some((running_id, cancel)) = &queue.running {
+            if running_id == id {
+                // Le worker émet le résultat "aborted" en s'arrêtant
+                cancel.store(true, Ordering::Relaxed);
+                return true;
+            }
+        }
+        let before = queue.jobs.len();
+        queue.jobs.retain(|job| job.id != id);
+        let removed = queue.jobs.len() != before;
+        if removed {
+            emit_result(&app, id, Err(anyhow::Error::new(Aborted)), false);
+        }
+        removed
+    }
+
+    fn run_queue(&self, app: AppHandle) {
+        loop {
+            let (job, cancel) = {
+                let mut queue = self.lock();
+                let Some(job) = queue.jobs.pop_front() else {
+                    queue.running = None;
+                    queue.worker_active = false;
+                    return;
+                };
+                let cancel = Arc::new(AtomicBool::new(false));
+                queue.running = Some((job.id.clone(), cancel.clone()));
+                (job, cancel)
+            };
+
+            let mut last_emit = std::time::Instant::now();
+            let result = convert_to_cbz(Path::new(&job.source), &cancel, |page, total| {
+                // Pas plus de 5 événements par seconde, sauf la dernière page
+                if page == total || last_emit.elapsed().as_millis() >= 200 {
+                    let _ = app.emit(
+                        "conversion-progress",
+                        ConversionProgress {
+                            id: job.id.clone(),
+                            page,
+                            total,
+                            progress: page as f64 / total as f64 * 100.0,
+                        },
+                    );
+                    last_emit = std::time::Instant::now();
+                }
+            });
+
+            let deleted =
+                result.is_ok() && job.delete_source && std::fs::remove_file(&job.source).is_ok();
+            emit_result(&app, &job.id, result, deleted);
+        }
+    }
+}
+
+fn emit_result(app: &AppHandle, id: &str, result: Result<Converted>, source_deleted: bool) {
+    let payload = match result {
+        Ok(converted) => serde_json::json!({
+            "id": id,
+            "ok": true,
+            "status": "converted",
+            "path": converted.path.to_string_lossy(),
+            "pages": converted.pages,
+            "sourceDeleted": source_deleted
+        }),
+        Err(e) if e.is::<Aborted>() => serde_json::json!({
+            "id": id,
+            "ok": false,
+            "status": "aborted"
+        }),
+        Err(e) => serde_json::json!({
+            "id": id,
+            "ok": false,
+            "status": "failed",
+            "error": e.to_string()
+        }),
+    };
+    let _ = app.emit("conversion-result", payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page, write_zip, TempDir};
+
+    #[test]
+    fn converts_archives_and_pdfs_to_cbz() {
+        use lopdf::{dictionary, Object, Stream};
+        use std::sync::atomic::AtomicBool;
+
+        let dir = TempDir::new("convert");
+        let cancel = AtomicBool::new(false);
+        let names = |path: &Path| -> Vec<String> {
+            archive::list_entries(path)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect()
+        };
+
+        let cbr = dir.join("Tome 3.cbr");
+        write_zip(&cbr, &["p10.png", "p9.png", "info.txt"]);
+        let converted = convert_to_cbz(&cbr, &cancel, |_, _| {}).unwrap();
+        assert_eq!(converted.path, dir.join("Tome 3.cbz"));
+        assert_eq!(names(&converted.path), vec!["0001.png", "0002.png"]);
+        // p9 (2e entrée, gris 100) passe avant p10
+        assert_eq!(
+            archive::read_entry(&converted.path, "0001.png").unwrap(),
+            page(100)
+        );
+        assert!(convert_to_cbz(&cbr, &cancel, |_, _| {}).is_err());
+
+        // Même nom à la casse près : deux pages distinctes, aucune ne manque
+        let cased = dir.join("Tome 4.cbr");
+        write_zip(&cased, &["P1.png", "p1.png", "p2.png"]);
+        let converted = convert_to_cbz(&cased, &cancel, |_, _| {}).unwrap();
+        assert_eq!(converted.pages, 3);
+        assert_eq!(names(&converted.path).len(), 3);
+
+        // PDF image seule : une image JPEG par page, dans l'ordre de l'arbre des pages
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let mut kids: Vec<Object> = Vec::new();
+        for shade in [30u8, 220] {
+            let mut jpeg = std::io::Cursor::new(Vec::new());
+            image::RgbImage::from_pixel(40, 60, image::Rgb([shade, shade, shade]))
+                .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+                .unwrap();
+            let image_id = doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => 40,
+                    "Height" => 60,
+                    "ColorSpace" => "DeviceRGB",
+                    "BitsPerComponent" => 8,
+                    "Filter" => "DCTDecode",
+                },
+                jpeg.into_inner(),
+            ));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+            });
+            kids.push(page_id.into());
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! { "Type" => "Pages", "Kids" => kids, "Count" => 2 }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let pdf = dir.join("Scan.pdf");
+        doc.save(&pdf).unwrap();
+
+        // Annulation : reconnue par son type, même enveloppée dans un contexte
+        let aborted = convert_to_cbz(&pdf, &AtomicBool::new(true), |_, _| {}).unwrap_err();
+        assert!(aborted.context("Scan.pdf").is::<Aborted>());
+
+        let mut progress = Vec::new();
+        let converted =
+            convert_to_cbz(&pdf, &cancel, |done, total| progress.push((done, total))).unwrap();
+        assert_eq!(names(&converted.path), vec!["0001.jpg", "0002.jpg"]);
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+        let first =
+            image::load_from_memory(&archive::read_entry(&converted.path, "0001.jpg").unwrap())
+                .unwrap()
+                .to_luma8();
+        assert!(first.get_pixel(20, 30)[0] < 60);
+    }
+}
@@ -0,0 +1,283 @@
+use super::now_millis;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// Bump when ReadingState changes incompatibly
+const PROGRESS_VERSION: u32 = 1;
+
+/// Reading state of one comic, keyed by content hash so it survives renames
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingState {
+    pub hash: String,
+    pub path: String, // Last known location
+    pub last_page: usize,
+    pub total_pages: usize,
+    pub completed: bool,
+    pub started_at: f64,
+    pub updated_at: f64,
+    pub completed_at: Option<f64>,
+}
+
+/// reading_progress.json in the app data dir
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressStore {
+    pub version: u32,
+    pub items: BTreeMap<String, ReadingState>,
+}
+
+/// Aggregated state of the comics of a series directory
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderProgress {
+    pub directory: String,
+    pub total: usize,
+    pub read: usize,
+    pub in_progress: usize,
+    pub unread: usize,
+    pub last_read: Option<ReadingState>, // For "continue reading"
+}
+
+impl ProgressStore {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<ProgressStore>(&content).ok())
+            .filter(|store| store.version == PROGRESS_VERSION)
+            .unwrap_or_else(|| ProgressStore {
+                version: PROGRESS_VERSION,
+                items: BTreeMap::new(),
+            })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn entry(&mut self, hash: &str, path: &str) -> &mut ReadingState {
+        let now = now_millis();
+        let state = self
+            .items
+            .entry(hash.to_string())
+            .or_insert_with(|| ReadingState {
+                hash: hash.to_string(),
+                started_at: now,
+                ..Default::default()
+            });
+        state.path = path.to_string();
+        state.updated_at = now;
+        state
+    }
+
+    /// Record the current page. Reaching the last page marks the comic as read;
+    /// going back to an earlier page later (re-reading) keeps it read.
+    pub fn update(
+        &mut self,
+        hash: &str,
+        path: &str,
+        page: usize,
+        total_pages: usize,
+    ) -> ReadingState {
+        let state = self.entry(hash, path);
+        state.last_page = page;
+        state.total_pages = total_pages;
+        if total_pages > 0 && page + 1 >= total_pages && !state.completed {
+            state.completed = true;
+            state.completed_at = Some(state.updated_at);
+        }
+        state.clone()
+    }
+
+    /// Mark as read / unread by hand. Unread forgets the comic entirely,
+    /// so it is neither in progress nor offered in "continue reading".
+    pub fn set_completed(&mut self, hash: &str, path: &str, completed: bool) -> ReadingState {
+        if !completed {
+            self.items.remove(hash);
+            return ReadingState {
+                hash: hash.to_string(),
+                path: path.to_string(),
+                ..Default::default()
+            };
+        }
+        let state = self.entry(hash, path);
+        state.completed = true;
+        state.completed_at = Some(state.updated_at);
+        state.clone()
+    }
+
+    /// Comics started but not finished, most recent first
+    pub fn recent(&self, limit: usize) -> Vec<ReadingState> {
+        let mut items: Vec<ReadingState> = self
+            .items
+            .values()
+            .filter(|s| !s.completed)
+            .cloned()
+            .collect();
+        items.sort_by(|a, b| b.updated_at.total_cmp(&a.updated_at));
+        items.truncate(limit);
+        items
+    }
+
+    /// `files` are the (path, hash) pairs of the comics found in `directory`
+    pub fn folder_progress(&self, directory: &str, files: &[(String, String)]) -> FolderProgress {
+        let mut progress = FolderProgress {
+            directory: directory.to_string(),
+            total: files.len(),
+            ..Default::default()
+        };
+        for (path, hash) in files {
+            match self.items.get(hash) {
+                Some(state) if state.completed => progress.read += 1,
+                Some(state) => {
+                    progress.in_progress += 1;
+                    let newer = progress
+                        .last_read
+                        .as_ref()
+                        .is_none_or(|last| state.updated_at > last.updated_at);
+                    if newer {
+                        // Le fichier a pu être renommé depuis la dernière lecture
+                        let mut state = state.clone();
+                        state.path = path.clone();
+                        progress.last_read = Some(state);
+                    }
+                }
+                None => progress.unread += 1,
+            }
+        }
+        progress
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ReadingProgress {
+    store: Arc<Mutex<Option<ProgressStore>>>,
+}
+
+impl ReadingProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_store<R>(&self, store_path: &Path, f: impl FnOnce(&mut ProgressStore) -> R) -> R {
+        let mut guard = self.store.lock().unwrap_or_else(|e| e.into_inner());
+        let store = guard.get_or_insert_with(|| ProgressStore::load(store_path));
+        f(store)
+    }
+
+    pub fn update(
+        &self,
+        store_path: &Path,
+        hash: &str,
+        path: &str,
+        page: usize,
+        total_pages: usize,
+    ) -> Result<ReadingState> {
+        if total_pages > 0 && page >= total_pages {
+            return Err(anyhow!("Page {} out of range", page));
+        }
+        self.with_store(store_path, |store| {
+            let state = store.update(hash, path, page, total_pages);
+            store.save(store_path)?;
+            Ok(state)
+        })
+    }
+
+    pub fn set_completed(
+        &self,
+        store_path: &Path,
+        hash: &str,
+        path: &str,
+        completed: bool,
+    ) -> Result<ReadingState> {
+        self.with_store(store_path, |store| {
+            let state = store.set_completed(hash, path, completed);
+            store.save(store_path)?;
+            Ok(state)
+        })
+    }
+
+    pub fn get(&self, store_path: &Path, hash: &str) -> Option<ReadingState> {
+        self.with_store(store_path, |store| store.items.get(hash).cloned())
+    }
+
+    pub fn recent(&self, store_path: &Path, limit: usize) -> Vec<ReadingState> {
+        self.with_store(store_path, |store| store.recent(limit))
+    }
+
+    pub fn folder_progress(
+        &self,
+        store_path: &Path,
+        directory: &str,
+        files: &[(String, String)],
+    ) -> FolderProgress {
+        self.with_store(store_path, |store| store.folder_progress(directory, files))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_progress_follows_content() {
+        let mut store = ProgressStore::default();
+        let state = store.update("aaa", "/bd/XIII T01.cbz", 10, 48);
+        assert!(!state.completed);
+        assert!(store.update("aaa", "/bd/XIII T01.cbz", 47, 48).completed);
+        // Relecture : le tome reste lu
+        assert!(store.update("aaa", "/bd/XIII T01.cbz", 3, 48).completed);
+        store.update("bbb", "/bd/XIII T02.cbz", 5, 46);
+
+        // T02 renommé : même hash, nouvelle place
+        let files = vec![
+            ("/bd/XIII/T01.cbz".to_string(), "aaa".to_string()),
+            ("/bd/XIII/T02.cbz".to_string(), "bbb".to_string()),
+            ("/bd/XIII/T03.cbz".to_string(), "ccc".to_string()),
+        ];
+        let folder = store.folder_progress("/bd/XIII", &files);
+        assert_eq!(
+            (folder.total, folder.read, folder.in_progress, folder.unread),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(folder.last_read.unwrap().path, "/bd/XIII/T02.cbz");
+
+        let recent: Vec<_> = store.recent(10).into_iter().map(|s| s.hash).collect();
+        assert_eq!(recent, vec!["bbb"]);
+        assert!(
+            !store
+                .set_completed("aaa", "/bd/XIII/T01.cbz", false)
+                .completed
+        );
+    }
+
+    #[test]
+    fn marking_unread_forgets_the_comic() {
+        let mut store = ProgressStore::default();
+        let files = vec![("/bd/XIII/T01.cbz".to_string(), "aaa".to_string())];
+
+        assert!(
+            store
+                .set_completed("aaa", "/bd/XIII/T01.cbz", true)
+                .completed
+        );
+        let folder = store.folder_progress("/bd/XIII", &files);
+        assert_eq!((folder.read, folder.in_progress, folder.unread), (1, 0, 0));
+
+        let state = store.set_completed("aaa", "/bd/XIII/T01.cbz", false);
+        assert!(!state.completed && state.last_page == 0);
+        let folder = store.folder_progress("/bd/XIII", &files);
+        assert_eq!((folder.read, folder.in_progress, folder.unread), (0, 0, 1));
+        assert!(folder.last_read.is_none());
+        assert!(store.recent(10).is_empty());
+    }
+}
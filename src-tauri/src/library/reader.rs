@@ -0,0 +1,259 @@
+use super::archive;
+use super::file_stat;
+use crate::fs_ops::ensure_within;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::http::{header, Request, Response, StatusCode};
+
+// Listings kept in memory, one per recently opened archive
+const MAX_CACHED_ARCHIVES: usize = 16;
+// Pages the reader will probably ask for next
+const PREFETCH_PAGES: usize = 2;
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub index: usize,
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PageList {
+    pub path: String,
+    pub count: usize,
+    pub pages: Vec<PageInfo>,
+}
+
+struct CachedListing {
+    size: u64,
+    modified_at: f64,
+    pages: Vec<PageInfo>,
+    last_used: Instant,
+}
+
+lazy_static::lazy_static! {
+    static ref LISTINGS: Mutex<HashMap<PathBuf, CachedListing>> = Mutex::new(HashMap::new());
+}
+
+/// Pages of a CBZ/CBR/CB7 in reading order (natural sort of the image names)
+pub fn list_pages(path: &Path) -> Result<Vec<PageInfo>> {
+    let (size, modified_at) = file_stat(path).ok_or_else(|| anyhow!("File not found"))?;
+    let mut cache = LISTINGS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cached) = cache.get_mut(path) {
+        if cached.size == size && cached.modified_at == modified_at {
+            cached.last_used = Instant::now();
+            return Ok(cached.pages.clone());
+        }
+    }
+
+    let pages = read_listing(path)?;
+    if cache.len() >= MAX_CACHED_ARCHIVES {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, c)| c.last_used)
+            .map(|(p, _)| p.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        path.to_path_buf(),
+        CachedListing {
+            size,
+            modified_at,
+            pages: pages.clone(),
+            last_used: Instant::now(),
+        },
+    );
+    Ok(pages)
+}
+
+/// Bytes of one page, by its index in `list_pages`
+pub fn read_page(path: &Path, index: usize) -> Result<Vec<u8>> {
+    let pages = list_pages(path)?;
+    let page = pages
+        .get(index)
+        .ok_or_else(|| anyhow!("Page {} out of range", index))?;
+    archive::read_entry(path, &page.name)
+}
+
+fn read_listing(path: &Path) -> Result<Vec<PageInfo>> {
+    Ok(archive::list_images(path)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| PageInfo {
+            index,
+            name: entry.name,
+            size: entry.size,
+        })
+        .collect())
+}
+
+fn content_type(name: &str) -> &'static str {
+    let ext = name
+        .rsplit_once('.')
+        .map(|(_, e)| e.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        _ => "image/jpeg",
+    }
+}
+
+/// Handler of the `vkomic-page://` scheme:
+/// `/<encoded file path>` returns the page list as JSON,
+/// `/<encoded file path>/<index>` returns one page image.
+/// Only files inside `root` (the download folder) are served.
+pub fn serve(request: &Request<Vec<u8>>, root: &str) -> Response<Vec<u8>> {
+    let mut segments = request.uri().path().trim_start_matches('/').splitn(2, '/');
+    let encoded = segments.next().unwrap_or_default();
+    let page = segments.next().filter(|s| !s.is_empty());
+
+    let path = match urlencoding::decode(encoded) {
+        Ok(path) => path,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+    let path = match ensure_within(root, &path) {
+        Ok(path) => path,
+        Err(e) => return error_response(StatusCode::FORBIDDEN, &e.to_string()),
+    };
+    let Some((size, modified_at)) = file_stat(&path) else {
+        return error_response(StatusCode::NOT_FOUND, "File not found");
+    };
+
+    // Le contenu d'une page ne change qu'avec l'archive : taille + mtime suffisent
+    let etag = format!(
+        "\"{}-{}-{}\"",
+        size,
+        modified_at as u64,
+        page.unwrap_or("list")
+    );
+    let not_modified = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|v| v.as_bytes() == etag.as_bytes());
+    if not_modified {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, etag)
+            .body(Vec::new())
+            .unwrap_or_default();
+    }
+
+    let result = match page {
+        None => list_pages(&path).and_then(|pages| {
+            let list = PageList {
+                path: path.to_string_lossy().to_string(),
+                count: pages.len(),
+                pages,
+            };
+            Ok(("application/json", serde_json::to_vec(&list)?, None))
+        }),
+        Some(index) => match index.parse::<usize>() {
+            Ok(index) => page_body(&path, index),
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "Invalid page index"),
+        },
+    };
+
+    match result {
+        Ok((mime, body, prefetch)) => {
+            let mut builder = Response::builder()
+                .header(header::CONTENT_TYPE, mime)
+                .header(header::ETAG, etag)
+                .header(header::CACHE_CONTROL, "max-age=3600")
+                .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+            if let Some(prefetch) = prefetch {
+                builder = builder.header(header::LINK, prefetch);
+            }
+            builder.body(body).unwrap_or_default()
+        }
+        Err(e) => error_response(StatusCode::NOT_FOUND, &e.to_string()),
+    }
+}
+
+/// Page bytes with a `Link: rel=prefetch` hint for the following pages
+fn page_body(path: &Path, index: usize) -> Result<(&'static str, Vec<u8>, Option<String>)> {
+    let pages = list_pages(path)?;
+    let bytes = read_page(path, index)?;
+    let mime = content_type(&pages[index].name);
+    // URLs relatives : "/<chemin>/3" -> "4"
+    let next: Vec<String> = (index + 1..pages.len())
+        .take(PREFETCH_PAGES)
+        .map(|i| format!("<{}>; rel=prefetch", i))
+        .collect();
+    Ok((mime, bytes, (!next.is_empty()).then(|| next.join(", "))))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(message.as_bytes().to_vec())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page, write_zip, TempDir};
+
+    #[test]
+    fn reader_serves_pages_in_natural_order() {
+        let dir = TempDir::new("reader");
+        let cbz = dir.join("Tome 1.cbz");
+        write_zip(&cbz, &["p10.png", "notes.txt", "p2.png", "p1.png"]);
+
+        let names: Vec<_> = list_pages(&cbz)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["p1.png", "p2.png", "p10.png"]);
+
+        let encoded = urlencoding::encode(&cbz.to_string_lossy()).to_string();
+        let request = |uri: String| {
+            tauri::http::Request::builder()
+                .uri(uri)
+                .body(Vec::new())
+                .unwrap()
+        };
+        let root = dir.path().to_string_lossy().to_string();
+        let response = serve(
+            &request(format!("vkomic-page://localhost/{}/1", encoded)),
+            &root,
+        );
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "image/png");
+        assert_eq!(response.headers()["link"], "<2>; rel=prefetch");
+        assert_eq!(response.body(), &page(200));
+
+        let list = serve(
+            &request(format!("vkomic-page://localhost/{}", encoded)),
+            &root,
+        );
+        let json: serde_json::Value = serde_json::from_slice(list.body()).unwrap();
+        assert_eq!(json["count"], 3);
+
+        let missing = serve(
+            &request(format!("vkomic-page://localhost/{}/3", encoded)),
+            &root,
+        );
+        assert_eq!(missing.status(), 404);
+
+        let outside = urlencoding::encode("/etc/passwd").to_string();
+        let forbidden = serve(
+            &request(format!("vkomic-page://localhost/{}", outside)),
+            &root,
+        );
+        assert_eq!(forbidden.status(), 403);
+    }
+}
@@ -0,0 +1,241 @@
+use super::archive;
+use super::file_stat;
+use crate::fs_ops::ensure_within;
+use anyhow::{anyhow, Result};
+use image::codecs::jpeg::JpegEncoder;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{BufWriter, Read};
+use std::path::{Path, PathBuf};
+use tauri::http::{Request, Response, StatusCode};
+
+// Taille max de la miniature, ratio conservé
+const THUMB_WIDTH: u32 = 300;
+const THUMB_HEIGHT: u32 = 450;
+const THUMB_QUALITY: u8 = 80;
+
+// Scanned PDFs keep the first page near the start; don't read huge files whole
+const MAX_PDF_SCAN_BYTES: u64 = 256 * 1024 * 1024;
+// Smaller embedded images are logos or masks, not pages
+const MIN_PDF_IMAGE_WIDTH: u32 = 200;
+
+/// Cached thumbnail for a comic file, generated on first request.
+/// The cache key covers path, size and mtime so edited files get a new cover.
+pub fn thumbnail_path(cache_dir: &Path, path: &Path) -> Result<PathBuf> {
+    let (size, modified_at) = file_stat(path).ok_or_else(|| anyhow!("File not found"))?;
+    let key = format!("{}|{}|{}", path.to_string_lossy(), size, modified_at);
+    let hash: String = Sha256::digest(key.as_bytes())
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let cached = cache_dir.join(format!("{}.jpg", hash));
+    if cached.is_file() {
+        return Ok(cached);
+    }
+
+    let bytes = extract_cover(path)?;
+    let cover = image::load_from_memory(&bytes)?;
+    let thumb = cover.thumbnail(THUMB_WIDTH, THUMB_HEIGHT).to_rgb8();
+
+    std::fs::create_dir_all(cache_dir)?;
+    // Écriture atomique : une miniature à moitié écrite ne doit jamais être servie
+    let tmp = cached.with_extension("jpg.tmp");
+    {
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        JpegEncoder::new_with_quality(&mut out, THUMB_QUALITY).encode_image(&thumb)?;
+    }
+    std::fs::rename(&tmp, &cached)?;
+    Ok(cached)
+}
+
+/// Raw bytes of the cover image: `cover.*` or the first page (ZIP, RAR, 7z) or PDF page image
+pub fn extract_cover(path: &Path) -> Result<Vec<u8>> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "pdf" => pdf_cover(path),
+        _ => archive_cover(path),
+    }
+}
+
+/// Handler of the `vkomic-thumb://` scheme, the path being the encoded file path.
+/// Only files inside `root` (the download folder) are opened.
+pub fn serve(cache_dir: &Path, request: &Request<Vec<u8>>, root: &str) -> Response<Vec<u8>> {
+    let encoded = request.uri().path().trim_start_matches('/');
+    let path = match urlencoding::decode(encoded) {
+        Ok(path) => ensure_within(root, &path),
+        Err(e) => Err(anyhow!(e)),
+    };
+    let path = match path {
+        Ok(path) => path,
+        Err(e) => {
+            return Response::builder()
+                .status(StatusCode::FORBIDDEN)
+                .header("Content-Type", "text/plain")
+                .body(e.to_string().into_bytes())
+                .unwrap_or_default()
+        }
+    };
+    let result = thumbnail_path(cache_dir, &path).and_then(|thumb| Ok(std::fs::read(thumb)?));
+
+    match result {
+        Ok(bytes) => Response::builder()
+            .header("Content-Type", "image/jpeg")
+            .header("Cache-Control", "max-age=86400")
+            .body(bytes),
+        Err(e) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .header("Content-Type", "text/plain")
+            .body(e.to_string().into_bytes()),
+    }
+    .unwrap_or_default()
+}
+
+fn archive_cover(path: &Path) -> Result<Vec<u8>> {
+    let images = archive::list_images(path)?;
+    // cover.jpg / Cover.png gagne, sinon la première page
+    let cover = images
+        .iter()
+        .find(|e| {
+            let file_name = e.name.rsplit(['/', '\\']).next().unwrap_or(&e.name);
+            file_name.to_lowercase().starts_with("cover.")
+        })
+        .or_else(|| images.first())
+        .ok_or_else(|| anyhow!("No image in archive"))?;
+    archive::read_entry(path, &cover.name)
+}
+
+/// First JPEG page image (DCTDecode stream) of a PDF, read straight from the file.
+/// Scans store each page as a JPEG, so no PDF renderer is needed.
+fn pdf_cover(path: &Path) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    File::open(path)?
+        .take(MAX_PDF_SCAN_BYTES)
+        .read_to_end(&mut data)?;
+
+    let mut pos = 0;
+    while let Some(offset) = find_bytes(&data[pos..], b"/DCTDecode") {
+        let at = pos + offset;
+        pos = at + 1;
+
+        // Dictionnaire de l'objet : du "obj" précédent jusqu'au mot-clé "stream"
+        let Some(start) = rfind_bytes(&data[..at], b"obj") else {
+            continue;
+        };
+        let Some(stream) = find_bytes(&data[at..], b"stream").map(|i| at + i) else {
+            continue;
+        };
+        let dict = &data[start..stream];
+        if find_bytes(dict, b"/Image").is_none() {
+            continue;
+        }
+        if dict_number(dict, b"/Width").is_some_and(|w| w < MIN_PDF_IMAGE_WIDTH) {
+            continue;
+        }
+
+        let mut body = stream + b"stream".len();
+        if data.get(body) == Some(&b'\r') {
+            body += 1;
+        }
+        if data.get(body) == Some(&b'\n') {
+            body += 1;
+        }
+        let Some(end) = find_bytes(&data[body..], b"endstream").map(|i| body + i) else {
+            continue;
+        };
+        // Filtres chaînés ([/FlateDecode /DCTDecode]) : le flux n'est pas un JPEG brut
+        let jpeg = &data[body..end];
+        if jpeg.starts_with(&[0xFF, 0xD8]) {
+            let len = jpeg
+                .windows(2)
+                .rposition(|w| w == [0xFF, 0xD9])
+                .map_or(jpeg.len(), |i| i + 2);
+            return Ok(jpeg[..len].to_vec());
+        }
+    }
+    Err(anyhow!("No JPEG page image in PDF"))
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn rfind_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|w| w == needle)
+}
+
+fn dict_number(dict: &[u8], key: &[u8]) -> Option<u32> {
+    let start = find_bytes(dict, key)? + key.len();
+    let digits: String = dict[start..]
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take_while(|b| b.is_ascii_digit())
+        .map(|b| *b as char)
+        .collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{page, write_zip, TempDir};
+
+    #[test]
+    fn zip_cover_prefers_cover_then_first_page() {
+        let dir = TempDir::new("thumbs");
+
+        // "p2" passe avant "p10", les métadonnées macOS sont ignorées
+        let pages = dir.join("pages.cbz");
+        write_zip(&pages, &["__MACOSX/._p1.png", "p10.png", "p2.png"]);
+        assert_eq!(
+            extract_cover(&pages).unwrap(),
+            page(200),
+            "first page in natural order"
+        );
+
+        let with_cover = dir.join("cover.cbz");
+        write_zip(&with_cover, &["01.png", "Cover.png"]);
+        assert_eq!(extract_cover(&with_cover).unwrap(), page(100));
+
+        let cache = dir.join("cache");
+        let thumb = thumbnail_path(&cache, &with_cover).unwrap();
+        let decoded = image::open(&thumb).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (300, 450));
+        assert_eq!(thumbnail_path(&cache, &with_cover).unwrap(), thumb);
+    }
+
+    #[test]
+    fn thumbnails_stay_inside_the_root() {
+        let root = TempDir::new("thumbs_root");
+        let outside = TempDir::new("thumbs_outside");
+        let inside = root.join("Tome 1.cbz");
+        write_zip(&inside, &["01.png"]);
+        let secret = outside.join("Tome 2.cbz");
+        write_zip(&secret, &["01.png"]);
+
+        let cache = root.join(".cache");
+        let serve_path = |path: &str| {
+            let request = tauri::http::Request::builder()
+                .uri(format!(
+                    "vkomic-thumb://localhost/{}",
+                    urlencoding::encode(path)
+                ))
+                .body(Vec::new())
+                .unwrap();
+            serve(&cache, &request, &root.path().to_string_lossy()).status()
+        };
+
+        assert_eq!(serve_path(&inside.to_string_lossy()), 200);
+        assert_eq!(serve_path(&secret.to_string_lossy()), 403);
+        // "<root>/../<autre dossier>" : normalisé avant la vérification
+        let traversal = root
+            .join("..")
+            .join(outside.path().file_name().unwrap())
+            .join("Tome 2.cbz");
+        assert_eq!(serve_path(&traversal.to_string_lossy()), 403);
+        assert_eq!(serve_path("/etc/passwd"), 403);
+    }
+}
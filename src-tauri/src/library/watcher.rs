@@ -0,0 +1,233 @@
+use super::Library;
+use anyhow::Result;
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+// Copies and downloads fire bursts of events: apply them once things are quiet
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+    Renamed,
+    Rescanned, // Changes made while the app was closed
+}
+
+/// Payload item of the `library-changed` event
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryChange {
+    pub kind: ChangeKind,
+    pub path: String,
+    pub from: Option<String>,
+}
+
+/// Watches the download root and keeps the library index current
+#[derive(Clone, Default)]
+pub struct LibraryWatcher {
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+}
+
+impl LibraryWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(
+        &self,
+        app: AppHandle,
+        library: Library,
+        index_path: PathBuf,
+        root: PathBuf,
+    ) -> Result<()> {
+        if !root.is_dir() {
+            return Err(anyhow::anyhow!("Path is not a directory"));
+        }
+
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event);
+            }
+        })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        // Dropping the previous watcher closes its channel, which ends its worker thread
+        *self.watcher.lock().unwrap_or_else(|e| e.into_inner()) = Some(watcher);
+        std::thread::spawn(move || run_worker(app, library, index_path, root, rx));
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        *self.watcher.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+fn run_worker(
+    app: AppHandle,
+    library: Library,
+    index_path: PathBuf,
+    root: PathBuf,
+    rx: Receiver<Event>,
+) {
+    // Fichiers déplacés ou supprimés pendant que l'app était fermée
+    match library.scan(&index_path, &root) {
+        Ok(summary) if summary.added + summary.updated + summary.removed > 0 => {
+            emit_changes(
+                &app,
+                vec![LibraryChange {
+                    kind: ChangeKind::Rescanned,
+                    path: root.to_string_lossy().to_string(),
+                    from: None,
+                }],
+            );
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Library scan failed: {}", e),
+    }
+
+    let mut pending: Vec<LibraryChange> = Vec::new();
+    loop {
+        let event = if pending.is_empty() {
+            match rx.recv() {
+                Ok(event) => Some(event),
+                Err(_) => break,
+            }
+        } else {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        };
+
+        match event {
+            Some(event) => pending.extend(event_changes(&event)),
+            None => {
+                let changes =
+                    apply_changes(&library, &index_path, &root, std::mem::take(&mut pending));
+                if !changes.is_empty() {
+                    emit_changes(&app, changes);
+                }
+            }
+        }
+    }
+}
+
+fn emit_changes(app: &AppHandle, changes: Vec<LibraryChange>) {
+    let _ = app.emit("library-changed", changes);
+}
+
+fn event_changes(event: &Event) -> Vec<LibraryChange> {
+    let change = |kind, path: &PathBuf| LibraryChange {
+        kind,
+        path: path.to_string_lossy().to_string(),
+        from: None,
+    };
+
+    let kind = match event.kind {
+        EventKind::Create(_) => ChangeKind::Created,
+        EventKind::Remove(_) => ChangeKind::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() >= 2 => {
+            return vec![LibraryChange {
+                kind: ChangeKind::Renamed,
+                path: event.paths[1].to_string_lossy().to_string(),
+                from: Some(event.paths[0].to_string_lossy().to_string()),
+            }];
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => ChangeKind::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => ChangeKind::Created,
+        // Some backends don't tell which side of the rename this is
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            return event
+                .paths
+                .iter()
+                .map(|p| {
+                    let kind = if p.exists() {
+                        ChangeKind::Created
+                    } else {
+                        ChangeKind::Removed
+                    };
+                    change(kind, p)
+                })
+                .collect();
+        }
+        EventKind::Modify(_) => ChangeKind::Modified,
+        _ => return Vec::new(),
+    };
+
+    event.paths.iter().map(|p| change(kind, p)).collect()
+}
+
+/// Update the index and keep the changes that touched a comic file
+fn apply_changes(
+    library: &Library,
+    index_path: &Path,
+    root: &Path,
+    changes: Vec<LibraryChange>,
+) -> Vec<LibraryChange> {
+    // inotify reports a rename as a pair and as separate from / to events
+    let renamed: Vec<(String, String)> = changes
+        .iter()
+        .filter_map(|c| c.from.clone().map(|from| (from, c.path.clone())))
+        .collect();
+    let mut unique: Vec<LibraryChange> = Vec::new();
+    for change in changes {
+        let covered_by_rename = change.kind != ChangeKind::Renamed
+            && renamed
+                .iter()
+                .any(|(from, to)| change.path == *from || change.path == *to);
+        if !covered_by_rename && !unique.contains(&change) {
+            unique.push(change);
+        }
+    }
+
+    let paths: Vec<PathBuf> = unique
+        .iter()
+        .flat_map(|c| {
+            std::iter::once(PathBuf::from(&c.path)).chain(c.from.as_ref().map(PathBuf::from))
+        })
+        .collect();
+    let counts = match library.refresh_paths(index_path, root, &paths) {
+        Ok(counts) => counts,
+        Err(e) => {
+            log::warn!("Library index update failed: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut counts = counts.into_iter();
+    let mut result = Vec::new();
+    for mut change in unique {
+        let to_count = counts.next().unwrap_or(0);
+        let from_count = if change.from.is_some() {
+            counts.next().unwrap_or(0)
+        } else {
+            0
+        };
+        if to_count + from_count == 0 {
+            continue;
+        }
+        // "Tome.cbz.part" -> "Tome.cbz" is a new file for the library
+        if change.kind == ChangeKind::Renamed {
+            if from_count == 0 {
+                change.kind = ChangeKind::Created;
+                change.from = None;
+            } else if to_count == 0 {
+                change.kind = ChangeKind::Removed;
+                change.path = change.from.take().unwrap_or_default();
+            }
+        }
+        result.push(change);
+    }
+    result
+}
@@ -0,0 +1,365 @@
+use crate::fs_ops::{portable_name, shorten_name, MAX_COMPONENT_BYTES, MAX_PATH_CHARS};
+use crate::library::COMIC_EXTENSIONS;
+use crate::vk_parser::metadata::parse_title;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::Path;
+
+// Components are never shortened below this when fitting a long path
+const MIN_COMPONENT_BYTES: usize = 16;
+
+const FIELDS: &[&str] = &[
+    "category",
+    "parent",
+    "series",
+    "title",
+    "volume_title",
+    "volume",
+    "volume_end",
+    "issue",
+    "number",
+    "year",
+    "language",
+    "ext",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Field { name: String, width: Option<usize> },
+    Optional(Vec<Segment>), // Dropped whole when one of its fields is empty
+}
+
+/// Download layout such as `{category}/{series}/{series}< - T{volume:02}>< ({year})>.{ext}`.
+/// `/` separates folders, `{field:02}` zero-pads numbers and `<...>` is only kept
+/// when every field inside has a value (`<` and `>` can't appear in file names anyway).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamingTemplate {
+    components: Vec<Vec<Segment>>,
+}
+
+/// Values available to a template, from the document title and its `VkNode` ancestry
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NamingContext {
+    pub category: Option<String>, // Top-level node (topic / category)
+    pub parent: Option<String>,   // Node the document is listed under
+    pub series: Option<String>,
+    pub title: String, // Document title without extension
+    pub volume_title: Option<String>,
+    pub volume: Option<u32>,
+    pub volume_end: Option<u32>,
+    pub issue: Option<u32>,
+    pub year: Option<u16>,
+    pub language: Option<String>,
+    pub ext: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedPath {
+    pub directory: String,
+    pub file_name: String,
+    pub relative_path: String, // Always with "/" separators
+}
+
+impl NamingTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut components = vec![Vec::new()];
+        let mut optional: Option<Vec<Segment>> = None;
+        let mut chars = template.trim().chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let segment = match c {
+                '/' | '\\' => {
+                    if optional.is_some() {
+                        return Err(anyhow!("Folder separator inside <...>"));
+                    }
+                    components.push(Vec::new());
+                    continue;
+                }
+                '<' => {
+                    if optional.is_some() {
+                        return Err(anyhow!("Nested <...> are not supported"));
+                    }
+                    optional = Some(Vec::new());
+                    continue;
+                }
+                '>' => {
+                    let inner = optional.take().ok_or_else(|| anyhow!("Unmatched '>'"))?;
+                    Segment::Optional(inner)
+                }
+                '{' => {
+                    let mut spec = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => spec.push(c),
+                            None => return Err(anyhow!("Unclosed '{{'")),
+                        }
+                    }
+                    parse_field(&spec)?
+                }
+                '}' => return Err(anyhow!("Unmatched '}}'")),
+                c => {
+                    let mut text = c.to_string();
+                    while let Some(&next) = chars.peek() {
+                        if "/\\<>{}".contains(next) {
+                            break;
+                        }
+                        text.push(next);
+                        chars.next();
+                    }
+                    Segment::Text(text)
+                }
+            };
+            match optional.as_mut() {
+                Some(inner) => inner.push(segment),
+                None => components.last_mut().unwrap().push(segment),
+            }
+        }
+        if optional.is_some() {
+            return Err(anyhow!("Unclosed '<'"));
+        }
+
+        components.retain(|segments| !segments.is_empty());
+        if components.is_empty() {
+            return Err(anyhow!("Empty template"));
+        }
+        Ok(NamingTemplate { components })
+    }
+
+    /// Folders then file name, each already portable. Empty folders are dropped and the
+    /// document extension is appended when the template doesn't produce it.
+    pub fn render(&self, context: &NamingContext) -> Vec<String> {
+        let mut parts: Vec<String> = self
+            .components
+            .iter()
+            .map(|segments| tidy(&render_segments(segments, context).0))
+            .collect();
+
+        let mut file_name = parts
+            .pop()
+            .filter(|f| !f.is_empty())
+            .unwrap_or_else(|| context.title.clone());
+        if let Some(ext) = &context.ext {
+            let suffix = format!(".{}", ext);
+            if !file_name.to_lowercase().ends_with(&suffix) {
+                file_name.push_str(&suffix);
+            }
+        }
+        parts.retain(|p| !p.is_empty());
+        parts.push(file_name);
+        parts
+            .iter()
+            .map(|p| portable_name(p, MAX_COMPONENT_BYTES))
+            .collect()
+    }
+
+    /// Render under `root`, shortening the longest components until the whole path
+    /// fits in MAX_PATH_CHARS. The same inputs always give the same path.
+    pub fn resolve(&self, root: &Path, context: &NamingContext) -> ResolvedPath {
+        let mut parts = self.render(context);
+        let root_chars = root.to_string_lossy().chars().count();
+        loop {
+            let total = root_chars + parts.iter().map(|p| p.chars().count() + 1).sum::<usize>();
+            if total <= MAX_PATH_CHARS {
+                break;
+            }
+            let Some((index, longest)) = parts
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.len() > MIN_COMPONENT_BYTES)
+                .max_by_key(|(i, p)| (p.len(), *i))
+            else {
+                break;
+            };
+            let target = longest
+                .len()
+                .saturating_sub(total - MAX_PATH_CHARS)
+                .max(MIN_COMPONENT_BYTES);
+            parts[index] = shorten_name(longest, target.min(longest.len() - 1));
+        }
+
+        let file_name = parts.pop().unwrap_or_default();
+        let directory = parts.iter().fold(root.to_path_buf(), |dir, p| dir.join(p));
+        parts.push(file_name.clone());
+        ResolvedPath {
+            directory: directory.to_string_lossy().to_string(),
+            file_name,
+            relative_path: parts.join("/"),
+        }
+    }
+}
+
+fn parse_field(spec: &str) -> Result<Segment> {
+    let (name, format) = match spec.split_once(':') {
+        Some((name, format)) => (name.trim(), Some(format.trim())),
+        None => (spec.trim(), None),
+    };
+    if !FIELDS.contains(&name) {
+        return Err(anyhow!(
+            "Unknown field {{{}}} (expected one of: {})",
+            name,
+            FIELDS.join(", ")
+        ));
+    }
+    let width = match format {
+        Some(format) => Some(
+            format
+                .parse::<usize>()
+                .ok()
+                .filter(|w| *w <= 9)
+                .ok_or_else(|| anyhow!("Invalid width in {{{}}}", spec))?,
+        ),
+        None => None,
+    };
+    Ok(Segment::Field {
+        name: name.to_string(),
+        width,
+    })
+}
+
+/// Rendered text, and whether every field had a value (`<...>` is dropped otherwise)
+fn render_segments(segments: &[Segment], context: &NamingContext) -> (String, bool) {
+    let mut out = String::new();
+    let mut complete = true;
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => out.push_str(text),
+            Segment::Field { name, width } => match context.field(name, *width) {
+                Some(value) => out.push_str(&value),
+                None => complete = false,
+            },
+            Segment::Optional(inner) => {
+                let (text, inner_complete) = render_segments(inner, context);
+                if inner_complete {
+                    out.push_str(&text);
+                }
+            }
+        }
+    }
+    (out, complete)
+}
+
+/// Remove what empty fields leave behind: "()", "[]", doubled spaces, dangling " - "
+fn tidy(component: &str) -> String {
+    let mut text = component.replace("()", "").replace("[]", "");
+    while text.contains("  ") {
+        text = text.replace("  ", " ");
+    }
+    text.trim_matches(|c: char| c == ' ' || c == '-' || c == '_')
+        .to_string()
+}
+
+impl NamingContext {
+    /// `ancestors` are the titles of the nodes above the document, root first
+    pub fn from_document(title: &str, extension: Option<&str>, ancestors: &[String]) -> Self {
+        let title = title.trim();
+        let (stem, title_ext) = match title.rsplit_once('.') {
+            Some((stem, ext)) if COMIC_EXTENSIONS.contains(&ext.to_lowercase().as_str()) => {
+                (stem.trim(), Some(ext.to_lowercase()))
+            }
+            _ => (title, None),
+        };
+        let meta = parse_title(stem);
+        let parent = ancestors.last().filter(|p| !p.trim().is_empty()).cloned();
+        // Le nœud parent est stable d'un tome à l'autre, contrairement aux titres
+        let series = parent
+            .as_deref()
+            .and_then(|p| parse_title(p).series)
+            .or_else(|| meta.series.clone())
+            .or_else(|| parent.clone());
+
+        NamingContext {
+            category: ancestors.first().filter(|c| !c.trim().is_empty()).cloned(),
+            parent,
+            series,
+            title: stem.to_string(),
+            volume_title: meta.volume_title.clone(),
+            volume: meta.volume,
+            volume_end: meta.volume_end,
+            issue: meta.issue,
+            year: meta.year,
+            language: meta.language.clone(),
+            ext: extension
+                .map(|e| e.trim_start_matches('.').to_lowercase())
+                .filter(|e| !e.is_empty())
+                .or(title_ext),
+        }
+    }
+
+    fn field(&self, name: &str, width: Option<usize>) -> Option<String> {
+        let number =
+            |n: Option<u32>| n.map(|n| format!("{:0width$}", n, width = width.unwrap_or(0)));
+        let value = match name {
+            "category" => self.category.clone(),
+            "parent" => self.parent.clone(),
+            "series" => self.series.clone(),
+            "title" => Some(self.title.clone()),
+            "volume_title" => self.volume_title.clone(),
+            "volume" => number(self.volume),
+            "volume_end" => number(self.volume_end),
+            "issue" => number(self.issue),
+            "number" => number(self.volume.or(self.issue)),
+            "year" => self.year.map(|y| y.to_string()),
+            "language" => self.language.clone(),
+            "ext" => self.ext.clone(),
+            _ => None,
+        };
+        value.filter(|v| !v.trim().is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(title: &str, ancestors: &[&str]) -> NamingContext {
+        let ancestors: Vec<String> = ancestors.iter().map(|a| a.to_string()).collect();
+        NamingContext::from_document(title, Some("cbz"), &ancestors)
+    }
+
+    #[test]
+    fn renders_series_layout() {
+        let template =
+            NamingTemplate::parse("{category}/{series}/{series}< - T{volume:02}>< ({year})>.{ext}")
+                .unwrap();
+        let full = context(
+            "Blacksad T3 - Âme rouge (2005) [FR].cbz",
+            &["BD", "Blacksad"],
+        );
+        assert_eq!(
+            template.render(&full),
+            vec!["BD", "Blacksad", "Blacksad - T03 (2005).cbz"]
+        );
+
+        // Champs absents : les sections <...> disparaissent, le dossier vide aussi
+        let bare = context("Le Transperceneige", &[]);
+        assert_eq!(
+            template.render(&bare),
+            vec!["Le Transperceneige", "Le Transperceneige.cbz"]
+        );
+    }
+
+    #[test]
+    fn paths_are_portable_and_bounded() {
+        let template = NamingTemplate::parse("{parent}/{title}").unwrap();
+        let rendered = template.render(&context("CON", &["a:b/c. "]));
+        assert_eq!(rendered, vec!["a_b_c", "_CON.cbz"]);
+
+        // NFD (macOS) et NFC donnent le même chemin
+        let nfd = template.render(&context("Ame\u{301}lie", &["S"]));
+        assert_eq!(nfd[1], "Amélie.cbz");
+
+        let long = "x".repeat(400);
+        let root = Path::new("C:\\Users\\someone\\Comics");
+        let resolved = template.resolve(root, &context(&long, &[&long]));
+        let full = Path::new(&resolved.directory).join(&resolved.file_name);
+        assert!(full.to_string_lossy().chars().count() <= MAX_PATH_CHARS);
+        assert!(resolved.file_name.ends_with(".cbz"));
+        assert_eq!(resolved, template.resolve(root, &context(&long, &[&long])));
+
+        assert!(NamingTemplate::parse("{serie}").is_err());
+        assert!(NamingTemplate::parse("<{series}").is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::download::ConflictPolicy;
+use crate::vk_parser::rules::{load_rules_file, set_active_rules, RulesSummary};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager};
@@ -12,6 +14,12 @@ pub struct AppSettings {
     pub vk_topic_id: String,
     #[serde(default)]
     pub vk_download_path: String,
+    #[serde(default)]
+    pub write_comic_info: bool, // Embed ComicInfo.xml into downloaded CBZ/ZIP
+    #[serde(default)]
+    pub naming_template: String, // Empty: legacy "<folder>/<title>" layout
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy, // When the target file already exists
 }
 
 fn settings_path(app: &AppHandle) -> Option<PathBuf> {
@@ -42,3 +50,48 @@ pub fn save_settings(app: &AppHandle, settings: &AppSettings) -> anyhow::Result<
     std::fs::write(&path, json)?;
     Ok(())
 }
+
+pub fn library_index_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|d| d.join("library_index.json"))
+}
+
+pub fn reading_progress_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|d| d.join("reading_progress.json"))
+}
+
+pub fn download_history_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|d| d.join("download_history.json"))
+}
+
+pub fn thumbnail_cache_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|d| d.join("thumbnails"))
+}
+
+fn parsing_rules_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|d| d.join("parsing_rules.json"))
+}
+
+/// (Re)load the user parsing rules. On error the previous rules stay active.
+pub fn load_parsing_rules(app: &AppHandle) -> anyhow::Result<RulesSummary> {
+    let path =
+        parsing_rules_path(app).ok_or_else(|| anyhow::anyhow!("Cannot resolve app data dir"))?;
+    let rules = load_rules_file(&path)?;
+    let summary = rules.summary(&path);
+    set_active_rules(rules);
+    Ok(summary)
+}
@@ -1,8 +1,14 @@
-use crate::vk_parser::{extract_documents, parse_topic_body, VkNode};
+use crate::vk_parser::rules::active_rules;
+use crate::vk_parser::{
+    classify_link, collect_topic_ids, container_type, extract_albums, extract_board_topics,
+    extract_documents, extract_photo_urls, flatten_wall_posts, parse_topic_body,
+    scope_comments_to_post, BoardTopic, LinkKind, NodeType, VkNode,
+};
 use anyhow::Result;
 use log::info;
 use reqwest::Client;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
@@ -10,6 +16,14 @@ use tokio::sync::Mutex;
 const VK_API_MIN_INTERVAL: Duration = Duration::from_millis(500);
 const VK_API_RETRY_BACKOFF: Duration = Duration::from_millis(1_500);
 const VKOMIC_USER_AGENT: &str = "KateMobileAndroid/110.1 lite-x86_64 (Android 11; SDK 30; x86_64; en)";
+// wall.get returns up to 100 items per call, 10 calls = 1000 posts per execute
+const WALL_BATCH_CALLS: usize = 10;
+// photos.get returns up to 1000 items per call, 5 calls per execute
+const ALBUM_BATCH_CALLS: usize = 5;
+// board.getTopics returns up to 100 items per call
+const TOPICS_BATCH_CALLS: usize = 25;
+// Comments fetched after a linked post to find the rest of its listing
+const POST_SCOPE_COUNT: usize = 20;
 
 static VK_API_LAST_REQUEST: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
 
@@ -32,17 +46,78 @@ fn board_get_comments_call(
     topic_id: &str,
     count: usize,
     offset: Option<usize>,
+    start_comment_id: Option<&str>,
 ) -> String {
     let offset_arg = offset
         .map(|value| format!(", \"offset\":{}", value))
         .unwrap_or_default();
+    let start_arg = start_comment_id
+        .map(|value| format!(", \"start_comment_id\":{}", value))
+        .unwrap_or_default();
 
     format!(
-        "API.board.getComments({{\"group_id\":{}, \"topic_id\":{}, \"count\":{}, \"extended\":1{}}})",
-        group_id, topic_id, count, offset_arg
+        "API.board.getComments({{\"group_id\":{}, \"topic_id\":{}, \"count\":{}, \"extended\":1{}{}}})",
+        group_id, topic_id, count, offset_arg, start_arg
     )
 }
 
+/// Unique vk.cc URLs among parsed nodes
+fn short_link_urls(nodes: &[VkNode]) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for node in nodes.iter().filter(|n| n.id.starts_with("link_")) {
+        if let Some(url) = &node.url {
+            if !urls.contains(url) {
+                urls.push(url.clone());
+            }
+        }
+    }
+    urls
+}
+
+/// Re-parse resolved short links so they become regular topic/doc/wall/album nodes.
+/// Unresolved links and targets we can't use stay in the tree as external links.
+fn apply_short_links(
+    nodes: &mut Vec<VkNode>,
+    resolved: &HashMap<String, String>,
+    exclude_topic_id: Option<&str>,
+) {
+    let mut dropped = std::collections::HashSet::new();
+    for (idx, node) in nodes.iter_mut().enumerate() {
+        if !node.id.starts_with("link_") {
+            continue;
+        }
+        let Some(link) = node.url.as_ref().and_then(|url| resolved.get(url)).cloned() else {
+            continue;
+        };
+        // Keep the title written next to the short link, unless it's the placeholder
+        let text = if node.title.starts_with("vk.cc/") {
+            link.clone()
+        } else {
+            format!("{} {}", node.title, link)
+        };
+
+        match parse_topic_body(&text, exclude_topic_id).into_iter().next() {
+            Some(target) => *node = target,
+            None => match classify_link(&link) {
+                // Points back to the excluded topic: nothing to show
+                Some(LinkKind::Topic) | Some(LinkKind::Document) => {
+                    dropped.insert(idx);
+                }
+                _ => node.url = Some(link),
+            },
+        }
+    }
+
+    // Resolved links may duplicate nodes that were linked directly
+    let mut seen_ids = std::collections::HashSet::new();
+    let mut idx = 0;
+    nodes.retain(|n| {
+        let keep = !dropped.contains(&idx) && seen_ids.insert(n.id.clone());
+        idx += 1;
+        keep
+    });
+}
+
 fn redact_access_tokens(value: &mut Value) {
     match value {
         Value::Object(map) => {
@@ -137,7 +212,7 @@ impl VkApi {
             return Ok(Vec::new());
         }
 
-        let nodes = parse_topic_body(&full_text, None);
+        let nodes = self.parse_and_resolve(&full_text, None).await;
         info!("Parsed {} nodes from topic body", nodes.len());
 
         // Filtrage heuristique comme dans la version classique pour éviter le bruit
@@ -151,7 +226,7 @@ impl VkApi {
 
         for node in &final_nodes {
             info!(
-                "Found node: {} (ID: {}, Type: {})",
+                "Found node: {} (ID: {}, Type: {:?})",
                 node.title, node.id, node.node_type
             );
         }
@@ -159,14 +234,23 @@ impl VkApi {
         Ok(final_nodes)
     }
 
-    /// Fetch the full content of a VK topic node: sub-topics + attached documents
-    pub async fn fetch_node_content(&self, group_id: &str, topic_id: &str) -> Result<VkNode> {
+    /// Fetch the full content of a VK topic node: sub-topics + attached documents.
+    /// With a post id, only the linked comment and its continuation are used.
+    pub async fn fetch_node_content(
+        &self,
+        group_id: &str,
+        topic_id: &str,
+        post_id: Option<&str>,
+    ) -> Result<VkNode> {
         info!(
-            "Fetching node content for group {} topic {}",
-            group_id, topic_id
+            "Fetching node content for group {} topic {} (post {:?})",
+            group_id, topic_id, post_id
         );
 
-        let items = self.fetch_all_comments(group_id, topic_id).await?;
+        let items = match post_id {
+            Some(post) => self.fetch_post_comments(group_id, topic_id, post).await?,
+            None => self.fetch_all_comments(group_id, topic_id).await?,
+        };
         info!("Fetched {} comments for node content", items.len());
 
         // 1. Extract sub-topics from text
@@ -176,7 +260,7 @@ impl VkApi {
             .collect::<Vec<_>>()
             .join("\n");
 
-        let sub_topics = parse_topic_body(&full_text, Some(topic_id));
+        let sub_topics = self.parse_and_resolve(&full_text, Some(topic_id)).await;
         info!("Found {} sub-topics", sub_topics.len());
 
         // 2. Extract documents from attachments
@@ -188,31 +272,169 @@ impl VkApi {
         children.extend(documents);
 
         // 4. Determine node type based on content
-        let node_type = if children.iter().any(|c| c.node_type == "file") {
-            "series".to_string()
-        } else {
-            "genre".to_string()
+        let node_type = container_type(&children);
+
+        let mut node = VkNode {
+            node_type,
+            children: Some(children),
+            is_loaded: Some(true),
+            structure_only: Some(false),
+            ..VkNode::topic(group_id, topic_id, post_id)
         };
+        active_rules().override_type(&mut node);
+        Ok(node)
+    }
+
+    /// Fetch the group wall as a node: sub-topics linked in posts + attached documents
+    pub async fn fetch_wall_content(&self, group_id: &str) -> Result<VkNode> {
+        info!("Fetching wall content for group {}", group_id);
+        let gid = group_id.replace('-', "");
+
+        let posts = self.fetch_all_wall_posts(&gid).await?;
+        let items = flatten_wall_posts(&posts);
+        info!(
+            "Fetched {} wall posts ({} with reposts)",
+            posts.len(),
+            items.len()
+        );
+
+        let full_text = items
+            .iter()
+            .filter_map(|i| i.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut children = self.parse_and_resolve(&full_text, None).await;
+        let documents = extract_documents(&items);
+        info!(
+            "Found {} sub-topics and {} documents on the wall",
+            children.len(),
+            documents.len()
+        );
+        children.extend(documents);
+
+        let node_type = container_type(&children);
 
         Ok(VkNode {
-            id: format!("topic_{}", topic_id),
-            title: format!("Topic {}", topic_id),
+            id: format!("wall_{}", gid),
+            title: "Wall".to_string(),
             node_type,
-            url: Some(format!("https://vk.com/topic-{}_{}", group_id, topic_id)),
-            vk_group_id: Some(group_id.to_string()),
-            vk_topic_id: Some(topic_id.to_string()),
+            url: Some(format!("https://vk.com/wall-{}", gid)),
+            vk_group_id: Some(gid),
             children: Some(children),
             is_loaded: Some(true),
-            count: None,
-            extension: None,
+            count: Some(posts.len() as i32),
             structure_only: Some(false),
-            vk_owner_id: None,
-            vk_doc_id: None,
-            vk_access_key: None,
-            size_bytes: None,
+            ..Default::default()
         })
     }
 
+    /// Fetch a single wall post (linked from a topic) as a node
+    pub async fn fetch_wall_post_content(&self, owner_id: &str, post_id: &str) -> Result<VkNode> {
+        info!("Fetching wall post {}_{}", owner_id, post_id);
+
+        let code = format!(
+            "return API.wall.getById({{\"posts\":\"{}_{}\"}});",
+            owner_id, post_id
+        );
+        let res = self.execute_with_retry(&code).await?;
+        let response = res
+            .get("response")
+            .ok_or_else(|| anyhow::anyhow!("No response body"))?;
+        // Depending on the API version, wall.getById returns an array or { items }
+        let posts = response
+            .as_array()
+            .or_else(|| response.get("items").and_then(|i| i.as_array()))
+            .cloned()
+            .unwrap_or_default();
+        let items = flatten_wall_posts(&posts);
+
+        let full_text = items
+            .iter()
+            .filter_map(|i| i.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut children = self.parse_and_resolve(&full_text, None).await;
+        children.extend(extract_documents(&items));
+
+        let node_type = container_type(&children);
+
+        Ok(VkNode {
+            node_type,
+            children: Some(children),
+            is_loaded: Some(true),
+            structure_only: Some(false),
+            ..VkNode::wall_post(owner_id, post_id)
+        })
+    }
+
+    /// List the photo albums of a group, each one downloadable as a CBZ
+    pub async fn fetch_albums(&self, group_id: &str) -> Result<Vec<VkNode>> {
+        info!("Fetching photo albums for group {}", group_id);
+        let owner_id = format!("-{}", group_id.replace('-', ""));
+
+        let code = format!(
+            "return API.photos.getAlbums({{\"owner_id\":{}, \"need_system\":0}});",
+            owner_id
+        );
+        let res = self.execute_with_retry(&code).await?;
+        let items = res
+            .get("response")
+            .and_then(|r| r.get("items"))
+            .and_then(|i| i.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let albums = extract_albums(&items, &owner_id);
+        info!("Found {} albums", albums.len());
+        Ok(albums)
+    }
+
+    /// Fetch the full-size image URLs of an album, in album order
+    pub async fn fetch_album_photo_urls(
+        &self,
+        owner_id: &str,
+        album_id: &str,
+    ) -> Result<Vec<String>> {
+        info!("Fetching photos of album {}_{}", owner_id, album_id);
+        let params = format!(
+            r#""owner_id":{}, "album_id":{}, "photo_sizes":1"#,
+            owner_id, album_id
+        );
+        let all_items = self
+            .fetch_all_paged("photos.get", &params, 1000, ALBUM_BATCH_CALLS)
+            .await?;
+
+        let urls = extract_photo_urls(&all_items);
+        info!("Album {}_{}: {} photos", owner_id, album_id, urls.len());
+        Ok(urls)
+    }
+
+    /// Fetch a linked comment and the comments right after it, scoped to that post
+    async fn fetch_post_comments(
+        &self,
+        group_id: &str,
+        topic_id: &str,
+        post_id: &str,
+    ) -> Result<Vec<Value>> {
+        let gid = group_id.replace('-', "");
+        let code = format!(
+            "return {};",
+            board_get_comments_call(&gid, topic_id, POST_SCOPE_COUNT, None, Some(post_id))
+        );
+
+        let res = self.execute_with_retry(&code).await?;
+        let items = res
+            .get("response")
+            .and_then(|r| r.get("items"))
+            .and_then(|i| i.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        Ok(scope_comments_to_post(&items, post_id))
+    }
+
     /// Fetch folder tree level-by-level (like the old app), not recursively per-node.
     /// This is MUCH faster because we batch all nodes at each depth.
     pub async fn fetch_folder_tree_recursive(
@@ -344,7 +566,7 @@ impl VkApi {
             .iter()
             .enumerate()
             .filter(|(_, n)| {
-                (n.node_type == "genre" || n.node_type == "category")
+                (n.node_type == NodeType::Genre || n.node_type == NodeType::Category)
                     && n.vk_group_id.is_some()
                     && n.vk_topic_id.is_some()
             })
@@ -369,8 +591,18 @@ impl VkApi {
                         let node = &nodes[idx];
                         let gid = node.vk_group_id.as_ref().unwrap().replace('-', "");
                         let tid = node.vk_topic_id.as_ref().unwrap();
-                        // Fetch the head (first 100), including attachments.
-                        board_get_comments_call(&gid, tid, 100, None)
+                        match node.vk_post_id.as_deref() {
+                            // Post links only need the linked comment and its continuation
+                            Some(post) => board_get_comments_call(
+                                &gid,
+                                tid,
+                                POST_SCOPE_COUNT,
+                                None,
+                                Some(post),
+                            ),
+                            // Fetch the head (first 100), including attachments.
+                            None => board_get_comments_call(&gid, tid, 100, None, None),
+                        }
                     })
                     .collect();
 
@@ -412,6 +644,16 @@ impl VkApi {
 
                                 if let Some(it_val) = r_map.get("items") {
                                     if let Some(preview_items) = it_val.as_array() {
+                                        let scoped_items;
+                                        let preview_items: &[Value] = match &node.vk_post_id {
+                                            Some(post) => {
+                                                scoped_items =
+                                                    scope_comments_to_post(preview_items, post);
+                                                &scoped_items
+                                            }
+                                            None => preview_items,
+                                        };
+
                                         let mut full_text = String::new();
                                         for item in preview_items {
                                             if let Some(t) = item
@@ -433,7 +675,7 @@ impl VkApi {
                                         node.is_loaded = Some(true);
                                         node.structure_only = Some(true);
 
-                                        if count > 100 {
+                                        if count > 100 && node.vk_post_id.is_none() {
                                             if let (Some(gid), Some(tid)) =
                                                 (&node.vk_group_id, &node.vk_topic_id)
                                             {
@@ -484,6 +726,25 @@ impl VkApi {
             }
         }
 
+        // Short links of the whole level in one go, not a checkLink call per node
+        let mut urls: Vec<String> = target_indices
+            .iter()
+            .filter_map(|&idx| nodes[idx].children.as_deref())
+            .flat_map(short_link_urls)
+            .collect();
+        urls.sort();
+        urls.dedup();
+        let resolved = self.check_short_links(&urls).await;
+        if !resolved.is_empty() {
+            for &idx in &target_indices {
+                let node = &mut nodes[idx];
+                let exclude_tid = node.vk_topic_id.clone();
+                if let Some(children) = node.children.as_mut() {
+                    apply_short_links(children, &resolved, exclude_tid.as_deref());
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -515,7 +776,7 @@ impl VkApi {
             gid,
             topic_id,
             batch_size,
-            board_get_comments_call(&gid, topic_id, 1, None)
+            board_get_comments_call(&gid, topic_id, 1, None, None)
         );
 
         let first_res = self.execute_with_retry(&first_code).await?;
@@ -528,6 +789,7 @@ impl VkApi {
             .and_then(|i| i.as_array())
             .ok_or_else(|| anyhow::anyhow!("No items in first response"))?;
 
+        #[allow(clippy::iter_cloned_collect)]
         let mut all_items: Vec<Value> = first_items.iter().cloned().collect();
         let total_count = response
             .get("total")
@@ -591,6 +853,183 @@ impl VkApi {
         Ok(all_items)
     }
 
+    /// List every topic of the group board, including the ones never linked from the index
+    pub async fn fetch_board_topics(&self, group_id: &str) -> Result<Vec<BoardTopic>> {
+        info!("Fetching board topics for group {}", group_id);
+        let gid = group_id.replace('-', "");
+        let params = format!(r#""group_id":{}, "preview":0"#, gid);
+        let all_items = self
+            .fetch_all_paged("board.getTopics", &params, 100, TOPICS_BATCH_CALLS)
+            .await?;
+
+        let topics = extract_board_topics(&all_items);
+        info!("Found {} board topics", topics.len());
+        Ok(topics)
+    }
+
+    /// Board topics that are not reachable from the indexed tree (nor the index topic itself)
+    pub async fn find_orphan_topics(
+        &self,
+        group_id: &str,
+        index_topic_id: &str,
+        tree: &[VkNode],
+    ) -> Result<Vec<BoardTopic>> {
+        let mut known = std::collections::HashSet::new();
+        collect_topic_ids(tree, &mut known);
+        known.insert(index_topic_id.to_string());
+
+        let orphans: Vec<BoardTopic> = self
+            .fetch_board_topics(group_id)
+            .await?
+            .into_iter()
+            .filter(|t| !known.contains(&t.id))
+            .collect();
+
+        info!(
+            "{} orphan topics ({} reachable from the index)",
+            orphans.len(),
+            known.len()
+        );
+        Ok(orphans)
+    }
+
+    async fn fetch_all_wall_posts(&self, gid: &str) -> Result<Vec<Value>> {
+        let params = format!(r#""owner_id":-{}"#, gid);
+        let all_items = self
+            .fetch_all_paged("wall.get", &params, 100, WALL_BATCH_CALLS)
+            .await?;
+
+        info!("fetch_all_wall_posts done: {} posts total", all_items.len());
+        Ok(all_items)
+    }
+
+    /// Parse topic text, then resolve the vk.cc short links it contains
+    async fn parse_and_resolve(&self, text: &str, exclude_topic_id: Option<&str>) -> Vec<VkNode> {
+        let mut nodes = parse_topic_body(text, exclude_topic_id);
+        let resolved = self.check_short_links(&short_link_urls(&nodes)).await;
+        apply_short_links(&mut nodes, &resolved, exclude_topic_id);
+        nodes
+    }
+
+    /// Resolve vk.cc URLs with utils.checkLink (or by following the redirect).
+    /// Best effort: a link that can't be resolved is simply missing from the map.
+    async fn check_short_links(&self, urls: &[String]) -> HashMap<String, String> {
+        let mut resolved: HashMap<String, String> = HashMap::new();
+        if urls.is_empty() {
+            return resolved;
+        }
+
+        info!("Resolving {} short links", urls.len());
+        for chunk in urls.chunks(25) {
+            let calls: Vec<String> = chunk
+                .iter()
+                .map(|url| format!("API.utils.checkLink({{\"url\":\"{}\"}}).link", url))
+                .collect();
+            let code = format!("return [{}];", calls.join(","));
+
+            // Un échec ici ne doit pas faire échouer la synchro : on tente la redirection
+            let res = match self.execute_with_retry(&code).await {
+                Ok(res) => res,
+                Err(e) => {
+                    info!("utils.checkLink failed: {}", e);
+                    continue;
+                }
+            };
+            if let Some(links) = res.get("response").and_then(|r| r.as_array()) {
+                for (url, link) in chunk.iter().zip(links) {
+                    if let Some(link) = link.as_str() {
+                        resolved.insert(url.clone(), link.to_string());
+                    }
+                }
+            }
+        }
+
+        // Fallback: follow the redirect ourselves
+        for url in urls {
+            if resolved.contains_key(url) {
+                continue;
+            }
+            match self.follow_redirect(url).await {
+                Ok(link) => {
+                    resolved.insert(url.clone(), link);
+                }
+                Err(e) => info!("Could not resolve short link {}: {}", url, e),
+            }
+        }
+
+        resolved
+    }
+
+    async fn follow_redirect(&self, url: &str) -> Result<String> {
+        let client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(VKOMIC_USER_AGENT)
+            .build()?;
+        let res = client.get(url).send().await?;
+        res.headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .map(|l| l.to_string())
+            .ok_or_else(|| anyhow::anyhow!("No redirect for {}", url))
+    }
+
+    /// Helper: page through a list method with `batch_calls` calls per execute.
+    /// `params` is the VKScript object body without count/offset.
+    async fn fetch_all_paged(
+        &self,
+        method: &str,
+        params: &str,
+        page_size: usize,
+        batch_calls: usize,
+    ) -> Result<Vec<Value>> {
+        let mut all_items: Vec<Value> = Vec::new();
+        let mut offset = 0usize;
+
+        loop {
+            let code = format!(
+                r#"
+                var off = {};
+                var i = 0;
+                var items = [];
+                var total = 0;
+                while (i < {}) {{
+                    var r = API.{}({{{}, "count":{}, "offset":off}});
+                    total = r.count;
+                    if (r.items) {{
+                        items = items + r.items;
+                    }}
+                    off = off + {};
+                    if (!r.items || r.items.length < {}) {{ i = 30; }}
+                    i = i + 1;
+                }}
+                return {{ "items": items, "next_offset": off, "total": total }};
+            "#,
+                offset, batch_calls, method, params, page_size, page_size, page_size
+            );
+
+            let val = self.execute_with_retry(&code).await?;
+            let response = val
+                .get("response")
+                .ok_or_else(|| anyhow::anyhow!("No response body"))?;
+            let items = response
+                .get("items")
+                .and_then(|i| i.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let total = response.get("total").and_then(|t| t.as_u64()).unwrap_or(0) as usize;
+
+            let fetched = items.len();
+            all_items.extend(items);
+            offset += batch_calls * page_size;
+
+            if fetched < batch_calls * page_size || offset >= total {
+                break;
+            }
+        }
+
+        Ok(all_items)
+    }
+
     /// Helper: execute VKScript with retry
     async fn execute_with_retry(&self, code: &str) -> Result<Value> {
         let url = "https://api.vk.ru/method/execute";
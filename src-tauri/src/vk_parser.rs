@@ -1,15 +1,40 @@
+pub mod duplicates;
+pub mod metadata;
+pub mod multipart;
+pub mod report;
+pub mod rules;
+#[cfg(test)]
+mod tests;
+
+use duplicates::DuplicateOf;
 use lazy_static::lazy_static;
+use metadata::{parse_title, TitleMetadata};
+use multipart::group_multipart;
 use regex::Regex;
+use rules::{active_rules, CompiledRules};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Kind of node in the tree, serialized as the lowercase strings the frontend uses
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    #[default]
+    Category,
+    Genre,
+    Series,
+    Topic,
+    File,
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct VkNode {
     pub id: String,
     pub title: String,
     pub url: Option<String>,
     #[serde(rename = "type")]
-    pub node_type: String, // category | genre | series | topic | file
+    pub node_type: NodeType,
     pub children: Option<Vec<VkNode>>,
     pub count: Option<i32>,
     pub extension: Option<String>,
@@ -20,14 +45,134 @@ pub struct VkNode {
     pub vk_owner_id: Option<String>,
     pub vk_doc_id: Option<String>,
     pub vk_access_key: Option<String>,
+    pub vk_album_id: Option<String>,
+    pub vk_post_id: Option<String>, // Board comment (?post=NNN) the topic node is scoped to
+    pub vk_wall_post_id: Option<String>, // Wall post, with vk_owner_id
     pub size_bytes: Option<u64>,
+    pub is_external: Option<bool>, // Link outside VK (file host, unresolved short link)
+    pub meta: Option<TitleMetadata>, // Series, volume, year... parsed from the title
+    pub parts: Option<Vec<VkNode>>, // Ordered volumes of a split archive
+    pub duplicate_of: Option<DuplicateOf>, // Same document already attached elsewhere in the tree
+}
+
+/// What a URL found in topic text points to
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkKind {
+    Topic,
+    Document,
+    WallPost { owner_id: String, post_id: String },
+    Album { owner_id: String, album_id: String },
+    ShortLink { code: String },
+    ExternalHost { host: String },
+}
+
+impl VkNode {
+    /// Unloaded topic node, optionally scoped to one comment
+    pub fn topic(group_id: &str, topic_id: &str, post_id: Option<&str>) -> Self {
+        Self {
+            id: topic_node_id(topic_id, post_id),
+            title: format!("Topic {}", topic_id),
+            node_type: NodeType::Genre,
+            url: Some(topic_url(group_id, topic_id, post_id)),
+            vk_group_id: Some(group_id.to_string()),
+            vk_topic_id: Some(topic_id.to_string()),
+            vk_post_id: post_id.map(|p| p.to_string()),
+            children: Some(Vec::new()),
+            is_loaded: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Document linked in text (https://vk.com/doc-123_456)
+    pub fn text_document(owner_id: &str, doc_id: &str) -> Self {
+        Self {
+            id: format!("doc_{}_{}", owner_id, doc_id),
+            title: format!("Document {}", doc_id),
+            node_type: NodeType::File,
+            url: Some(format!("https://vk.com/doc{}_{}", owner_id, doc_id)),
+            extension: Some("FILE".to_string()),
+            vk_owner_id: Some(owner_id.to_string()),
+            vk_doc_id: Some(doc_id.to_string()),
+            is_loaded: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Unloaded wall post node
+    pub fn wall_post(owner_id: &str, post_id: &str) -> Self {
+        Self {
+            id: format!("wall_{}_{}", owner_id, post_id),
+            title: format!("Post {}", post_id),
+            node_type: NodeType::Genre,
+            url: Some(format!("https://vk.com/wall{}_{}", owner_id, post_id)),
+            vk_owner_id: Some(owner_id.to_string()),
+            vk_wall_post_id: Some(post_id.to_string()),
+            children: Some(Vec::new()),
+            is_loaded: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Photo album, downloaded as a CBZ
+    pub fn album(owner_id: &str, album_id: &str) -> Self {
+        Self {
+            id: format!("album_{}_{}", owner_id, album_id),
+            title: format!("Album {}", album_id),
+            node_type: NodeType::File,
+            url: Some(format!("https://vk.com/album{}_{}", owner_id, album_id)),
+            extension: Some("CBZ".to_string()),
+            vk_owner_id: Some(owner_id.to_string()),
+            vk_album_id: Some(album_id.to_string()),
+            is_loaded: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// vk.cc short link, flagged external until VkApi resolves it
+    pub fn short_link(code: &str) -> Self {
+        Self {
+            id: format!("link_{}", code),
+            title: format!("vk.cc/{}", code),
+            node_type: NodeType::File,
+            url: Some(format!("https://vk.cc/{}", code)),
+            is_external: Some(true),
+            is_loaded: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Link to an external file host
+    pub fn external(url: &str, host: &str) -> Self {
+        Self {
+            id: format!("ext_{}", url),
+            title: host.to_string(),
+            node_type: NodeType::File,
+            url: Some(url.to_string()),
+            is_external: Some(true),
+            is_loaded: Some(true),
+            ..Default::default()
+        }
+    }
+}
+
+/// A board topic as listed by board.getTopics
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardTopic {
+    pub id: String,
+    pub title: String,
+    pub comments: u32,
+    pub created: i64,
+    pub updated: i64,
+    pub is_closed: bool,
+    pub is_fixed: bool,
 }
 
 lazy_static! {
     static ref RE_BBCODE_TITLE: Regex = Regex::new(r"\[topic-\d+(?:_\d+)?\|([^\]]+)\]").unwrap();
     static ref RE_CLEAN_1: Regex = Regex::new(r"\s*[-–—=]+[>→»]\s*.*$").unwrap();
     static ref RE_CLEAN_2: Regex = Regex::new(r"https?://.*$").unwrap();
-    static ref RE_CLEAN_3: Regex = Regex::new(r"[:\-–—]+$").unwrap();
+    static ref RE_CLEAN_3: Regex = Regex::new(r"\s*[:\-–—→]+\s*$").unwrap();
     static ref RE_CLEAN_4: Regex = Regex::new(r"^\s*[-–—'»«•*·]+\s*").unwrap();
     static ref RE_CLEAN_5: Regex = Regex::new(r"\s*[-–—'»«•*·]+\s*$").unwrap();
     // Case-insensitive cleanup for (lien) - mobile compatible
@@ -43,6 +188,132 @@ lazy_static! {
     static ref RE_URL_INVERTED: Regex = Regex::new(r"https?://(?:[a-z0-9]+\.)?vk\.com/topic-(\d+)_(\d+)\|([^\]]+)\]").unwrap();
     // Support documents in text: https://vk.com/doc-123_456
     static ref RE_DOC_URL: Regex = Regex::new(r"https?://(?:[a-z0-9]+\.)?vk\.com/doc(-?\d+)_(\d+)").unwrap();
+
+    // Link classification: any URL, plus vk.cc short links written without scheme
+    static ref RE_ANY_URL: Regex = Regex::new(r#"(?:https?://|\bvk\.cc/)[^\s\[\]|<>"]+"#).unwrap();
+    static ref RE_WALL_URL: Regex = Regex::new(r"vk\.com/[^\s\]]*?wall(-?\d+)_(\d+)").unwrap();
+    static ref RE_ALBUM_URL: Regex = Regex::new(r"vk\.com/[^\s\]]*?album(-?\d+)_(\d+)").unwrap();
+    static ref RE_SHORT_URL: Regex = Regex::new(r"^(?:https?://)?vk\.cc/([A-Za-z0-9_-]+)").unwrap();
+    static ref RE_URL_HOST: Regex = Regex::new(r"^https?://(?:www\.)?([a-z0-9.-]+)").unwrap();
+}
+
+// File hosts commonly used as mirrors for scans
+const EXTERNAL_FILE_HOSTS: &[&str] = &[
+    "mega.nz",
+    "mega.co.nz",
+    "drive.google.com",
+    "mediafire.com",
+    "1fichier.com",
+    "uptobox.com",
+    "dropbox.com",
+    "yadi.sk",
+    "disk.yandex.ru",
+    "disk.yandex.com",
+    "cloud.mail.ru",
+    "workupload.com",
+    "pixeldrain.com",
+    "gofile.io",
+    "sendspace.com",
+    "dl.free.fr",
+    "rapidgator.net",
+    "turbobit.net",
+    "nitroflare.com",
+];
+
+pub fn topic_node_id(topic_id: &str, post_id: Option<&str>) -> String {
+    match post_id {
+        Some(post) => format!("topic_{}_post_{}", topic_id, post),
+        None => format!("topic_{}", topic_id),
+    }
+}
+
+pub fn topic_url(group_id: &str, topic_id: &str, post_id: Option<&str>) -> String {
+    match post_id {
+        Some(post) => format!(
+            "https://vk.com/topic-{}_{}?post={}",
+            group_id, topic_id, post
+        ),
+        None => format!("https://vk.com/topic-{}_{}", group_id, topic_id),
+    }
+}
+
+/// Classify a URL found in topic text. Returns None for links we don't care about.
+pub fn classify_link(url: &str) -> Option<LinkKind> {
+    if let Some(caps) = RE_SHORT_URL.captures(url) {
+        return Some(LinkKind::ShortLink {
+            code: caps[1].to_string(),
+        });
+    }
+
+    let host = RE_URL_HOST.captures(url)?.get(1)?.as_str().to_lowercase();
+
+    if host == "vk.com" || host.ends_with(".vk.com") {
+        if RE_URL.is_match(url) || RE_BOARD_URL.is_match(url) {
+            return Some(LinkKind::Topic);
+        }
+        if RE_DOC_URL.is_match(url) {
+            return Some(LinkKind::Document);
+        }
+        if let Some(caps) = RE_WALL_URL.captures(url) {
+            return Some(LinkKind::WallPost {
+                owner_id: caps[1].to_string(),
+                post_id: caps[2].to_string(),
+            });
+        }
+        if let Some(caps) = RE_ALBUM_URL.captures(url) {
+            // album-123_0 / _00 are system albums (profile, wall photos)
+            if caps[2].parse::<u64>().unwrap_or(0) > 0 {
+                return Some(LinkKind::Album {
+                    owner_id: caps[1].to_string(),
+                    album_id: caps[2].to_string(),
+                });
+            }
+        }
+        return None;
+    }
+
+    let is_file_host = EXTERNAL_FILE_HOSTS
+        .iter()
+        .any(|h| host == *h || host.ends_with(&format!(".{}", h)));
+    if is_file_host {
+        return Some(LinkKind::ExternalHost { host });
+    }
+
+    None
+}
+
+/// Title for a link found on a line (mobile logic):
+/// 1. Text before the URL on the same line
+/// 2. Previous line (if not a URL)
+/// 3. Text after the URL on the same line (fallback)
+fn link_title(lines: &[&str], i: usize, url_start: usize, url_end: usize) -> String {
+    let line = lines[i];
+    let mut title = String::new();
+
+    let before_text = line[..url_start].trim();
+    if before_text.len() > 1 {
+        title = clean_title(before_text);
+    }
+
+    if title.len() < 2 && i > 0 {
+        let prev_line = lines[i - 1].trim();
+        if !prev_line.contains("vk.com") && prev_line.len() > 2 {
+            title = clean_title(prev_line);
+        }
+    }
+
+    if title.len() < 2 && url_end < line.len() {
+        // "https://... -> Titre" : the arrow points at the title, don't let clean_title eat it
+        let after_text = line[url_end..]
+            .trim_start()
+            .trim_start_matches(['-', '–', '—', '=', '>', '→', '»', ':'])
+            .trim();
+        if after_text.len() > 2 && !after_text.contains("vk.com") {
+            title = clean_title(after_text);
+        }
+    }
+
+    title
 }
 
 pub fn clean_title(text: &str) -> String {
@@ -64,331 +335,249 @@ pub fn clean_title(text: &str) -> String {
     cleaned.trim().to_string()
 }
 
-pub fn parse_topic_body(text: &str, exclude_topic_id: Option<&str>) -> Vec<VkNode> {
-    let mut nodes: Vec<VkNode> = Vec::new();
-    let mut seen_ids = std::collections::HashSet::new();
+/// Where a candidate title comes from, lowest priority first.
+/// Explicit link text beats a title guessed from the surrounding line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum TitleSource {
+    Placeholder,
+    LinkContext,
+    Mention,
+    Inverted,
+    BBCode,
+    Rule, // Title group of a user link pattern
+}
 
-    // 1. BBCode
-    for caps in RE_BBCODE.captures_iter(text) {
-        let group_id = &caps[1];
-        let topic_id = &caps[2];
-        let link_text = &caps[3];
+// Longer "titles" are usually a whole paragraph around a link
+const MAX_TITLE_LEN: usize = 200;
+
+/// Dedup/merge routine shared by every extractor of parse_topic_body.
+/// A node keeps the title from its highest-priority source; on a tie the first one
+/// seen in the text wins, so the result doesn't depend on extractor order.
+struct NodeCollector<'a> {
+    exclude_topic_id: Option<&'a str>,
+    rules: &'a CompiledRules,
+    nodes: Vec<VkNode>,
+    sources: Vec<TitleSource>,
+    index: HashMap<String, usize>,
+}
 
-        if let Some(ex) = exclude_topic_id {
-            if topic_id == ex {
-                continue;
-            }
+impl<'a> NodeCollector<'a> {
+    fn new(exclude_topic_id: Option<&'a str>, rules: &'a CompiledRules) -> Self {
+        Self {
+            exclude_topic_id,
+            rules,
+            nodes: Vec::new(),
+            sources: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Add a candidate node; `node.title` is used as the placeholder title
+    fn add(&mut self, mut node: VkNode, title: Option<String>, source: TitleSource) {
+        if self.exclude_topic_id.is_some() && node.vk_topic_id.as_deref() == self.exclude_topic_id {
+            return;
         }
 
-        let unique_id = format!("topic_{}", topic_id);
+        let (title, source) = match title.map(|t| self.rules.clean_title(&t)) {
+            Some(t) if t.len() >= 2 => (t, source),
+            _ => (node.title.clone(), TitleSource::Placeholder),
+        };
+
+        // Skip folders whose "title" is a paragraph, but never lose a file
+        let (title, source) = if title.len() >= MAX_TITLE_LEN {
+            if node.node_type != NodeType::File {
+                return;
+            }
+            (node.title.clone(), TitleSource::Placeholder)
+        } else {
+            (title, source)
+        };
 
-        let mut title = clean_title(link_text);
-        if title.len() < 2 {
-            title = format!("Topic {}", topic_id);
+        if self.rules.is_ignored(node.vk_topic_id.as_deref(), &title) {
+            return;
         }
 
-        if title.len() < 200 {
-            if let Some(existing) = nodes.iter_mut().find(|n| n.id == unique_id) {
-                if !existing.title.contains(&title) {
-                    existing.title = format!("{} - {}", existing.title, title);
+        match self.index.get(&node.id) {
+            Some(&idx) => {
+                if source > self.sources[idx] {
+                    self.nodes[idx].title = title;
+                    self.sources[idx] = source;
                 }
-            } else {
-                seen_ids.insert(unique_id.clone());
-                nodes.push(VkNode {
-                    id: unique_id,
-                    title,
-                    node_type: "genre".to_string(),
-                    url: Some(format!("https://vk.com/topic-{}_{}", group_id, topic_id)),
-                    vk_group_id: Some(group_id.to_string()),
-                    vk_topic_id: Some(topic_id.to_string()),
-                    children: Some(Vec::new()),
-                    is_loaded: Some(false),
-                    count: None,
-                    extension: None,
-                    structure_only: None,
-                    vk_owner_id: None,
-                    vk_doc_id: None,
-                    vk_access_key: None,
-                    size_bytes: None,
-                });
+            }
+            None => {
+                node.title = title;
+                self.index.insert(node.id.clone(), self.nodes.len());
+                self.nodes.push(node);
+                self.sources.push(source);
             }
         }
     }
 
-    // 2. Mentions @topic-XXX_YYY
-    for caps in RE_MENTION.captures_iter(text) {
-        let group_id = &caps[1];
-        let topic_id = &caps[2];
-        let _post_id = caps.get(3).map(|m| m.as_str());
-        let link_text = caps.get(4).map(|m| m.as_str());
-
-        if let Some(ex) = exclude_topic_id {
-            if topic_id == ex {
-                continue;
+    fn into_nodes(self) -> Vec<VkNode> {
+        let mut nodes = self.nodes;
+        // Placeholder titles ("Topic 123") carry no metadata
+        for (node, source) in nodes.iter_mut().zip(self.sources) {
+            if source != TitleSource::Placeholder {
+                node.meta = Some(parse_title(&node.title));
             }
+            self.rules.override_type(node);
         }
+        nodes
+    }
+}
 
-        let unique_id = format!("topic_{}", topic_id);
+fn is_download_label(title: &str) -> bool {
+    let lower = title.to_lowercase();
+    lower.contains("telecharger") || lower.contains("télécharger") || lower.contains("download")
+}
 
-        let mut title = match link_text {
-            Some(t) => clean_title(t),
-            None => format!("Topic {}", topic_id),
-        };
-        if title.len() < 2 {
-            title = format!("Topic {}", topic_id);
-        }
+pub fn parse_topic_body(text: &str, exclude_topic_id: Option<&str>) -> Vec<VkNode> {
+    parse_topic_body_with_rules(text, exclude_topic_id, &active_rules())
+}
 
-        if title.len() < 200 {
-            if let Some(existing) = nodes.iter_mut().find(|n| n.id == unique_id) {
-                if !existing.title.contains(&title) {
-                    existing.title = format!("{} - {}", existing.title, title);
-                }
-            } else {
-                seen_ids.insert(unique_id.clone());
-                nodes.push(VkNode {
-                    id: unique_id,
-                    title,
-                    node_type: "genre".to_string(),
-                    url: Some(format!("https://vk.com/topic-{}_{}", group_id, topic_id)),
-                    vk_group_id: Some(group_id.to_string()),
-                    vk_topic_id: Some(topic_id.to_string()),
-                    children: Some(Vec::new()),
-                    is_loaded: Some(false),
-                    count: None,
-                    extension: None,
-                    structure_only: None,
-                    vk_owner_id: None,
-                    vk_doc_id: None,
-                    vk_access_key: None,
-                    size_bytes: None,
-                });
-            }
-        }
+pub fn parse_topic_body_with_rules(
+    text: &str,
+    exclude_topic_id: Option<&str>,
+    rules: &CompiledRules,
+) -> Vec<VkNode> {
+    let mut collector = NodeCollector::new(exclude_topic_id, rules);
+
+    // 1. BBCode [topic-XXX_YYY|Titre]
+    for caps in RE_BBCODE.captures_iter(text) {
+        collector.add(
+            VkNode::topic(&caps[1], &caps[2], None),
+            Some(clean_title(&caps[3])),
+            TitleSource::BBCode,
+        );
     }
 
-    // 3. Plain URLs (Topics & Docs) - Mobile-compatible parsing
+    // 2. Mentions @topic-XXX_YYY?post=NNN (Titre)
+    for caps in RE_MENTION.captures_iter(text) {
+        let post_id = caps.get(3).map(|m| m.as_str());
+        collector.add(
+            VkNode::topic(&caps[1], &caps[2], post_id),
+            caps.get(4).map(|m| clean_title(m.as_str())),
+            TitleSource::Mention,
+        );
+    }
+
+    // 3. Plain URLs - Mobile-compatible parsing
     let lines: Vec<&str> = text.lines().collect();
 
     for (i, line) in lines.iter().enumerate() {
         // 3a. Inverted format: https://vk.com/topic-XXX|Titre] (malformed BBCode)
         // Mobile finds ~150 extra BD with this format
         for caps in RE_URL_INVERTED.captures_iter(line) {
-            let group_id = &caps[1];
-            let topic_id = &caps[2];
-            let link_text = &caps[3];
-
-            if let Some(ex) = exclude_topic_id {
-                if topic_id == ex {
-                    continue;
-                }
-            }
-
-            let unique_id = format!("topic_{}", topic_id);
-
-            let mut title = clean_title(link_text);
-            if title.len() < 2 {
-                title = format!("Topic {}", topic_id);
-            }
-
-            if title.len() < 200 {
-                if let Some(existing) = nodes.iter_mut().find(|n| n.id == unique_id) {
-                    if !existing.title.contains(&title) {
-                        existing.title = format!("{} - {}", existing.title, title);
-                    }
-                } else {
-                    seen_ids.insert(unique_id.clone());
-                    nodes.push(VkNode {
-                        id: unique_id,
-                        title,
-                        node_type: "genre".to_string(),
-                        url: Some(format!("https://vk.com/topic-{}_{}", group_id, topic_id)),
-                        vk_group_id: Some(group_id.to_string()),
-                        vk_topic_id: Some(topic_id.to_string()),
-                        children: Some(Vec::new()),
-                        is_loaded: Some(false),
-                        count: None,
-                        extension: None,
-                        structure_only: None,
-                        vk_owner_id: None,
-                        vk_doc_id: None,
-                        vk_access_key: None,
-                        size_bytes: None,
-                    });
-                }
-            }
+            collector.add(
+                VkNode::topic(&caps[1], &caps[2], None),
+                Some(clean_title(&caps[3])),
+                TitleSource::Inverted,
+            );
         }
 
-        // 3b. Standard topic URLs with improved title detection (mobile logic)
-        let mut topic_links: Vec<(String, String, usize, usize, Option<String>)> = Vec::new();
-
+        // 3b. Standard topic URLs and board URLs, titled from the surrounding text
         for caps in RE_URL.captures_iter(line) {
             let url_match = caps.get(0).unwrap();
-            topic_links.push((
-                caps[1].to_string(),
-                caps[2].to_string(),
-                url_match.start(),
-                url_match.end(),
-                caps.get(3).map(|m| m.as_str().to_string()),
-            ));
+            collector.add(
+                VkNode::topic(&caps[1], &caps[2], caps.get(3).map(|m| m.as_str())),
+                Some(link_title(&lines, i, url_match.start(), url_match.end())),
+                TitleSource::LinkContext,
+            );
         }
 
         for caps in RE_BOARD_URL.captures_iter(line) {
             let url_match = caps.get(0).unwrap();
-            topic_links.push((
-                caps[1].to_string(),
-                caps[2].to_string(),
-                url_match.start(),
-                url_match.end(),
-                None,
-            ));
-        }
-
-        for (group_id, topic_id, url_start, url_end, _post_id) in topic_links {
-            // Skip if already processed via inverted format
-            if let Some(ex) = exclude_topic_id {
-                if topic_id == ex {
-                    continue;
-                }
-            }
-
-            // Ignore post_id to merge all links pointing to the same topic
-            let unique_id = format!("topic_{}", topic_id);
-
-            /*if seen_ids.contains(&unique_id) {
-                continue;
-            }*/
-
-            // Mobile title extraction priority:
-            // 1. Text before URL on same line
-            // 2. Previous line (if not a URL)
-            // 3. Text after URL on same line (fallback)
-            let mut title = String::new();
+            collector.add(
+                VkNode::topic(&caps[1], &caps[2], None),
+                Some(link_title(&lines, i, url_match.start(), url_match.end())),
+                TitleSource::LinkContext,
+            );
+        }
 
-            // Try text before URL
-            let before_text = &line[..url_start].trim();
-            if !before_text.is_empty() && before_text.len() > 1 {
-                title = clean_title(before_text);
-            }
+        // 3c. Documents in text (attachments are handled by extract_documents)
+        for caps in RE_DOC_URL.captures_iter(line) {
+            let url_match = caps.get(0).unwrap();
+            let mut title = link_title(&lines, i, url_match.start(), url_match.end());
 
-            // Try previous line if no title found
-            if (title.is_empty() || title.len() < 2) && i > 0 {
+            // Heuristic: "telecharger" / "download" is a button label, the real title
+            // is on the previous line
+            if is_download_label(&title) && i > 0 {
                 let prev_line = lines[i - 1].trim();
                 if !prev_line.contains("vk.com") && prev_line.len() > 2 {
                     title = clean_title(prev_line);
                 }
             }
 
-            // Try text after URL as fallback (mobile behavior)
-            if (title.is_empty() || title.len() < 2) && url_end < line.len() {
-                let after_text = &line[url_end..].trim();
-                if !after_text.is_empty() && after_text.len() > 2 && !after_text.contains("vk.com")
-                {
-                    title = clean_title(after_text);
-                }
-            }
-
-            if title.is_empty() || title.len() < 2 {
-                title = format!("Topic {}", topic_id);
-            }
-
-            if title.len() < 200 {
-                if let Some(existing) = nodes.iter_mut().find(|n| n.id == unique_id) {
-                    if !existing.title.contains(&title) {
-                        existing.title = format!("{} - {}", existing.title, title);
-                    }
-                } else {
-                    seen_ids.insert(unique_id.clone());
-                    nodes.push(VkNode {
-                        id: unique_id,
-                        title,
-                        node_type: "genre".to_string(),
-                        url: Some(format!("https://vk.com/topic-{}_{}", group_id, topic_id)),
-                        vk_group_id: Some(group_id.to_string()),
-                        vk_topic_id: Some(topic_id.to_string()),
-                        children: Some(Vec::new()),
-                        is_loaded: Some(false),
-                        count: None,
-                        extension: None,
-                        structure_only: None,
-                        vk_owner_id: None,
-                        vk_doc_id: None,
-                        vk_access_key: None,
-                        size_bytes: None,
-                    });
-                }
-            }
+            collector.add(
+                VkNode::text_document(&caps[1], &caps[2]),
+                Some(title),
+                TitleSource::LinkContext,
+            );
         }
 
-        // 3c. Documents in text (keep for compatibility but avoid duplicates with extract_documents)
-        // Only parse if the document is not also in attachments (will be filtered by seen_ids)
-        for caps in RE_DOC_URL.captures_iter(line) {
-            let owner_id = &caps[1];
-            let doc_id = &caps[2];
-
-            let unique_id = format!("doc_{}_{}", owner_id, doc_id);
-            if seen_ids.contains(&unique_id) {
-                continue;
-            }
-
-            // Find URL position for title extraction
-            let url_match = caps.get(0).unwrap();
+        // 3d. Other links: wall posts, albums, vk.cc short links and external file hosts
+        for url_match in RE_ANY_URL.find_iter(line) {
+            let url = url_match
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')']);
             let url_start = url_match.start();
+            let url_end = url_start + url.len();
 
-            // Title detection (mobile logic)
-            let mut title = String::new();
-
-            // Text before document URL
-            let before_text = &line[..url_start].trim();
-            if !before_text.is_empty() && before_text.len() > 1 {
-                title = clean_title(before_text);
-            }
-
-            // Previous line if needed
-            if (title.is_empty() || title.len() < 2) && i > 0 {
-                let prev_line = lines[i - 1].trim();
-                if !prev_line.contains("vk.com") && prev_line.len() > 2 {
-                    title = clean_title(prev_line);
+            let node = match classify_link(url) {
+                Some(LinkKind::WallPost { owner_id, post_id }) => {
+                    VkNode::wall_post(&owner_id, &post_id)
                 }
-            }
-
-            // Heuristic: if title contains "telecharger" or "download", use previous line
-            if title.to_lowercase().contains("telecharger")
-                || title.to_lowercase().contains("download")
-            {
-                if i > 0 {
-                    let prev_line = lines[i - 1].trim();
-                    if !prev_line.contains("vk.com") && prev_line.len() > 2 {
-                        title = clean_title(prev_line);
-                    }
-                }
-            }
+                Some(LinkKind::Album { owner_id, album_id }) => VkNode::album(&owner_id, &album_id),
+                Some(LinkKind::ShortLink { code }) => VkNode::short_link(&code),
+                Some(LinkKind::ExternalHost { host }) => VkNode::external(url, &host),
+                // Topics and documents were handled above
+                Some(LinkKind::Topic) | Some(LinkKind::Document) | None => continue,
+            };
+
+            collector.add(
+                node,
+                Some(link_title(&lines, i, url_start, url_end)),
+                TitleSource::LinkContext,
+            );
+        }
 
-            if title.is_empty() || title.len() < 2 {
-                title = format!("Document {}", doc_id);
+        // 3e. User link patterns (parsing_rules.json)
+        for re in &rules.link_patterns {
+            for caps in re.captures_iter(line) {
+                let (Some(group), Some(topic)) = (caps.name("group"), caps.name("topic")) else {
+                    continue;
+                };
+                let url_match = caps.get(0).unwrap();
+                let (title, source) = match caps.name("title") {
+                    Some(t) => (clean_title(t.as_str()), TitleSource::Rule),
+                    None => (
+                        link_title(&lines, i, url_match.start(), url_match.end()),
+                        TitleSource::LinkContext,
+                    ),
+                };
+                collector.add(
+                    VkNode::topic(
+                        group.as_str(),
+                        topic.as_str(),
+                        caps.name("post").map(|m| m.as_str()),
+                    ),
+                    Some(title),
+                    source,
+                );
             }
-
-            seen_ids.insert(unique_id.clone());
-            nodes.push(VkNode {
-                id: unique_id,
-                title,
-                node_type: "file".to_string(),
-                url: Some(format!("https://vk.com/doc{}_{}", owner_id, doc_id)),
-                extension: Some("FILE".to_string()),
-                vk_owner_id: Some(owner_id.to_string()),
-                vk_doc_id: Some(doc_id.to_string()),
-                vk_group_id: None,
-                vk_topic_id: None,
-                children: None,
-                is_loaded: Some(true),
-                count: None,
-                structure_only: None,
-                vk_access_key: None,
-                size_bytes: None,
-            });
         }
     }
 
-    nodes
+    collector.into_nodes()
+}
+
+/// A loaded container is a series as soon as it holds files
+pub fn container_type(children: &[VkNode]) -> NodeType {
+    if children.iter().any(|c| c.node_type == NodeType::File) {
+        NodeType::Series
+    } else {
+        NodeType::Genre
+    }
 }
 
 /// Extract documents (PDF, CBZ, CBR, ZIP...) from VK comment attachments
@@ -426,7 +615,7 @@ pub fn extract_documents(items: &[serde_json::Value]) -> Vec<VkNode> {
                     nodes.push(VkNode {
                         id: format!("doc_{}", doc_id),
                         title: title.to_string(),
-                        node_type: "file".to_string(),
+                        node_type: NodeType::File,
                         url: Some(url.to_string()),
                         extension: ext,
                         size_bytes: size,
@@ -434,16 +623,158 @@ pub fn extract_documents(items: &[serde_json::Value]) -> Vec<VkNode> {
                         vk_doc_id: Some(doc_id.to_string()),
                         vk_access_key: access_key.map(|a| a.to_string()),
                         is_loaded: Some(true),
-                        children: None,
-                        count: None,
-                        structure_only: None,
-                        vk_group_id: None,
-                        vk_topic_id: None,
+                        meta: Some(parse_title(title)),
+                        ..Default::default()
                     });
                 }
             }
         }
     }
 
+    let rules = active_rules();
+    nodes.retain(|n| !rules.is_ignored(None, &n.title));
+
+    group_multipart(nodes)
+}
+
+/// Flatten wall posts so reposts (copy_history) are parsed like regular posts
+pub fn flatten_wall_posts(posts: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    let mut items = Vec::new();
+
+    for post in posts {
+        items.push(post.clone());
+        if let Some(history) = post.get("copy_history").and_then(|h| h.as_array()) {
+            items.extend(history.iter().cloned());
+        }
+    }
+
+    items
+}
+
+/// Extract photo albums (photos.getAlbums items) as downloadable CBZ nodes
+pub fn extract_albums(items: &[serde_json::Value], owner_id: &str) -> Vec<VkNode> {
+    let mut nodes = Vec::new();
+
+    for item in items {
+        let album_id = match item.get("id").and_then(|i| i.as_i64()) {
+            Some(id) => id,
+            None => continue,
+        };
+        let size = item.get("size").and_then(|s| s.as_i64()).unwrap_or(0);
+        if size <= 0 {
+            continue;
+        }
+
+        let mut node = VkNode::album(owner_id, &album_id.to_string());
+        node.count = Some(size as i32);
+        if let Some(title) = item.get("title").and_then(|t| t.as_str()).map(clean_title) {
+            if title.len() >= 2 {
+                node.meta = Some(parse_title(&title));
+                node.title = title;
+            }
+        }
+        nodes.push(node);
+    }
+
     nodes
 }
+
+/// Pick the largest available image URL for each photo (photos.get items)
+pub fn extract_photo_urls(items: &[serde_json::Value]) -> Vec<String> {
+    let mut urls = Vec::new();
+
+    for item in items {
+        // API 5.199 exposes the original upload directly
+        if let Some(url) = item
+            .get("orig_photo")
+            .and_then(|o| o.get("url"))
+            .and_then(|u| u.as_str())
+        {
+            urls.push(url.to_string());
+            continue;
+        }
+
+        let best = item
+            .get("sizes")
+            .and_then(|s| s.as_array())
+            .and_then(|sizes| {
+                sizes.iter().max_by_key(|size| {
+                    let w = size.get("width").and_then(|w| w.as_u64()).unwrap_or(0);
+                    let h = size.get("height").and_then(|h| h.as_u64()).unwrap_or(0);
+                    w * h
+                })
+            })
+            .and_then(|size| size.get("url"))
+            .and_then(|u| u.as_str());
+
+        if let Some(url) = best {
+            urls.push(url.to_string());
+        }
+    }
+
+    urls
+}
+
+/// Extract board topics from board.getTopics items
+pub fn extract_board_topics(items: &[serde_json::Value]) -> Vec<BoardTopic> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let id = item.get("id").and_then(|i| i.as_i64())?;
+            Some(BoardTopic {
+                id: id.to_string(),
+                title: item
+                    .get("title")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                comments: item.get("comments").and_then(|c| c.as_u64()).unwrap_or(0) as u32,
+                created: item.get("created").and_then(|c| c.as_i64()).unwrap_or(0),
+                updated: item.get("updated").and_then(|u| u.as_i64()).unwrap_or(0),
+                is_closed: item.get("is_closed").and_then(|c| c.as_i64()) == Some(1),
+                is_fixed: item.get("is_fixed").and_then(|f| f.as_i64()) == Some(1),
+            })
+        })
+        .collect()
+}
+
+/// Collect every topic id reachable in a tree of nodes
+pub fn collect_topic_ids(nodes: &[VkNode], ids: &mut std::collections::HashSet<String>) {
+    for node in nodes {
+        if let Some(tid) = &node.vk_topic_id {
+            ids.insert(tid.clone());
+        }
+        if let Some(children) = &node.children {
+            collect_topic_ids(children, ids);
+        }
+    }
+}
+
+/// Keep only the linked comment and the ones its author posted right after it
+/// (a series listing is often split over several consecutive comments)
+pub fn scope_comments_to_post(
+    items: &[serde_json::Value],
+    post_id: &str,
+) -> Vec<serde_json::Value> {
+    let start = match items.iter().position(|i| {
+        i.get("id")
+            .and_then(|id| id.as_i64())
+            .map(|id| id.to_string())
+            .as_deref()
+            == Some(post_id)
+    }) {
+        Some(idx) => idx,
+        None => return Vec::new(),
+    };
+
+    let author = items[start].get("from_id").and_then(|f| f.as_i64());
+    let mut scoped = vec![items[start].clone()];
+    for item in &items[start + 1..] {
+        if item.get("from_id").and_then(|f| f.as_i64()) != author {
+            break;
+        }
+        scoped.push(item.clone());
+    }
+
+    scoped
+}
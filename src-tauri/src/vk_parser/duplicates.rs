@@ -0,0 +1,111 @@
+use super::{NodeType, VkNode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// First occurrence of a document that was attached again elsewhere
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateOf {
+    pub node_id: String,
+    pub parent_id: Option<String>,
+    pub parent_title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateScan {
+    pub tree: Vec<VkNode>,
+    pub duplicate_count: usize,
+    pub duplicate_bytes: u64,
+}
+
+/// Identity of a VK document: split archives use their first part
+fn doc_key(node: &VkNode) -> Option<String> {
+    let source = node
+        .parts
+        .as_ref()
+        .and_then(|parts| parts.first())
+        .unwrap_or(node);
+    match (&source.vk_owner_id, &source.vk_doc_id) {
+        (Some(owner), Some(doc)) => Some(format!("doc:{}_{}", owner, doc)),
+        _ => None,
+    }
+}
+
+/// Title compared without extension, case, punctuation or spacing
+pub fn normalize_title(title: &str) -> String {
+    let stem = match title.rsplit_once('.') {
+        Some((stem, ext)) if !ext.is_empty() && ext.len() <= 4 && !ext.contains(' ') => stem,
+        _ => title,
+    };
+    stem.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+fn fuzzy_key(node: &VkNode) -> Option<String> {
+    let size = node.size_bytes.filter(|s| *s > 0)?;
+    let title = normalize_title(&node.title);
+    if title.is_empty() {
+        return None;
+    }
+    Some(format!("fuzzy:{}:{}", title, size))
+}
+
+#[derive(Default)]
+struct Seen {
+    keys: HashMap<String, DuplicateOf>,
+    count: usize,
+    bytes: u64,
+}
+
+fn walk(nodes: &mut [VkNode], parent: Option<(&str, &str)>, seen: &mut Seen) {
+    for node in nodes.iter_mut() {
+        if node.node_type == NodeType::File && node.is_external != Some(true) {
+            let keys: Vec<String> = doc_key(node).into_iter().chain(fuzzy_key(node)).collect();
+            let original = keys.iter().find_map(|k| seen.keys.get(k)).cloned();
+
+            match original {
+                // The same node listed twice under the same parent is not a cross-topic duplicate
+                Some(original)
+                    if original.node_id != node.id
+                        || original.parent_id.as_deref() != parent.map(|p| p.0) =>
+                {
+                    seen.count += 1;
+                    seen.bytes += node.size_bytes.unwrap_or(0);
+                    node.duplicate_of = Some(original);
+                }
+                Some(_) => {}
+                None => {
+                    node.duplicate_of = None;
+                    let entry = DuplicateOf {
+                        node_id: node.id.clone(),
+                        parent_id: parent.map(|p| p.0.to_string()),
+                        parent_title: parent.map(|p| p.1.to_string()),
+                    };
+                    for key in keys {
+                        seen.keys.insert(key, entry.clone());
+                    }
+                }
+            }
+        }
+
+        if let Some(children) = node.children.as_mut() {
+            walk(children, Some((&node.id, &node.title)), seen);
+        }
+    }
+}
+
+/// Mark documents attached in several topics. The first occurrence in tree order is
+/// the original; the others get `duplicate_of`. Matching is on the VK document id,
+/// then on normalised title + size for re-uploads.
+pub fn mark_duplicates(mut tree: Vec<VkNode>) -> DuplicateScan {
+    let mut seen = Seen::default();
+    walk(&mut tree, None, &mut seen);
+    DuplicateScan {
+        tree,
+        duplicate_count: seen.count,
+        duplicate_bytes: seen.bytes,
+    }
+}
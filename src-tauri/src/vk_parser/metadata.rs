@@ -0,0 +1,168 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Special editions recognised in titles
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Edition {
+    Integrale,
+    HorsSerie,
+    Collector,
+    Special,
+}
+
+/// Structured fields parsed from a comic title or file name
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TitleMetadata {
+    pub series: Option<String>,
+    pub volume: Option<u32>,
+    pub volume_end: Option<u32>, // "T01-03", "Tomes 1 à 5"
+    pub issue: Option<u32>,      // "#12"
+    pub volume_title: Option<String>,
+    pub year: Option<u16>,
+    pub language: Option<String>, // FR | EN | ES | IT | DE | RU
+    pub edition: Option<Edition>,
+    pub tags: Vec<String>, // Other bracketed tags, usually the scan group
+}
+
+impl TitleMetadata {
+    /// Number used to order volumes of a series (volume first, then issue)
+    pub fn number(&self) -> Option<u32> {
+        self.volume.or(self.issue)
+    }
+}
+
+lazy_static! {
+    // "X.part1.rar" is a volume of a split archive (see multipart.rs), not tome 1
+    static ref RE_SPLIT_PART: Regex = Regex::new(r"(?i)\.part\d{1,3}\.(?:rar|zip|7z)$").unwrap();
+    static ref RE_EXTENSION: Regex = Regex::new(r"(?i)\.(cbz|cbr|cb7|cbt|pdf|zip|rar|7z|epub|djvu)$").unwrap();
+    static ref RE_BRACKETS: Regex = Regex::new(r"[\[(]([^\[\]()]*)[\])]").unwrap();
+    static ref RE_YEAR: Regex = Regex::new(r"^(?:19\d{2}|20[0-4]\d)$").unwrap();
+    static ref RE_TRAILING_YEAR: Regex = Regex::new(r"\s(?:19\d{2}|20[0-4]\d)$").unwrap();
+    static ref RE_VOLUME: Regex = Regex::new(
+        r"(?i)\b(?:tomes?|t|volumes?|vol|livre|cycle|part(?:ie)?)\.?\s*(\d{1,4})(?:\s*(?:-|à|a|to|&)\s*(?:t\.?\s*)?(\d{1,4}))?\b"
+    ).unwrap();
+    static ref RE_ISSUE: Regex = Regex::new(r"#\s*(\d{1,4})\b").unwrap();
+    static ref RE_TRAILING_NUMBER: Regex = Regex::new(r"\s(\d{1,3})$").unwrap();
+    static ref RE_INTEGRALE: Regex = Regex::new(r"(?i)\b(?:l'\s*)?int[ée]grale\b").unwrap();
+    static ref RE_HORS_SERIE: Regex = Regex::new(r"(?i)(?:\bhors[- ]s[ée]rie\b|\bHS\b)").unwrap();
+    static ref RE_COLLECTOR: Regex = Regex::new(r"(?i)\b(?:[ée]dition\s+)?collector\b").unwrap();
+    static ref RE_SPECIAL: Regex = Regex::new(r"(?i)\b[ée]dition\s+sp[ée]ciale\b").unwrap();
+    static ref RE_SEPARATORS: Regex = Regex::new(r"^[\s\-–—:,.]+|[\s\-–—:,.]+$").unwrap();
+    static ref RE_SPACES: Regex = Regex::new(r"\s{2,}").unwrap();
+}
+
+fn language_tag(tag: &str) -> Option<&'static str> {
+    match tag.trim().to_lowercase().as_str() {
+        "fr" | "vf" | "french" | "français" | "francais" => Some("FR"),
+        "en" | "eng" | "english" | "anglais" => Some("EN"),
+        "es" | "spa" | "spanish" | "espagnol" => Some("ES"),
+        "it" | "ita" | "italian" | "italien" => Some("IT"),
+        "de" | "ger" | "german" | "allemand" => Some("DE"),
+        "ru" | "rus" | "russian" | "russe" => Some("RU"),
+        _ => None,
+    }
+}
+
+fn trim_separators(text: &str) -> String {
+    let collapsed = RE_SPACES.replace_all(text, " ");
+    RE_SEPARATORS.replace_all(&collapsed, "").to_string()
+}
+
+/// Parse French / English naming conventions:
+/// "Astérix - T01 - Astérix le Gaulois (1961) [FR]", "Batman #12", "Thorgal Intégrale T1-3 [Scan-Team]"
+pub fn parse_title(raw: &str) -> TitleMetadata {
+    let mut meta = TitleMetadata::default();
+
+    let raw = RE_SPLIT_PART.replace(raw.trim(), "");
+    let mut text = RE_EXTENSION.replace(&raw, "").to_string();
+    text = text.replace('_', " ");
+    // "Asterix.T01.Le.Gaulois" style file names
+    if !text.contains(' ') && text.matches('.').count() > 1 {
+        text = text.replace('.', " ");
+    }
+
+    // 1. Editions, possibly inside brackets: "(Edition Collector)"
+    for (re, edition) in [
+        (&*RE_INTEGRALE, Edition::Integrale),
+        (&*RE_HORS_SERIE, Edition::HorsSerie),
+        (&*RE_SPECIAL, Edition::Special),
+        (&*RE_COLLECTOR, Edition::Collector),
+    ] {
+        if re.is_match(&text) {
+            meta.edition.get_or_insert(edition);
+            text = re.replace_all(&text, " ").to_string();
+        }
+    }
+
+    // 2. Bracketed tags: year, language, scan group
+    for caps in RE_BRACKETS.captures_iter(&text.clone()) {
+        let tag = caps[1].trim();
+        if tag.is_empty() {
+            // Left over by the edition pass
+        } else if RE_YEAR.is_match(tag) {
+            meta.year = tag.parse().ok();
+        } else if let Some(lang) = language_tag(tag) {
+            meta.language = Some(lang.to_string());
+        } else if RE_VOLUME.is_match(tag) || RE_ISSUE.is_match(tag) {
+            // "(T01)" : keep the content for the volume pass
+            text = text.replacen(&caps[0], &format!(" {} ", tag), 1);
+            continue;
+        } else {
+            meta.tags.push(tag.to_string());
+        }
+        text = text.replacen(&caps[0], " ", 1);
+    }
+
+    // 3. Volume / issue: the series is what comes before, the volume title what comes after
+    let marker = RE_VOLUME
+        .captures(&text)
+        .map(|caps| {
+            meta.volume = caps[1].parse().ok();
+            meta.volume_end = caps.get(2).and_then(|m| m.as_str().parse().ok());
+            let m = caps.get(0).unwrap();
+            (m.start(), m.end())
+        })
+        .or_else(|| {
+            RE_ISSUE.captures(&text).map(|caps| {
+                meta.issue = caps[1].parse().ok();
+                let m = caps.get(0).unwrap();
+                (m.start(), m.end())
+            })
+        });
+
+    let mut series = match marker {
+        Some((start, end)) => {
+            let after = trim_separators(&text[end..]);
+            if !after.is_empty() {
+                meta.volume_title = Some(after);
+            }
+            text[..start].to_string()
+        }
+        None => text.clone(),
+    };
+
+    // 4. Bare year / number at the end: "Blake et Mortimer 05", "Asterix 2019"
+    series = series.trim().to_string();
+    if meta.year.is_none() {
+        if let Some(m) = RE_TRAILING_YEAR.find(&series) {
+            meta.year = m.as_str().trim().parse().ok();
+            series.truncate(m.start());
+        }
+    }
+    if meta.number().is_none() {
+        if let Some(caps) = RE_TRAILING_NUMBER.captures(&series) {
+            meta.volume = caps[1].parse().ok();
+            series.truncate(caps.get(0).unwrap().start());
+        }
+    }
+
+    let series = trim_separators(&series);
+    if !series.is_empty() {
+        meta.series = Some(series);
+    }
+
+    meta
+}
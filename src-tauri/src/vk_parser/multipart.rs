@@ -0,0 +1,184 @@
+use super::metadata::parse_title;
+use super::{NodeType, VkNode};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+lazy_static! {
+    // "X.part1.rar", "X part 02.zip": only real split archives, "Hush Part 1.cbz" is a
+    // complete comic and "Counterpart2.rar" has no separator before "part"
+    static ref RE_PART_N: Regex =
+        Regex::new(r"(?i)^(.*?)[\s._-]+part\s*0*(\d{1,3})\.(rar|zip|7z)$").unwrap();
+    // "X.7z.001", "X.zip.002", "X.001"
+    static ref RE_NUMBERED: Regex =
+        Regex::new(r"(?i)^(.*?)(?:\.(rar|zip|7z|cbr|cbz))?\.(\d{3})$").unwrap();
+    // "X.z01", "X.z02"... then "X.zip" as the last volume
+    static ref RE_ZIP_SPLIT: Regex = Regex::new(r"(?i)^(.*)\.z(\d{2})$").unwrap();
+    // "X.rar" as the first volume, then "X.r00", "X.r01"...
+    static ref RE_RAR_SPLIT: Regex = Regex::new(r"(?i)^(.*)\.r(\d{2})$").unwrap();
+    static ref RE_ARCHIVE: Regex = Regex::new(r"(?i)^(.*)\.(zip|rar)$").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum SplitKind {
+    PartN,
+    Numbered,
+    ZipSplit,
+    RarSplit,
+}
+
+struct PartKey {
+    base: String,
+    kind: SplitKind,
+    index: u32,
+    extension: Option<String>,
+}
+
+impl PartKey {
+    fn group(&self) -> (String, SplitKind) {
+        (self.base.to_lowercase(), self.kind)
+    }
+}
+
+/// File name of a document node (VK titles do not always carry the extension)
+fn file_name(node: &VkNode) -> String {
+    match &node.extension {
+        Some(ext)
+            if !node
+                .title
+                .to_lowercase()
+                .ends_with(&format!(".{}", ext.to_lowercase())) =>
+        {
+            format!("{}.{}", node.title, ext.to_lowercase())
+        }
+        _ => node.title.clone(),
+    }
+}
+
+fn part_key(name: &str) -> Option<PartKey> {
+    let key = |base: &str, kind, index: &str, ext: Option<&str>| {
+        Some(PartKey {
+            base: base.trim().to_string(),
+            kind,
+            index: index.parse().ok()?,
+            extension: ext.map(|e| e.to_uppercase()),
+        })
+    };
+
+    if let Some(caps) = RE_PART_N.captures(name) {
+        return key(&caps[1], SplitKind::PartN, &caps[2], Some(&caps[3]));
+    }
+    if let Some(caps) = RE_NUMBERED.captures(name) {
+        return key(
+            &caps[1],
+            SplitKind::Numbered,
+            &caps[3],
+            caps.get(2).map(|m| m.as_str()),
+        );
+    }
+    if let Some(caps) = RE_ZIP_SPLIT.captures(name) {
+        return key(&caps[1], SplitKind::ZipSplit, &caps[2], Some("zip"));
+    }
+    if let Some(caps) = RE_RAR_SPLIT.captures(name) {
+        // .r00 vient juste après le .rar
+        let index = caps[2].parse::<u32>().ok()? + 1;
+        return key(
+            &caps[1],
+            SplitKind::RarSplit,
+            &index.to_string(),
+            Some("rar"),
+        );
+    }
+    None
+}
+
+/// Group split archives (part1/part2, .001/.002, .z01/.zip, .rar/.r00) into a single
+/// file node whose `parts` are the ordered volumes. Other nodes are left untouched.
+pub fn group_multipart(nodes: Vec<VkNode>) -> Vec<VkNode> {
+    let mut keys: Vec<Option<PartKey>> = nodes.iter().map(|n| part_key(&file_name(n))).collect();
+
+    // The plain .zip / .rar of an old-style split set is one of its volumes
+    let split_groups: HashSet<(String, SplitKind)> = keys
+        .iter()
+        .flatten()
+        .filter(|k| matches!(k.kind, SplitKind::ZipSplit | SplitKind::RarSplit))
+        .map(|k| k.group())
+        .collect();
+    for (node, key) in nodes.iter().zip(keys.iter_mut()) {
+        if key.is_some() {
+            continue;
+        }
+        let name = file_name(node);
+        let Some(caps) = RE_ARCHIVE.captures(&name) else {
+            continue;
+        };
+        let base = caps[1].trim().to_string();
+        let (kind, index) = if caps[2].eq_ignore_ascii_case("zip") {
+            (SplitKind::ZipSplit, u32::MAX)
+        } else {
+            (SplitKind::RarSplit, 0)
+        };
+        if split_groups.contains(&(base.to_lowercase(), kind)) {
+            *key = Some(PartKey {
+                base,
+                kind,
+                index,
+                extension: Some(caps[2].to_uppercase()),
+            });
+        }
+    }
+
+    let mut groups: HashMap<(String, SplitKind), Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(key) = key {
+            groups.entry(key.group()).or_default().push(i);
+        }
+    }
+
+    let mut emitted = HashSet::new();
+    let mut result = Vec::with_capacity(nodes.len());
+    for (i, node) in nodes.iter().enumerate() {
+        let Some(key) = &keys[i] else {
+            result.push(node.clone());
+            continue;
+        };
+        let group = key.group();
+        let members = &groups[&group];
+        if members.len() < 2 {
+            result.push(node.clone());
+            continue;
+        }
+        if !emitted.insert(group) {
+            continue;
+        }
+
+        let mut ordered: Vec<usize> = members.clone();
+        ordered.sort_by_key(|&m| keys[m].as_ref().map(|k| k.index));
+        // Une même partie postée deux fois
+        ordered.dedup_by_key(|m| keys[*m].as_ref().map(|k| k.index));
+        let parts: Vec<VkNode> = ordered.iter().map(|&m| nodes[m].clone()).collect();
+
+        let first = &parts[0];
+        let size_bytes = parts.iter().map(|p| p.size_bytes).sum::<Option<u64>>();
+        let extension = ordered
+            .iter()
+            .find_map(|&m| keys[m].as_ref().and_then(|k| k.extension.clone()))
+            .or_else(|| first.extension.clone());
+
+        result.push(VkNode {
+            id: format!("multi_{}", first.id),
+            title: key.base.clone(),
+            node_type: NodeType::File,
+            url: first.url.clone(),
+            extension,
+            size_bytes,
+            vk_owner_id: first.vk_owner_id.clone(),
+            is_loaded: Some(true),
+            meta: Some(parse_title(&key.base)),
+            parts: Some(parts),
+            ..Default::default()
+        });
+    }
+
+    result
+}
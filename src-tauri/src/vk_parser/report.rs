@@ -0,0 +1,142 @@
+use super::metadata::{parse_title, TitleMetadata};
+use super::{NodeType, VkNode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One volume number of a series, as posted on VK and as found on disk
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeEntry {
+    pub volume: u32,
+    pub node_ids: Vec<String>,
+    pub formats: Vec<String>,
+    pub local_files: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesReport {
+    pub series_id: String,
+    pub title: String,
+    pub first_volume: Option<u32>,
+    pub last_volume: Option<u32>,
+    pub volumes: Vec<VolumeEntry>,
+    pub missing: Vec<u32>, // Gaps in the numbering, neither on VK nor on disk
+    pub duplicates: Vec<u32>, // Same volume posted twice in the same format
+    pub multiple_formats: Vec<u32>, // Same volume available as CBZ and PDF...
+    pub unnumbered: Vec<String>, // Documents without a volume number
+    pub missing_locally: Vec<u32>, // On VK but not in the download folder
+    pub local_only: Vec<u32>, // In the download folder but not on VK
+}
+
+/// Volumes covered by a title ("Intégrale T1-3" covers 1, 2 and 3)
+fn covered_volumes(meta: &TitleMetadata) -> Vec<u32> {
+    match (meta.number(), meta.volume_end) {
+        (Some(start), Some(end)) if end > start && end - start < 100 => (start..=end).collect(),
+        (Some(n), _) => vec![n],
+        _ => Vec::new(),
+    }
+}
+
+fn file_extension(name: &str) -> Option<String> {
+    name.rsplit_once('.')
+        .map(|(_, ext)| ext.to_uppercase())
+        .filter(|ext| !ext.is_empty() && ext.len() <= 5)
+}
+
+/// Group the files of a loaded series by volume number and compare with the local folder.
+/// `local_files` are file names found in the series download folder.
+pub fn build_series_report(series: &VkNode, local_files: &[String]) -> SeriesReport {
+    let mut volumes: BTreeMap<u32, VolumeEntry> = BTreeMap::new();
+    let mut duplicates = BTreeSet::new();
+    let mut unnumbered = Vec::new();
+
+    let files = series
+        .children
+        .iter()
+        .flatten()
+        .filter(|c| c.node_type == NodeType::File && c.is_external != Some(true));
+
+    for file in files {
+        let meta = file
+            .meta
+            .clone()
+            .unwrap_or_else(|| parse_title(&file.title));
+        let numbers = covered_volumes(&meta);
+        if numbers.is_empty() {
+            unnumbered.push(file.title.clone());
+            continue;
+        }
+        let format = file
+            .extension
+            .clone()
+            .or_else(|| file_extension(&file.title))
+            .unwrap_or_default()
+            .to_uppercase();
+
+        for n in numbers {
+            let entry = volumes.entry(n).or_insert_with(|| VolumeEntry {
+                volume: n,
+                ..Default::default()
+            });
+            if entry.node_ids.contains(&file.id) {
+                continue;
+            }
+            if entry.formats.contains(&format) {
+                duplicates.insert(n);
+            } else {
+                entry.formats.push(format.clone());
+            }
+            entry.node_ids.push(file.id.clone());
+        }
+    }
+
+    let remote: BTreeSet<u32> = volumes.keys().copied().collect();
+    let mut local_only = BTreeSet::new();
+    for name in local_files {
+        // Fichiers partiels du gestionnaire de téléchargement
+        if name.ends_with(".part") {
+            continue;
+        }
+        for n in covered_volumes(&parse_title(name)) {
+            match volumes.get_mut(&n) {
+                Some(entry) => entry.local_files.push(name.clone()),
+                None => {
+                    local_only.insert(n);
+                }
+            }
+        }
+    }
+
+    let first_volume = remote.first().copied();
+    let last_volume = remote.last().copied();
+    let missing = match (first_volume, last_volume) {
+        // On ne suppose pas où la série commence : tome 0, 1 ou une reprise au tome 20
+        (Some(first), Some(last)) => (first..=last)
+            .filter(|n| !remote.contains(n) && !local_only.contains(n))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    SeriesReport {
+        series_id: series.id.clone(),
+        title: series.title.clone(),
+        first_volume,
+        last_volume,
+        missing,
+        duplicates: duplicates.into_iter().collect(),
+        multiple_formats: volumes
+            .values()
+            .filter(|v| v.formats.len() > 1)
+            .map(|v| v.volume)
+            .collect(),
+        unnumbered,
+        missing_locally: volumes
+            .values()
+            .filter(|v| v.local_files.is_empty())
+            .map(|v| v.volume)
+            .collect(),
+        local_only: local_only.into_iter().collect(),
+        volumes: volumes.into_values().collect(),
+    }
+}
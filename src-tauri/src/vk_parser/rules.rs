@@ -0,0 +1,225 @@
+use super::{NodeType, VkNode};
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// User rules file (`parsing_rules.json` in the app data dir), for boards whose
+/// index doesn't follow the formats handled by the built-in regexes.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParsingRules {
+    /// Extra topic link formats. Named groups: `group` and `topic`, optional `post` and `title`
+    pub link_patterns: Vec<String>,
+    /// Patterns removed from titles after the built-in cleanup
+    pub title_cleanup: Vec<String>,
+    /// Topic ids never added to the tree
+    pub ignore_topics: Vec<String>,
+    /// Nodes whose title matches one of these patterns are dropped
+    pub ignore_titles: Vec<String>,
+    /// Force the type of folders (files keep theirs)
+    pub type_overrides: Vec<TypeOverride>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TypeOverride {
+    #[serde(default)]
+    pub topic_id: Option<String>,
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+}
+
+/// Validated rules, ready to be used by the parser
+#[derive(Debug, Default)]
+pub struct CompiledRules {
+    pub(super) link_patterns: Vec<Regex>,
+    title_cleanup: Vec<Regex>,
+    ignore_topics: HashSet<String>,
+    ignore_titles: Vec<Regex>,
+    type_overrides: Vec<(Option<String>, Option<Regex>, NodeType)>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesSummary {
+    pub path: String,
+    pub file_found: bool,
+    pub link_patterns: usize,
+    pub title_cleanup: usize,
+    pub ignore_topics: usize,
+    pub ignore_titles: usize,
+    pub type_overrides: usize,
+}
+
+lazy_static! {
+    static ref ACTIVE_RULES: RwLock<Arc<CompiledRules>> =
+        RwLock::new(Arc::new(CompiledRules::default()));
+}
+
+/// Rules used by `parse_topic_body` and `extract_documents`
+pub fn active_rules() -> Arc<CompiledRules> {
+    ACTIVE_RULES
+        .read()
+        .map(|rules| rules.clone())
+        .unwrap_or_default()
+}
+
+pub fn set_active_rules(rules: CompiledRules) {
+    if let Ok(mut active) = ACTIVE_RULES.write() {
+        *active = Arc::new(rules);
+    }
+}
+
+fn compile_regex(
+    field: &str,
+    index: usize,
+    pattern: &str,
+    errors: &mut Vec<String>,
+) -> Option<Regex> {
+    match Regex::new(pattern) {
+        Ok(re) => Some(re),
+        Err(e) => {
+            errors.push(format!(
+                "{}[{}]: invalid pattern `{}`: {}",
+                field, index, pattern, e
+            ));
+            None
+        }
+    }
+}
+
+impl ParsingRules {
+    /// Validate every pattern; all problems are reported at once
+    pub fn compile(&self) -> Result<CompiledRules> {
+        let mut errors = Vec::new();
+        let mut compiled = CompiledRules {
+            ignore_topics: self
+                .ignore_topics
+                .iter()
+                .map(|t| t.trim().to_string())
+                .collect(),
+            ..Default::default()
+        };
+
+        for (i, pattern) in self.link_patterns.iter().enumerate() {
+            let Some(re) = compile_regex("link_patterns", i, pattern, &mut errors) else {
+                continue;
+            };
+            let names: Vec<&str> = re.capture_names().flatten().collect();
+            let missing: Vec<&str> = ["group", "topic"]
+                .into_iter()
+                .filter(|g| !names.contains(g))
+                .collect();
+            if missing.is_empty() {
+                compiled.link_patterns.push(re);
+            } else {
+                errors.push(format!(
+                    "link_patterns[{}]: missing named group(s) {} (e.g. `(?P<topic>\\d+)`)",
+                    i,
+                    missing.join(", ")
+                ));
+            }
+        }
+
+        for (i, pattern) in self.title_cleanup.iter().enumerate() {
+            compiled
+                .title_cleanup
+                .extend(compile_regex("title_cleanup", i, pattern, &mut errors));
+        }
+
+        for (i, pattern) in self.ignore_titles.iter().enumerate() {
+            compiled
+                .ignore_titles
+                .extend(compile_regex("ignore_titles", i, pattern, &mut errors));
+        }
+
+        for (i, rule) in self.type_overrides.iter().enumerate() {
+            match (&rule.topic_id, &rule.title_pattern) {
+                (Some(_), Some(_)) | (None, None) => errors.push(format!(
+                    "type_overrides[{}]: set exactly one of `topic_id` or `title_pattern`",
+                    i
+                )),
+                (Some(topic_id), None) => compiled.type_overrides.push((
+                    Some(topic_id.trim().to_string()),
+                    None,
+                    rule.node_type,
+                )),
+                (None, Some(pattern)) => {
+                    if let Some(re) = compile_regex("type_overrides", i, pattern, &mut errors) {
+                        compiled
+                            .type_overrides
+                            .push((None, Some(re), rule.node_type));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(compiled)
+        } else {
+            Err(anyhow!("Invalid parsing rules:\n- {}", errors.join("\n- ")))
+        }
+    }
+}
+
+impl CompiledRules {
+    pub fn clean_title(&self, title: &str) -> String {
+        let mut cleaned = title.to_string();
+        for re in &self.title_cleanup {
+            cleaned = re.replace_all(&cleaned, "").to_string();
+        }
+        cleaned.trim().to_string()
+    }
+
+    pub fn is_ignored(&self, topic_id: Option<&str>, title: &str) -> bool {
+        topic_id.is_some_and(|t| self.ignore_topics.contains(t))
+            || self.ignore_titles.iter().any(|re| re.is_match(title))
+    }
+
+    /// Apply the first matching type override (folders only)
+    pub fn override_type(&self, node: &mut VkNode) {
+        if node.node_type == NodeType::File {
+            return;
+        }
+        let matched =
+            self.type_overrides
+                .iter()
+                .find(|(topic_id, pattern, _)| match (topic_id, pattern) {
+                    (Some(topic_id), _) => node.vk_topic_id.as_deref() == Some(topic_id.as_str()),
+                    (None, Some(re)) => re.is_match(&node.title),
+                    (None, None) => false,
+                });
+        if let Some((_, _, node_type)) = matched {
+            node.node_type = *node_type;
+        }
+    }
+
+    pub fn summary(&self, path: &Path) -> RulesSummary {
+        RulesSummary {
+            path: path.to_string_lossy().to_string(),
+            file_found: path.exists(),
+            link_patterns: self.link_patterns.len(),
+            title_cleanup: self.title_cleanup.len(),
+            ignore_topics: self.ignore_topics.len(),
+            ignore_titles: self.ignore_titles.len(),
+            type_overrides: self.type_overrides.len(),
+        }
+    }
+}
+
+/// Load and validate a rules file. A missing file means no extra rules.
+pub fn load_rules_file(path: &Path) -> Result<CompiledRules> {
+    if !path.exists() {
+        return Ok(CompiledRules::default());
+    }
+    let content = std::fs::read_to_string(path)?;
+    let rules: ParsingRules =
+        serde_json::from_str(&content).map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    rules.compile()
+}
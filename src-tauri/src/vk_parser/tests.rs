@@ -0,0 +1,379 @@
+//! Golden corpus and property tests for the topic parser.
+//! The corpus is made of anonymised topic bodies and comment attachments; expected
+//! outputs are insta snapshots in `snapshots/` (review changes with `cargo insta review`).
+
+use super::duplicates::mark_duplicates;
+use super::metadata::parse_title;
+use super::multipart::group_multipart;
+use super::report::build_series_report;
+use super::rules::ParsingRules;
+use super::*;
+use proptest::prelude::*;
+use std::collections::HashSet;
+
+// (name, body, topic being parsed)
+const TOPIC_CORPUS: &[(&str, &str, Option<&str>)] = &[
+    ("index", include_str!("corpus/index.txt"), Some("20000001")),
+    ("mobile", include_str!("corpus/mobile.txt"), None),
+    (
+        "series_links",
+        include_str!("corpus/series_links.txt"),
+        None,
+    ),
+    ("noise", include_str!("corpus/noise.txt"), Some("20000099")),
+];
+
+#[test]
+fn parse_topic_body_corpus() {
+    for (name, body, exclude) in TOPIC_CORPUS {
+        insta::assert_json_snapshot!(
+            format!("parse_topic_body_{}", name),
+            parse_topic_body(body, *exclude)
+        );
+    }
+}
+
+#[test]
+fn extract_documents_corpus() {
+    let items: Vec<serde_json::Value> =
+        serde_json::from_str(include_str!("corpus/comments.json")).unwrap();
+    insta::assert_json_snapshot!(extract_documents(&items));
+}
+
+#[test]
+fn clean_title_cases() {
+    let cases = [
+        "Astérix",
+        "  • Valérian -> https://vk.com/topic-1_2",
+        "Aldébaran → suite",
+        "Le Transperceneige :",
+        "[topic-100000001_20000002|Astérix]",
+        "« Les Passagers du vent »",
+        "(lien) Yoko Tsuno",
+        "Miroir : https://mega.nz/file/abc",
+        "-- Thorgal --",
+        "Gaston ==> ici",
+        "",
+    ];
+    let output = cases
+        .iter()
+        .map(|c| format!("{:?} => {:?}", c, clean_title(c)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    insta::assert_snapshot!(output);
+}
+
+#[test]
+fn parse_title_cases() {
+    let cases = [
+        "Astérix - T01 - Astérix le Gaulois (1961) [FR].cbz",
+        "Batman #12 (2019)",
+        "Thorgal Intégrale T1-3 [Scan-Team]",
+        "Blake et Mortimer 05.cbr",
+        "Asterix.T01.Le.Gaulois.cbz",
+        "The Walking Dead Vol. 2 [EN].pdf",
+        "XIII - Tomes 1 à 5 (Edition Collector)",
+        "Spider-Man 2099",
+        "One Piece (T12) [VF]",
+    ];
+    let output: Vec<_> = cases.iter().map(|c| (c, parse_title(c))).collect();
+    insta::assert_json_snapshot!(output);
+}
+
+#[test]
+fn split_archive_parts_are_not_volumes() {
+    let part = parse_title("Hush.part1.rar");
+    assert_eq!(part.volume, None);
+    assert_eq!(part.series.as_deref(), Some("Hush"));
+
+    let tome = parse_title("Hush Partie 2.cbz");
+    assert_eq!(tome.volume, Some(2));
+    assert_eq!(tome.series.as_deref(), Some("Hush"));
+}
+
+/// The mobile heuristics are what find the "~150 extra BD": guard them explicitly
+#[test]
+fn mobile_heuristics_keep_their_titles() {
+    let nodes = parse_topic_body(include_str!("corpus/mobile.txt"), None);
+    let title = |id: &str| nodes.iter().find(|n| n.id == id).map(|n| n.title.as_str());
+
+    assert_eq!(title("topic_20000030"), Some("Blacksad")); // Previous line
+    assert_eq!(title("topic_20000040"), Some("Spirou")); // Inverted BBCode
+    assert_eq!(title("topic_20000041"), Some("Largo Winch")); // Inverted beats context
+    assert_eq!(title("topic_20000050"), Some("Les Schtroumpfs")); // Text after the link
+    assert_eq!(title("topic_20000060"), Some("Lien vers un forum")); // Board URL
+}
+
+#[test]
+fn telecharger_label_uses_previous_line() {
+    let nodes = parse_topic_body(include_str!("corpus/series_links.txt"), None);
+    let title = |id: &str| nodes.iter().find(|n| n.id == id).map(|n| n.title.as_str());
+
+    assert_eq!(
+        title("doc_-100000001_600000001"),
+        Some("XIII T01 - Le Jour du soleil noir")
+    );
+    assert_eq!(
+        title("doc_-100000001_600000002"),
+        Some("XIII T02 - Là où va l'indien")
+    );
+}
+
+#[test]
+fn parsing_rules_report_every_error() {
+    let rules: ParsingRules = serde_json::from_str(
+        r#"{
+            "link_patterns": ["forum/(?P<topic>\\d+)", "("],
+            "type_overrides": [{"type": "series"}]
+        }"#,
+    )
+    .unwrap();
+    let error = rules.compile().unwrap_err().to_string();
+
+    assert!(error.contains("link_patterns[0]: missing named group(s) group"));
+    assert!(error.contains("link_patterns[1]: invalid pattern"));
+    assert!(error.contains("type_overrides[0]: set exactly one of"));
+}
+
+#[test]
+fn parsing_rules_apply_to_topic_body() {
+    let rules: ParsingRules = serde_json::from_str(
+        r#"{
+            "link_patterns": ["forum\\.example/g(?P<group>\\d+)/t(?P<topic>\\d+) \\((?P<title>[^)]+)\\)"],
+            "title_cleanup": ["\\s*\\(COMPLET\\)"],
+            "ignore_topics": ["30"],
+            "ignore_titles": ["(?i)^règles"],
+            "type_overrides": [{"topic_id": "31", "type": "series"}]
+        }"#,
+    )
+    .unwrap();
+    let rules = rules.compile().unwrap();
+
+    let text = "Voir forum.example/g1/t40 (Bone)\n\
+                [topic-1_30|Ignoré]\n\
+                [topic-1_31|Akira (COMPLET)]\n\
+                [topic-1_32|Règles du groupe]";
+    let nodes = parse_topic_body_with_rules(text, None, &rules);
+    let summary: Vec<_> = nodes
+        .iter()
+        .map(|n| (n.id.as_str(), n.title.as_str(), n.node_type))
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            ("topic_31", "Akira", NodeType::Series),
+            ("topic_40", "Bone", NodeType::Genre),
+        ]
+    );
+}
+
+#[test]
+fn duplicates_are_marked_across_topics() {
+    let doc = |id: &str, owner: &str, title: &str, size: u64| VkNode {
+        id: format!("doc_{}", id),
+        title: title.to_string(),
+        node_type: NodeType::File,
+        vk_owner_id: Some(owner.to_string()),
+        vk_doc_id: Some(id.to_string()),
+        size_bytes: Some(size),
+        ..Default::default()
+    };
+    let series = |id: &str, children: Vec<VkNode>| VkNode {
+        id: id.to_string(),
+        title: id.to_uppercase(),
+        node_type: NodeType::Series,
+        children: Some(children),
+        ..Default::default()
+    };
+
+    let tree = vec![
+        series("topic_1", vec![doc("10", "-1", "XIII T01.cbz", 500)]),
+        series(
+            "topic_2",
+            vec![
+                doc("10", "-1", "XIII T01.cbz", 500),  // Same VK document
+                doc("11", "77", "xiii_t01 .CBZ", 500), // Re-upload, same title and size
+                doc("12", "77", "XIII T01.cbz", 501),  // Different size
+            ],
+        ),
+    ];
+    let scan = mark_duplicates(tree);
+    let marks: Vec<_> = scan.tree[1]
+        .children
+        .iter()
+        .flatten()
+        .map(|n| n.duplicate_of.as_ref().map(|d| d.parent_id.as_deref()))
+        .collect();
+
+    assert_eq!(
+        marks,
+        vec![Some(Some("topic_1")), Some(Some("topic_1")), None]
+    );
+    assert_eq!(scan.duplicate_count, 2);
+    assert_eq!(scan.duplicate_bytes, 1000);
+}
+
+#[test]
+fn complete_comics_named_part_are_not_grouped() {
+    let nodes: Vec<VkNode> = [
+        "Hush Part 1.cbz",
+        "Hush Part 2.cbz",
+        "Counterpart 2.cbz",
+        "Counterpart2.rar",
+        "Counterpart3.rar",
+    ]
+    .iter()
+    .enumerate()
+    .map(|(i, name)| VkNode {
+        id: format!("doc_{}", i),
+        title: name.to_string(),
+        node_type: NodeType::File,
+        ..Default::default()
+    })
+    .collect();
+
+    let grouped = group_multipart(nodes);
+    assert_eq!(grouped.len(), 5);
+    assert!(grouped.iter().all(|n| n.parts.is_none()));
+
+    let split = group_multipart(
+        ["Hush.part1.rar", "Hush.part2.rar"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| VkNode {
+                id: format!("doc_{}", i),
+                title: name.to_string(),
+                node_type: NodeType::File,
+                ..Default::default()
+            })
+            .collect(),
+    );
+    assert_eq!(split.len(), 1);
+    assert_eq!(split[0].title, "Hush");
+}
+
+#[test]
+fn series_report_gaps_start_at_the_first_volume() {
+    let series = |titles: &[&str]| VkNode {
+        id: "series".to_string(),
+        node_type: NodeType::Series,
+        children: Some(
+            titles
+                .iter()
+                .enumerate()
+                .map(|(i, title)| VkNode {
+                    id: format!("doc_{}", i),
+                    title: title.to_string(),
+                    node_type: NodeType::File,
+                    ..Default::default()
+                })
+                .collect(),
+        ),
+        ..Default::default()
+    };
+
+    let from_zero = build_series_report(&series(&["XIII T00.cbz", "XIII T02.cbz"]), &[]);
+    assert_eq!(from_zero.first_volume, Some(0));
+    assert_eq!(from_zero.missing, vec![1]);
+
+    let later = build_series_report(
+        &series(&["XIII T20.cbz", "XIII T21.cbz", "XIII T23.cbz"]),
+        &["XIII T22.cbz".to_string()],
+    );
+    assert_eq!(later.first_volume, Some(20));
+    assert_eq!(later.missing, Vec::<u32>::new());
+    assert_eq!(later.local_only, vec![22]);
+}
+
+// Links glued to arbitrary text, to catch byte slicing on non char boundaries
+const LINKS: &[&str] = &[
+    "https://vk.com/topic-1_2",
+    "https://m.vk.com/topic-1_2?post=3",
+    "https://vk.com/topic-1_2|Titre]",
+    "[topic-1_2|Titre]",
+    "@topic-1_2?post=3 (Titre)",
+    "https://vk.com/board1?act=topic&topic_id=2",
+    "https://vk.com/doc-1_2",
+    "https://vk.com/wall-1_2",
+    "https://vk.com/album-1_2",
+    "https://vk.cc/abc",
+    "vk.cc/abc",
+    "https://mega.nz/file/abc",
+];
+
+proptest! {
+    #[test]
+    fn parse_topic_body_never_panics(text in any::<String>()) {
+        let _ = parse_topic_body(&text, None);
+    }
+
+    #[test]
+    fn parse_topic_body_handles_links_in_any_text(
+        prev in any::<String>(),
+        prefix in any::<String>(),
+        link in prop::sample::select(LINKS),
+        suffix in any::<String>(),
+    ) {
+        let text = format!("{}\n{}{}{}\n{}", prev, prefix, link, suffix, link);
+        let nodes = parse_topic_body(&text, None);
+
+        let ids: HashSet<_> = nodes.iter().map(|n| n.id.as_str()).collect();
+        prop_assert_eq!(ids.len(), nodes.len());
+        // The link alone on the last line is always found
+        prop_assert!(!nodes.is_empty());
+    }
+
+    #[test]
+    fn parse_topic_body_skips_excluded_topic(
+        prefix in any::<String>(),
+        link in prop::sample::select(LINKS),
+    ) {
+        let text = format!("{}{}", prefix, link);
+        let nodes = parse_topic_body(&text, Some("2"));
+        prop_assert!(nodes.iter().all(|n| n.vk_topic_id.as_deref() != Some("2")));
+    }
+
+    #[test]
+    fn clean_title_is_trimmed(text in any::<String>()) {
+        let title = clean_title(&text);
+        prop_assert_eq!(title.trim(), title.as_str());
+    }
+
+    #[test]
+    fn parse_title_never_panics(text in any::<String>()) {
+        let _ = parse_title(&text);
+    }
+
+    #[test]
+    fn group_multipart_keeps_every_document(
+        names in prop::collection::hash_set(
+            r"[a-z]{1,6}(\.part[1-3]\.rar|\.z0[1-2]|\.zip|\.rar|\.r0[0-1]|\.7z\.00[1-3]|\.cbz)",
+            0..12,
+        )
+    ) {
+        let nodes: Vec<VkNode> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| VkNode {
+                id: format!("doc_{}", i),
+                title: name.clone(),
+                node_type: NodeType::File,
+                ..Default::default()
+            })
+            .collect();
+
+        let grouped = group_multipart(nodes);
+        let mut ids: Vec<&str> = grouped
+            .iter()
+            .flat_map(|n| match &n.parts {
+                Some(parts) => parts.iter().map(|p| p.id.as_str()).collect(),
+                None => vec![n.id.as_str()],
+            })
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        prop_assert_eq!(ids.len(), names.len());
+        prop_assert!(grouped.iter().all(|n| n.parts.as_ref().is_none_or(|p| p.len() >= 2)));
+    }
+}
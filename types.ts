@@ -11,6 +11,19 @@ export interface NavItem {
 // category/genre/series = Dossiers | file = Fichiers (PDF/CBZ)
 export type NodeType = "category" | "genre" | "series" | "topic" | "file";
 
+// Informations extraites du titre ("Astérix - T01 (1961) [FR]")
+export interface TitleMetadata {
+  series?: string | null;
+  volume?: number | null;
+  volumeEnd?: number | null; // Intégrales : "T1-3"
+  issue?: number | null; // Numéro "#12"
+  volumeTitle?: string | null;
+  year?: number | null;
+  language?: string | null; // FR, EN, ES, IT, DE, RU
+  edition?: "integrale" | "hors-serie" | "collector" | "special" | null;
+  tags?: string[]; // Autres tags entre crochets (team de scan...)
+}
+
 // Structure principale de l'arbre de données VK
 export interface VkNode {
   id: string; // Identifiant unique
@@ -24,10 +37,63 @@ export interface VkNode {
   structureOnly?: boolean; // Indique que ce nœud a été chargé via "Tout Sync" (structure seulement, pas les docs)
   vkGroupId?: string; // ID du groupe VK (nécessaire pour l'API)
   vkTopicId?: string; // ID du topic VK (nécessaire pour l'API)
+  vkPostId?: string; // ID du commentaire ciblé (?post=NNN), limite le noeud à ce commentaire
+  vkWallPostId?: string; // ID du post du mur (avec vkOwnerId)
   vkOwnerId?: string;
   vkDocId?: string;
   vkAccessKey?: string;
+  vkAlbumId?: string; // ID de l'album photo (téléchargé en CBZ)
   sizeBytes?: number; // Taille du fichier (en octets) pour les noeuds de type "file"
+  isExternal?: boolean; // Lien hors VK (hébergeur de fichiers, lien vk.cc non résolu)
+  meta?: TitleMetadata | null; // Série, tome, année... extraits du titre
+  parts?: VkNode[] | null; // Volumes ordonnés d'une archive découpée (part1/part2, .001/.002)
+  duplicateOf?: DuplicateOf | null; // Même document déjà présent ailleurs dans l'arbre
+}
+
+// Première occurrence d'un document ré-attaché dans un autre topic
+export interface DuplicateOf {
+  nodeId: string;
+  parentId?: string | null;
+  parentTitle?: string | null;
+}
+
+export interface DuplicateScan {
+  tree: VkNode[];
+  duplicateCount: number;
+  duplicateBytes: number;
+}
+
+// Topic du forum listé par board.getTopics
+export interface BoardTopic {
+  id: string;
+  title: string;
+  comments: number;
+  created: number; // Timestamp Unix (secondes)
+  updated: number;
+  isClosed: boolean;
+  isFixed: boolean;
+}
+
+// Rapport de complétude d'une série (tomes manquants, doublons, formats)
+export interface VolumeEntry {
+  volume: number;
+  nodeIds: string[];
+  formats: string[];
+  localFiles: string[];
+}
+
+export interface SeriesReport {
+  seriesId: string;
+  title: string;
+  firstVolume?: number | null;
+  lastVolume?: number | null;
+  volumes: VolumeEntry[];
+  missing: number[];
+  duplicates: number[];
+  multipleFormats: number[];
+  unnumbered: string[];
+  missingLocally: number[];
+  localOnly: number[];
 }
 
 export interface VkConnectionStatus {
@@ -46,6 +112,12 @@ export interface AppUpdateInfo {
   error?: string | null;
 }
 
+export interface DownloadPart {
+  url: string;
+  fileName: string;
+  size?: number | null;
+}
+
 export interface DownloadItem {
   id: string;
   title: string;
@@ -59,12 +131,272 @@ export interface DownloadItem {
   | "error";
   createdAt?: string; // ISO date de la dernre tentative
   size?: string;
+  sizeBytes?: number; // Taille exacte, pour repérer un fichier déjà téléchargé ailleurs
   speed?: string;
   url?: string;
   vkOwnerId?: string;
   vkDocId?: string;
   vkAccessKey?: string;
+  vkAlbumId?: string; // ID de l'album photo (téléchargé en CBZ)
+  parts?: DownloadPart[]; // Archive découpée : toutes les parties en un seul téléchargement
   path?: string; // Local save path
   extension?: string;
   subFolder?: string;
+  ancestors?: string[]; // Titres des dossiers VK au-dessus du document (modèle de nommage)
+}
+
+// Fichier déjà présent à l'emplacement du téléchargement
+export type ConflictPolicy = "skip" | "verify" | "resume" | "overwrite" | "rename";
+
+export interface ResolvedPath {
+  directory: string;
+  fileName: string;
+  relativePath: string;
+}
+
+// File d'attente du backend : priorité, déplacement et annulation groupée
+export type DownloadPriority = "low" | "normal" | "high";
+
+export type QueueMove = { to: "top" } | { to: "bottom" } | { to: "position"; index: number };
+
+export type QueueStatus = "queued" | "active";
+
+export interface QueueFilter {
+  series?: string;
+  folder?: string;
+  status?: QueueStatus;
+}
+
+export interface QueueItem {
+  id: string;
+  fileName: string;
+  directory: string;
+  series?: string;
+  priority: DownloadPriority;
+  size?: number;
+  status: QueueStatus;
+}
+
+export interface QueueState {
+  paused: boolean;
+  maxActive: number;
+  active: QueueItem[];
+  queued: QueueItem[]; // Ordre de démarrage
+}
+
+// Un document de fs_queue_downloads (mêmes champs que queueDownload)
+export interface DownloadRequest {
+  id: string;
+  url: string;
+  directory: string;
+  fileName: string;
+  token?: string;
+  docKey?: string;
+  size?: number;
+  comicInfo?: ComicInfoSource;
+  conflictPolicy?: ConflictPolicy;
+  priority?: DownloadPriority;
+}
+
+// Historique des téléchargements (download_history.json)
+export type HistoryResult = "completed" | "skipped" | "failed" | "cancelled" | "interrupted";
+
+export interface HistoryRecord {
+  taskId: string;
+  docId?: string;
+  url: string;
+  path?: string;
+  series?: string;
+  size?: number;
+  received: number; // Octets reçus par cette tentative
+  startedAt: number;
+  finishedAt: number;
+  durationMs: number;
+  averageSpeed: number; // Octets par seconde
+  result: HistoryResult;
+  error?: string;
+}
+
+export interface HistoryQuery {
+  result?: HistoryResult;
+  series?: string;
+  docId?: string;
+  search?: string;
+  since?: number;
+  until?: number;
+  offset?: number;
+  limit?: number;
+}
+
+export interface HistoryPage {
+  total: number;
+  records: HistoryRecord[];
+}
+
+export interface HistoryStatsQuery {
+  since?: number;
+  until?: number;
+  utcOffsetMinutes?: number; // -new Date().getTimezoneOffset()
+  top?: number;
+}
+
+export interface HistoryStats {
+  attempts: number;
+  completed: number;
+  skipped: number;
+  failed: number;
+  cancelled: number;
+  successRate: number;
+  totalBytes: number;
+  bytesPerDay: { day: string; bytes: number; completed: number }[];
+  topSeries: { series: string; downloads: number; bytes: number }[];
+  repeatedFailures: {
+    key: string;
+    failures: number;
+    lastError?: string;
+    lastPath?: string;
+    lastAttempt: number;
+    sinceSucceeded: boolean;
+  }[];
+}
+
+// Volume du dossier de téléchargement (fs_get_disk_space)
+export interface DiskSpace {
+  path: string;
+  total: number;
+  available: number;
+  used: number;
+}
+
+// Événement "download-low-space" : la file est en pause jusqu'à resumeDownloadQueue
+export interface LowDiskSpace extends DiskSpace {
+  id: string;
+  needed: number;
+}
+
+// Index persistant de la bibliothèque locale (library_index.json)
+export interface LibraryEntry {
+  path: string;
+  relativePath: string;
+  fileName: string;
+  folder: string;
+  series?: string | null;
+  volume?: number | null;
+  format: string; // CBZ, PDF...
+  size: number;
+  modifiedAt: number;
+  hash: string; // SHA-256
+  comicInfo?: ComicInfo | null; // ComicInfo.xml embarqué
+}
+
+// ComicInfo.xml (sous-ensemble lu par Komga / Kavita)
+export interface ComicInfo {
+  title?: string | null;
+  series?: string | null;
+  number?: string | null;
+  notes?: string | null;
+  year?: number | null;
+  web?: string | null;
+  pageCount?: number | null;
+  languageIso?: string | null;
+}
+
+// Ce qu'on sait d'un document VK au moment de le télécharger
+export interface ComicInfoSource {
+  series?: string;
+  title: string;
+  web?: string;
+  group?: string;
+}
+
+export interface LibraryQuery {
+  series?: string;
+  format?: string;
+  text?: string;
+  modifiedAfter?: number;
+  modifiedBefore?: number;
+  offset?: number;
+  limit?: number;
+}
+
+export interface LibrarySeries {
+  series: string;
+  files: number;
+  totalSize: number;
+  formats: string[];
+  volumes: number[];
+  lastModified: number;
+}
+
+export interface LibraryScanSummary {
+  root: string;
+  total: number;
+  added: number;
+  updated: number;
+  removed: number;
+  unchanged: number;
+  durationMs: number;
+}
+
+export interface ArchiveCheck {
+  path: string;
+  format: 'zip' | 'rar' | '7z' | null;
+  entries: number;
+  ok: boolean;
+  error: string | null;
+}
+
+export interface ConversionProgress {
+  id: string;
+  page: number;
+  total: number;
+  progress: number;
+}
+
+export interface ConversionResult {
+  id: string;
+  ok: boolean;
+  status: 'converted' | 'aborted' | 'failed';
+  path?: string;
+  pages?: number;
+  sourceDeleted?: boolean;
+  error?: string;
+}
+
+export interface ReadingState {
+  hash: string;
+  path: string;
+  lastPage: number;
+  totalPages: number;
+  completed: boolean;
+  startedAt: number;
+  updatedAt: number;
+  completedAt: number | null;
+}
+
+export interface FolderProgress {
+  directory: string;
+  total: number;
+  read: number;
+  inProgress: number;
+  unread: number;
+  lastRead: ReadingState | null;
+}
+
+export interface ReaderPage {
+  index: number;
+  name: string;
+  size: number;
+}
+
+export interface ReaderPageList {
+  path: string;
+  count: number;
+  pages: ReaderPage[];
+}
+
+export interface LibraryChange {
+  kind: 'created' | 'modified' | 'removed' | 'renamed' | 'rescanned';
+  path: string;
+  from: string | null;
 }